@@ -1,22 +1,46 @@
+use std::collections::HashMap;
+
 use serde_json::Value as JsonValue;
 
+/// Query parameters binding `$rows` to a batch, for `query(..).with_params(..)`.
+///
+/// FalkorDB receives parameters as a `CYPHER name=value` header in front of the query, so the
+/// value is still rendered as a literal, but the query text itself stays constant across
+/// batches and can be served from the plan cache.
+pub fn rows_param(rows: &JsonValue) -> HashMap<String, String> {
+    HashMap::from([("rows".to_string(), json_value_to_cypher_literal(rows))])
+}
+
+/// A self-contained statement binding `$rows` inline through a `CYPHER rows=...` header,
+/// rendered on a single line: line breaks inside string values are written as `\n`/`\r`
+/// escapes.
+pub fn inline_rows_statement(cypher: &str, rows: &JsonValue) -> String {
+    format!("CYPHER rows={} {}", render_literal(rows, true), cypher)
+}
+
 /// Convert serde_json::Value to a Cypher literal string.
 ///
-/// This is a minimal helper to inline JSON-like structures into Cypher queries
-/// (e.g. for UNWIND batches) without relying on driver-specific parameter
-/// support.
+/// Used to render parameter values (see `rows_param`), which FalkorDB parses with the same
+/// literal syntax as the query body.
 pub fn json_value_to_cypher_literal(value: &JsonValue) -> String {
+    render_literal(value, false)
+}
+
+fn render_literal(value: &JsonValue, one_line: bool) -> String {
     match value {
         JsonValue::Null => "null".to_string(),
         JsonValue::Bool(b) => b.to_string(),
         JsonValue::Number(n) => n.to_string(),
         JsonValue::String(s) => {
             // Escape backslashes and single quotes, then wrap in single quotes.
-            let escaped = s.replace("\\", "\\\\").replace("'", "\\'");
+            let mut escaped = s.replace("\\", "\\\\").replace("'", "\\'");
+            if one_line {
+                escaped = escaped.replace('\n', "\\n").replace('\r', "\\r");
+            }
             format!("'{}'", escaped)
         }
         JsonValue::Array(arr) => {
-            let items: Vec<String> = arr.iter().map(json_value_to_cypher_literal).collect();
+            let items: Vec<String> = arr.iter().map(|v| render_literal(v, one_line)).collect();
             format!("[{}]", items.join(", "))
         }
         JsonValue::Object(map) => {
@@ -25,10 +49,38 @@ pub fn json_value_to_cypher_literal(value: &JsonValue) -> String {
                 .map(|(k, v)| {
                     // Escape backticks in keys by doubling them, then wrap in backticks
                     let escaped_key = k.replace("`", "``");
-                    format!("`{}`: {}", escaped_key, json_value_to_cypher_literal(v))
+                    format!("`{}`: {}", escaped_key, render_literal(v, one_line))
                 })
                 .collect();
             format!("{{{}}}", items.join(", "))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn string_literals_escape_quotes_and_backslashes() {
+        assert_eq!(
+            json_value_to_cypher_literal(&json!("O'Brien\\x")),
+            "'O\\'Brien\\\\x'"
+        );
+        assert_eq!(rows_param(&json!([{ "key": 1 }]))["rows"], "[{`key`: 1}]");
+    }
+
+    #[test]
+    fn inline_statements_stay_on_one_line() {
+        let stmt = inline_rows_statement(
+            "UNWIND $rows AS row RETURN row",
+            &json!([{ "note": "a\nb" }]),
+        );
+        assert_eq!(
+            stmt,
+            "CYPHER rows=[{`note`: 'a\\nb'}] UNWIND $rows AS row RETURN row"
+        );
+        assert!(!stmt.contains('\n'));
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde_json::{json, Value as JsonValue};
+
+use crate::config::{DeadLetterConfig, DeadLetterMode};
+use crate::mapping::SkippedRow;
+
+/// Prepare the dead-letter file at the start of a run (truncating it in `truncate` mode).
+pub fn begin_run(cfg: &DeadLetterConfig) -> Result<()> {
+    if cfg.mode == DeadLetterMode::Truncate {
+        File::create(&cfg.path)
+            .with_context(|| format!("Failed to truncate dead-letter file {}", cfg.path))?;
+    }
+    Ok(())
+}
+
+/// Append skipped rows to the dead-letter file, one JSON object per line:
+/// `{"mapping": ..., "reason": ..., "recorded_at": ..., "row": {...}}`.
+pub fn append_skipped_rows(
+    cfg: &DeadLetterConfig,
+    mapping: &str,
+    skipped: &[SkippedRow],
+) -> Result<()> {
+    if skipped.is_empty() {
+        return Ok(());
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&cfg.path)
+        .with_context(|| format!("Failed to open dead-letter file {}", cfg.path))?;
+
+    let recorded_at = Utc::now().to_rfc3339();
+    let mut buf = String::new();
+    for s in skipped {
+        let entry = json!({
+            "mapping": mapping,
+            "reason": s.reason,
+            "recorded_at": recorded_at,
+            "row": JsonValue::Object(s.row.values.clone()),
+        });
+        buf.push_str(&serde_json::to_string(&entry)?);
+        buf.push('\n');
+    }
+
+    file.write_all(buf.as_bytes())
+        .with_context(|| format!("Failed to write dead-letter file {}", cfg.path))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::LogicalRow;
+
+    #[test]
+    fn skipped_rows_are_appended_as_json_lines() -> Result<()> {
+        let path = std::env::temp_dir().join("snowflake_to_falkordb_dead_letter_test.jsonl");
+        let cfg = DeadLetterConfig {
+            path: path.to_string_lossy().to_string(),
+            mode: DeadLetterMode::Truncate,
+        };
+
+        let row = LogicalRow {
+            values: json!({ "NAME": "no key" }).as_object().cloned().unwrap(),
+        };
+        let skipped = vec![SkippedRow {
+            row,
+            reason: "Row 0 is missing key column 'ID'".to_string(),
+        }];
+
+        begin_run(&cfg)?;
+        append_skipped_rows(&cfg, "customers", &skipped)?;
+        append_skipped_rows(&cfg, "customers", &skipped)?;
+
+        let contents = std::fs::read_to_string(&path)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let entry: JsonValue = serde_json::from_str(lines[0])?;
+        assert_eq!(entry["mapping"], "customers");
+        assert_eq!(entry["reason"], "Row 0 is missing key column 'ID'");
+        assert_eq!(entry["row"]["NAME"], "no key");
+
+        // Truncate mode starts the next run from an empty file.
+        begin_run(&cfg)?;
+        assert!(std::fs::read_to_string(&path)?.is_empty());
+        Ok(())
+    }
+}
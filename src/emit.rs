@@ -0,0 +1,291 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use falkordb::AsyncGraph;
+use serde_json::Value as JsonValue;
+
+use crate::config::Config;
+use crate::cypher::inline_rows_statement;
+use crate::sink_async::{connect_falkordb_async, with_query_timeout, Backoff, WriteOptions};
+
+/// Name replay retries are counted under in the per-mapping retry metrics.
+const REPLAY_MAPPING: &str = "replay";
+
+/// Log replay progress every this many statements.
+const REPLAY_PROGRESS_EVERY: u64 = 1000;
+
+/// Parameter rows shown per statement in `--dry-run` logs.
+const DRY_RUN_SAMPLE_ROWS: usize = 3;
+
+/// Receives the statements a load would execute, in order, instead of FalkorDB.
+pub trait StatementSink {
+    /// Take one statement. `rows` is the `$rows` parameter, if the statement has one.
+    fn emit(&mut self, cypher: &str, rows: Option<&JsonValue>) -> Result<()>;
+}
+
+/// Writes generated Cypher to a file instead of executing it (`--emit-cypher`), one
+/// self-contained statement per line.
+pub struct CypherEmitter<W: Write> {
+    out: W,
+    statements: u64,
+}
+
+impl CypherEmitter<BufWriter<File>> {
+    /// Create (or truncate) the output file.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create Cypher file {}", path.display()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> CypherEmitter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, statements: 0 }
+    }
+
+    /// Flush the output and return the number of statements written.
+    pub fn finish(mut self) -> Result<u64> {
+        self.out.flush().context("Failed to flush Cypher file")?;
+        Ok(self.statements)
+    }
+}
+
+impl<W: Write> StatementSink for CypherEmitter<W> {
+    /// Append one statement. When `rows` is given it is bound inline as `$rows`.
+    fn emit(&mut self, cypher: &str, rows: Option<&JsonValue>) -> Result<()> {
+        let line = match rows {
+            Some(rows) => inline_rows_statement(cypher, rows),
+            None => cypher.to_string(),
+        };
+        writeln!(self.out, "{}", line).context("Failed to write Cypher statement")?;
+        self.statements += 1;
+        Ok(())
+    }
+}
+
+/// Logs generated statements at info level instead of executing them (`--dry-run`), with
+/// the first few parameter rows as a sample.
+#[derive(Debug, Default)]
+pub struct DryRunLog {
+    pub statements: u64,
+    /// Parameter rows across all statements.
+    pub rows: u64,
+}
+
+impl StatementSink for DryRunLog {
+    fn emit(&mut self, cypher: &str, rows: Option<&JsonValue>) -> Result<()> {
+        self.statements += 1;
+        match rows.and_then(JsonValue::as_array) {
+            Some(rows) => {
+                self.rows += rows.len() as u64;
+                let sample =
+                    JsonValue::Array(rows.iter().take(DRY_RUN_SAMPLE_ROWS).cloned().collect());
+                tracing::info!(
+                    statement = self.statements,
+                    rows = rows.len(),
+                    sample = %sample,
+                    "Dry run: {}",
+                    cypher,
+                );
+            }
+            None => tracing::info!(statement = self.statements, "Dry run: {}", cypher),
+        }
+        Ok(())
+    }
+}
+
+/// Counts from a `--replay` run.
+#[derive(Debug, Default)]
+pub struct ReplayStats {
+    /// Statements sent to FalkorDB, including index statements that failed.
+    pub executed: u64,
+    /// `CREATE INDEX` statements that failed, usually because the index already exists.
+    pub index_failures: u64,
+    pub elapsed: Duration,
+}
+
+/// Execute a file written by `--emit-cypher` against the configured graph, one statement per
+/// line. The file is read line by line rather than loaded whole. Statements are retried with
+/// the batch writers' backoff; a statement that still fails aborts the replay with its line
+/// number. Failed `CREATE INDEX` statements are logged and skipped.
+pub async fn replay_cypher(cfg: &Config, path: &Path) -> Result<ReplayStats> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open Cypher file {}", path.display()))?;
+    let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+    let write = WriteOptions::from_config(&cfg.falkordb);
+    let mut stats = ReplayStats::default();
+    let started = Instant::now();
+
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line_no = idx + 1;
+        let line =
+            line.with_context(|| format!("Failed to read {} line {}", path.display(), line_no))?;
+        let Some(statement) = replay_statement(&line) else {
+            continue;
+        };
+
+        if statement.starts_with("CREATE INDEX") {
+            let created = with_query_timeout(write.query_timeout, async {
+                graph
+                    .query(statement)
+                    .execute()
+                    .await
+                    .map_err(anyhow::Error::from)
+            })
+            .await;
+            if let Err(e) = created {
+                tracing::warn!(
+                    line = line_no,
+                    error = %e,
+                    "Index statement failed (the index may already exist)",
+                );
+                stats.index_failures += 1;
+            }
+        } else {
+            execute_with_retry(&mut graph, statement, &write)
+                .await
+                .with_context(|| format!("Statement on line {} failed", line_no))?;
+        }
+
+        stats.executed += 1;
+        if stats.executed % REPLAY_PROGRESS_EVERY == 0 {
+            tracing::info!(
+                statements = stats.executed,
+                elapsed_secs = started.elapsed().as_secs_f64(),
+                "Replay progress",
+            );
+        }
+    }
+
+    stats.elapsed = started.elapsed();
+    Ok(stats)
+}
+
+/// The statement on a replay file line, without a trailing `;`, or `None` for a blank line.
+fn replay_statement(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let statement = line.strip_suffix(';').unwrap_or(line).trim_end();
+    (!statement.is_empty()).then_some(statement)
+}
+
+/// Execute one replayed statement the way batch writes are sent: each attempt is limited by
+/// `query_timeout_ms` and failures are retried with the batch backoff.
+async fn execute_with_retry(
+    graph: &mut AsyncGraph,
+    statement: &str,
+    opts: &WriteOptions,
+) -> Result<()> {
+    let mut backoff = Backoff::for_mapping(opts.retry, REPLAY_MAPPING);
+    while let Err(e) = with_query_timeout(opts.query_timeout, async {
+        graph
+            .query(statement)
+            .execute()
+            .await
+            .map_err(anyhow::Error::from)
+    })
+    .await
+    {
+        backoff.after_failure(e).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn statements_are_written_one_per_line() -> Result<()> {
+        let mut buf = Vec::new();
+        let mut emitter = CypherEmitter::new(&mut buf);
+        emitter.emit("CREATE INDEX ON :Customer(id)", None)?;
+        emitter.emit(
+            "UNWIND $rows AS row MERGE (n:Customer { id: row.key })",
+            Some(&json!([{ "key": "multi\nline" }])),
+        )?;
+        assert_eq!(emitter.finish()?, 2);
+
+        let text = String::from_utf8(buf)?;
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "CREATE INDEX ON :Customer(id)");
+        assert!(lines[1].starts_with("CYPHER rows=[{`key`: 'multi\\nline'}] UNWIND $rows"));
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_counts_statements_and_rows() -> Result<()> {
+        let mut log = DryRunLog::default();
+        log.emit("CREATE INDEX ON :Customer(id)", None)?;
+        log.emit(
+            "UNWIND $rows AS row MERGE (n:Customer { id: row.key })",
+            Some(&json!([{ "key": 1 }, { "key": 2 }, { "key": 3 }, { "key": 4 }])),
+        )?;
+        assert_eq!(log.statements, 2);
+        assert_eq!(log.rows, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn replay_skips_blank_lines_and_trailing_semicolons() {
+        assert_eq!(replay_statement("   "), None);
+        assert_eq!(replay_statement(";"), None);
+        assert_eq!(
+            replay_statement("CREATE INDEX ON :Customer(id);\r"),
+            Some("CREATE INDEX ON :Customer(id)")
+        );
+        assert_eq!(
+            replay_statement("CYPHER rows=[] UNWIND $rows AS row RETURN row"),
+            Some("CYPHER rows=[] UNWIND $rows AS row RETURN row")
+        );
+    }
+
+    /// Optional replay round trip against a live FalkorDB; a no-op unless FALKORDB_ENDPOINT
+    /// is set.
+    #[tokio::test]
+    async fn emitted_statements_replay_into_falkordb() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+        let cfg = Config::from_str(
+            &format!(
+                r#"{{ "falkordb": {{ "endpoint": "{}", "graph": "snowflake_to_falkordb_replay_test" }}, "mappings": [] }}"#,
+                endpoint
+            ),
+            None,
+        )?;
+
+        let path = std::env::temp_dir().join("snowflake_to_falkordb_replay.cypher");
+        let mut emitter = CypherEmitter::create(&path)?;
+        emitter.emit("CREATE INDEX ON :ReplayNode(id)", None)?;
+        emitter.emit(
+            "UNWIND $rows AS row MERGE (n:ReplayNode { id: row.key }) SET n.note = row.note",
+            Some(&json!([{ "key": 1, "note": "two\nlines" }, { "key": 2, "note": "O'Brien" }])),
+        )?;
+        emitter.emit("CREATE INDEX ON :ReplayNode(id)", None)?;
+        emitter.finish()?;
+
+        let stats = replay_cypher(&cfg, &path).await?;
+        assert_eq!(stats.executed, 3);
+        assert!(stats.index_failures >= 1);
+
+        let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+        let count = crate::sink_async::query_count(
+            &mut graph,
+            "MATCH (n:ReplayNode) WHERE n.note IN ['two\\nlines', 'O\\'Brien'] RETURN count(n)",
+        )
+        .await?;
+        graph
+            .query("MATCH (n:ReplayNode) DELETE n")
+            .execute()
+            .await?;
+        assert_eq!(count, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! Load tabular data from Snowflake, local files or S3 into FalkorDB via UNWIND+MERGE.
+//!
+//! The `snowflake-to-falkordb` binary is a thin CLI over this library, so a Rust service can
+//! embed the same loader: build a [`Config`] (parsed with [`Config::from_str`] /
+//! [`Config::from_file`], or constructed in code), then call [`run_once`] for a single sync or
+//! [`run_daemon`] for periodic ones.
+//!
+//! ```no_run
+//! use snowflake_to_falkordb::{
+//!     run_once, validate_config, CommonMappingFields, Config, EntityMapping, FalkorConfig,
+//!     NodeKeySpec, NodeMappingConfig, RunOptions, SourceConfig,
+//! };
+//!
+//! # async fn load() -> anyhow::Result<()> {
+//! let cfg = Config {
+//!     falkordb: FalkorConfig {
+//!         endpoint: "falkor://127.0.0.1:6379".to_string(),
+//!         graph: "customer_graph".to_string(),
+//!         ..Default::default()
+//!     },
+//!     mappings: vec![EntityMapping::Node(NodeMappingConfig {
+//!         common: CommonMappingFields {
+//!             name: "customers".to_string(),
+//!             source: SourceConfig {
+//!                 file: Some("customers.json".to_string()),
+//!                 ..Default::default()
+//!             },
+//!             ..Default::default()
+//!         },
+//!         labels: vec!["Customer".to_string()],
+//!         key: NodeKeySpec {
+//!             column: "ID".to_string(),
+//!             property: "id".to_string(),
+//!             ..Default::default()
+//!         }
+//!         .into(),
+//!         ..Default::default()
+//!     })],
+//!     ..Default::default()
+//! };
+//! validate_config(&cfg)?;
+//!
+//! let summary = run_once(&cfg, &RunOptions::default()).await?.into_result()?;
+//! println!("wrote {} row(s)", summary.rows_written);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The modules are public for callers that need more than the re-exports below, but only the
+//! re-exported items are meant as the stable API.
+
+pub mod config;
+pub mod cypher;
+pub mod dead_letter;
+pub mod emit;
+pub mod mapping;
+pub mod metrics;
+pub mod orchestrator;
+pub mod s3;
+pub mod sink;
+pub mod sink_async;
+pub mod source;
+pub mod state;
+pub mod transform;
+
+pub use crate::config::{
+    CombinedMappingConfig, CommonMappingFields, Config, ConfigFormat, EdgeMappingConfig,
+    EntityMapping, FalkorConfig, FalkorTlsConfig, IndexConfig, NodeKey, NodeKeySpec,
+    NodeMappingConfig, SnowflakeConfig, SourceConfig, StateBackendKind, StateConfig,
+    TransformConfig,
+};
+pub use crate::emit::DryRunLog;
+pub use crate::mapping::{map_rows_to_combined, map_rows_to_edges, map_rows_to_nodes};
+pub use crate::orchestrator::{
+    dry_run, lint_config, run_daemon, run_once, run_once_with_graph, shutdown_on_signal,
+    validate_config, MappingSummary, RunOptions, RunSummary,
+};
+pub use crate::sink::MappedNode;
+pub use crate::sink_async::{connect_falkordb_async, FalkorConnection, MappedCombined, MappedEdge};
+pub use crate::source::LogicalRow;
+pub use crate::transform::{register_transform, RowTransform, TransformFactory};
@@ -1,32 +1,36 @@
-mod config;
-mod cypher;
-mod mapping;
-mod metrics;
-mod orchestrator;
-mod sink;
-mod sink_async;
-mod source;
-mod state;
-
 use std::path::PathBuf;
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
-use crate::config::Config;
-use crate::metrics::serve_metrics;
-use crate::orchestrator::{run_daemon, run_once};
+use snowflake_to_falkordb::emit::replay_cypher;
+use snowflake_to_falkordb::metrics::serve_metrics;
+use snowflake_to_falkordb::orchestrator::{drop_node_indexes, emit_cypher, verify_schema};
+use snowflake_to_falkordb::state::{migrate_state, StateLocation};
+use snowflake_to_falkordb::{
+    dry_run, lint_config, run_daemon, run_once, shutdown_on_signal, validate_config, Config,
+    ConfigFormat, RunOptions,
+};
 
 /// CLI for phase 2+: supports multi-mapping, async writes, purge, and daemon mode.
 #[derive(Debug, Parser)]
 #[command(name = "snowflake-to-falkordb")]
 #[command(about = "Load tabular/Snowflake data into FalkorDB via UNWIND+MERGE", long_about = None)]
 struct Cli {
-    /// Path to JSON or YAML config file.
-    #[arg(long, value_name = "PATH")]
-    config: PathBuf,
+    /// Path to a JSON, YAML or TOML config file; the format follows the extension.
+    #[arg(long, value_name = "PATH", required_unless_present = "config_inline")]
+    config: Option<PathBuf>,
+
+    /// Config document passed directly on the command line (JSON, YAML, or TOML with
+    /// --config-format toml).
+    #[arg(long, value_name = "CONFIG", conflicts_with = "config")]
+    config_inline: Option<String>,
+
+    /// Format of --config-inline; auto-detected (JSON, then YAML) when omitted.
+    #[arg(long, value_name = "json|yaml|toml", requires = "config_inline")]
+    config_format: Option<ConfigFormat>,
 
     /// Purge the entire graph before loading.
     #[arg(long)]
@@ -36,6 +40,25 @@ struct Cli {
     #[arg(long, value_name = "MAPPING_NAME")]
     purge_mapping: Vec<String>,
 
+    /// With --purge-mapping on an edge mapping, also delete the endpoint nodes the purge
+    /// leaves without any relationship. Nodes still connected to anything are kept.
+    #[arg(long, requires = "purge_mapping")]
+    purge_orphans: bool,
+
+    /// Run only this mapping (can be repeated). Other mappings are left untouched.
+    #[arg(long, value_name = "MAPPING_NAME")]
+    only_mapping: Vec<String>,
+
+    /// Leave this mapping out of the run (can be repeated).
+    #[arg(long, value_name = "MAPPING_NAME")]
+    skip_mapping: Vec<String>,
+
+    /// Fetch this mapping from the given watermark instead of its stored one, for this run
+    /// only, e.g. `orders=2024-01-15` to backfill everything changed since then (can be
+    /// repeated). The stored watermark moves only when the run advances it as usual.
+    #[arg(long, value_name = "NAME=VALUE", conflicts_with = "daemon")]
+    from_watermark: Vec<String>,
+
     /// Run continuously, performing syncs at a fixed interval.
     #[arg(long)]
     daemon: bool,
@@ -43,39 +66,247 @@ struct Cli {
     /// Interval in seconds between sync runs in daemon mode.
     #[arg(long, value_name = "SECS", default_value_t = 60)]
     interval_secs: u64,
+
+    /// In daemon mode, exit after this many sync runs (successful or failed).
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "daemon",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    max_runs: Option<u64>,
+
+    /// Drop the node key indexes the loader creates for this config and exit.
+    #[arg(long, conflicts_with_all = ["migrate_state", "daemon"])]
+    drop_indexes: bool,
+
+    /// Before loading, check that the graph has the labels and key indexes the config
+    /// expects; aborts or warns per `falkordb.verify_schema_action`.
+    #[arg(long)]
+    verify_schema: bool,
+
+    /// Copy watermarks from `--from` to `--to` and exit without loading any data.
+    #[arg(long, requires_all = ["migrate_from", "migrate_to"])]
+    migrate_state: bool,
+
+    /// Source state store for --migrate-state: `file:<path>`, `falkordb` or a `redis://` URL.
+    #[arg(long = "from", value_name = "STATE", requires = "migrate_state")]
+    migrate_from: Option<StateLocation>,
+
+    /// Target state store for --migrate-state: `file:<path>`, `falkordb` or a `redis://` URL.
+    #[arg(long = "to", value_name = "STATE", requires = "migrate_state")]
+    migrate_to: Option<StateLocation>,
+
+    /// Fetch and map every mapping, then write the generated Cypher (index creation and
+    /// UNWIND batches) to this file instead of executing it. Does not connect to FalkorDB.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "daemon",
+            "purge_graph",
+            "purge_mapping",
+            "drop_indexes",
+            "verify_schema",
+            "migrate_state",
+        ]
+    )]
+    emit_cypher: Option<PathBuf>,
+
+    /// Execute a file written by --emit-cypher against the configured graph, one statement
+    /// per line, and exit.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "emit_cypher",
+            "daemon",
+            "purge_graph",
+            "purge_mapping",
+            "drop_indexes",
+            "verify_schema",
+            "migrate_state",
+        ]
+    )]
+    replay: Option<PathBuf>,
+
+    /// Fetch and map every mapping from its current watermark and log the Cypher a load
+    /// would execute, with a sample of each batch's rows, without writing to FalkorDB.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "emit_cypher",
+            "replay",
+            "daemon",
+            "purge_graph",
+            "purge_mapping",
+            "drop_indexes",
+            "migrate_state",
+        ]
+    )]
+    dry_run: bool,
+
+    /// Print a JSON summary of the run (rows fetched, written and deleted per mapping, and
+    /// failed mappings) to stdout instead of the completion message.
+    #[arg(long, conflicts_with = "daemon")]
+    json_summary: bool,
+
+    /// Keep running the remaining mappings after one fails. The run still exits non-zero.
+    /// Daemon mode uses the config's `continue_on_error` instead.
+    #[arg(long, conflicts_with = "daemon")]
+    continue_on_error: bool,
+
+    /// Log output format: human-readable text, or one JSON object per line with tracing
+    /// fields kept as attributes.
+    #[arg(long, value_enum, env = "LOG_FORMAT", default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check the config and exit, without connecting to Snowflake or FalkorDB: parse it, run
+    /// the validation checks and make sure every `source.file` exists. Lists every problem
+    /// and exits non-zero if there are any.
+    Validate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_tracing();
-
     let cli = Cli::parse();
-    let cfg = Config::from_file(&cli.config)?;
+    let log_format = cli.log_format;
+    init_tracing(log_format);
+
+    let result = run(cli).await;
+    if let (Err(e), LogFormat::Json) = (&result, log_format) {
+        // Report the failure as a log line too, so it reaches the aggregator as JSON.
+        tracing::error!(error = %format!("{:#}", e), "Run failed");
+        std::process::exit(1);
+    }
+    result
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let cfg = match &cli.config_inline {
+        Some(inline) => Config::from_str(inline, cli.config_format)?,
+        None => Config::from_file(cli.config.as_ref().context("--config is required")?)?,
+    };
+
+    if let Some(Command::Validate) = cli.command {
+        lint_config(&cfg)?;
+        println!("Config is valid ({} mapping(s)).", cfg.mappings.len());
+        return Ok(());
+    }
+
+    validate_config(&cfg)?;
+    let mut opts = RunOptions {
+        purge_graph: cli.purge_graph,
+        purge_mappings: cli.purge_mapping,
+        purge_orphaned_endpoints: cli.purge_orphans,
+        continue_on_error: cli.continue_on_error,
+        ..Default::default()
+    };
+    opts.select_mappings(&cfg, &cli.only_mapping, &cli.skip_mapping)?;
+    opts.override_watermarks(&cfg, &cli.from_watermark)?;
+
+    if cli.migrate_state {
+        if let (Some(from), Some(to)) = (&cli.migrate_from, &cli.migrate_to) {
+            let migrated = migrate_state(&cfg, from, to).await?;
+            println!(
+                "Migrated {} watermark(s) from {:?} to {:?}.",
+                migrated, from, to
+            );
+            return Ok(());
+        }
+    }
+
+    if let Some(path) = &cli.emit_cypher {
+        let written = emit_cypher(&cfg, &opts, path).await?;
+        println!("Wrote {} statement(s) to {}.", written, path.display());
+        return Ok(());
+    }
+
+    if let Some(path) = &cli.replay {
+        let stats = replay_cypher(&cfg, path).await?;
+        println!(
+            "Replayed {} statement(s) from {} in {:.1}s ({} index statement(s) failed).",
+            stats.executed,
+            path.display(),
+            stats.elapsed.as_secs_f64(),
+            stats.index_failures
+        );
+        return Ok(());
+    }
+
+    if cli.dry_run {
+        let log = dry_run(&cfg, &opts).await?;
+        println!(
+            "Dry run: {} statement(s) covering {} row(s) would be executed; nothing was written.",
+            log.statements, log.rows
+        );
+        return Ok(());
+    }
+
+    if cli.drop_indexes {
+        let dropped = drop_node_indexes(&cfg).await?;
+        println!("Dropped {} index(es).", dropped);
+        return Ok(());
+    }
+
+    if cli.verify_schema {
+        verify_schema(&cfg).await?;
+    }
 
     // Start metrics server on 0.0.0.0:9898
-    tokio::spawn(async {
+    let metrics_token = cfg.metrics.as_ref().and_then(|m| m.auth_token.clone());
+    tokio::spawn(async move {
         let addr = ([0, 0, 0, 0], 9898).into();
-        serve_metrics(addr).await;
+        serve_metrics(addr, metrics_token).await;
     });
 
     if cli.daemon {
-        run_daemon(&cfg, cli.purge_graph, &cli.purge_mapping, cli.interval_secs).await?;
+        run_daemon(
+            &cfg,
+            &opts,
+            cli.interval_secs,
+            cli.max_runs,
+            shutdown_on_signal(),
+        )
+        .await?;
     } else {
-        run_once(&cfg, cli.purge_graph, &cli.purge_mapping).await?;
+        let summary = run_once(&cfg, &opts).await?;
+        if cli.json_summary {
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        }
+        summary.into_result()?;
+        if cli.json_summary {
+            return Ok(());
+        }
     }
 
     println!("Load completed successfully.");
     Ok(())
 }
 
-fn init_tracing() {
-    let fmt_layer = tracing_subscriber::fmt::layer().with_target(false);
+fn init_tracing(format: LogFormat) {
+    let json = format == LogFormat::Json;
+    let text_layer = (!json).then(|| tracing_subscriber::fmt::layer().with_target(false));
+    let json_layer = json.then(|| tracing_subscriber::fmt::layer().json().with_target(false));
     let filter = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new("info"))
         .unwrap();
 
     tracing_subscriber::registry()
         .with(filter)
-        .with(fmt_layer)
+        .with(text_layer)
+        .with(json_layer)
         .init();
 }
@@ -1,9 +1,14 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
 use serde_json::{Map as JsonMap, Value as JsonValue};
 
-use crate::config::{EdgeMappingConfig, MatchOn, NodeMappingConfig};
+use crate::config::{
+    CombinedMappingConfig, CombinedNodeSpec, CommonMappingFields, DedupeKeep, EdgeDedupeSpec,
+    EdgeEndpointMatch, EdgeKeySpec, EdgeMappingConfig, KeyNormalize, KeyType, MatchOn, NodeKey,
+    NodeKeySpec, NodeMappingConfig, OversizeAction, PropertySpec, PropertyType, WhenCondition,
+};
 use crate::sink::MappedNode;
-use crate::sink_async::MappedEdge;
+use crate::sink_async::{MappedCombined, MappedEdge};
 use crate::source::LogicalRow;
 
 /// Neo4j/FalkorDB only allow property values that are primitives or arrays of primitives.
@@ -36,101 +41,1698 @@ fn normalise_property_value(value: JsonValue) -> JsonValue {
     }
 }
 
-/// Map tabular rows to FalkorDB nodes according to a NodeMappingConfig.
+/// Apply an optional key normalization. Only string values are affected.
+fn normalise_key_value(value: JsonValue, normalize: Option<KeyNormalize>) -> JsonValue {
+    match (value, normalize) {
+        (JsonValue::String(s), Some(n)) => JsonValue::String(n.apply(&s)),
+        (v, _) => v,
+    }
+}
+
+/// Coerce a key value to the configured key type. Nulls are left untouched.
+fn coerce_key_value(
+    value: JsonValue,
+    key_type: Option<KeyType>,
+    column: &str,
+) -> Result<JsonValue> {
+    let Some(key_type) = key_type else {
+        return Ok(value);
+    };
+
+    match (key_type, value) {
+        (_, JsonValue::Null) => Ok(JsonValue::Null),
+        (KeyType::String, JsonValue::String(s)) => Ok(JsonValue::String(s)),
+        (KeyType::String, v @ (JsonValue::Number(_) | JsonValue::Bool(_))) => {
+            Ok(JsonValue::String(v.to_string()))
+        }
+        (KeyType::Int, JsonValue::Number(n)) => {
+            if let Some(i) = n.as_i64() {
+                Ok(JsonValue::from(i))
+            } else {
+                match n.as_f64() {
+                    Some(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => {
+                        Ok(JsonValue::from(f as i64))
+                    }
+                    _ => Err(anyhow!(
+                        "Key column '{}' value {} is not an integer",
+                        column,
+                        n
+                    )),
+                }
+            }
+        }
+        (KeyType::Int, JsonValue::String(s)) => s
+            .trim()
+            .parse::<i64>()
+            .map(JsonValue::from)
+            .map_err(|_| anyhow!("Key column '{}' value '{}' is not an integer", column, s)),
+        (_, v) => Err(anyhow!(
+            "Key column '{}' value {} cannot be coerced to {:?}",
+            column,
+            v,
+            key_type
+        )),
+    }
+}
+
+/// Coerce a property value to the spec's `type`, if any. Nulls stay null.
+fn coerce_property_value(
+    value: JsonValue,
+    ty: Option<PropertyType>,
+    column: &str,
+) -> Result<JsonValue> {
+    let Some(ty) = ty else {
+        return Ok(value);
+    };
+    let invalid = |v: &JsonValue| {
+        anyhow!(
+            "Column '{}' value {} cannot be coerced to {:?}",
+            column,
+            v,
+            ty
+        )
+    };
+
+    match (ty, value) {
+        (_, JsonValue::Null) => Ok(JsonValue::Null),
+        (PropertyType::Int, JsonValue::Number(n)) => match n.as_i64() {
+            Some(i) => Ok(JsonValue::from(i)),
+            None => match n.as_f64() {
+                Some(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => {
+                    Ok(JsonValue::from(f as i64))
+                }
+                _ => Err(invalid(&JsonValue::Number(n))),
+            },
+        },
+        (PropertyType::Int, JsonValue::String(s)) => s
+            .trim()
+            .parse::<i64>()
+            .map(JsonValue::from)
+            .map_err(|_| invalid(&JsonValue::String(s))),
+        (PropertyType::Float, JsonValue::Number(n)) => Ok(JsonValue::Number(n)),
+        (PropertyType::Float, JsonValue::String(s)) => match s.trim().parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(JsonValue::from(f)),
+            _ => Err(invalid(&JsonValue::String(s))),
+        },
+        (PropertyType::Bool, JsonValue::Bool(b)) => Ok(JsonValue::Bool(b)),
+        (PropertyType::Bool, JsonValue::String(s)) => {
+            match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "1" => Ok(JsonValue::Bool(true)),
+                "false" | "f" | "no" | "n" | "0" => Ok(JsonValue::Bool(false)),
+                _ => Err(invalid(&JsonValue::String(s))),
+            }
+        }
+        (PropertyType::Bool, JsonValue::Number(n)) => match n.as_i64() {
+            Some(0) => Ok(JsonValue::Bool(false)),
+            Some(1) => Ok(JsonValue::Bool(true)),
+            _ => Err(invalid(&JsonValue::Number(n))),
+        },
+        (PropertyType::String, JsonValue::String(s)) => Ok(JsonValue::String(s)),
+        (PropertyType::String, v @ (JsonValue::Number(_) | JsonValue::Bool(_))) => {
+            Ok(JsonValue::String(v.to_string()))
+        }
+        (PropertyType::Timestamp, JsonValue::String(s)) => match parse_timestamp(s.trim()) {
+            Some(ts) => Ok(JsonValue::String(
+                ts.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            )),
+            None => Err(invalid(&JsonValue::String(s))),
+        },
+        (PropertyType::Timestamp, JsonValue::Number(n)) => {
+            // Epoch seconds, possibly fractional.
+            match n
+                .as_f64()
+                .and_then(|secs| DateTime::from_timestamp_millis((secs * 1000.0).round() as i64))
+            {
+                Some(ts) => Ok(JsonValue::String(
+                    ts.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                )),
+                None => Err(invalid(&JsonValue::Number(n))),
+            }
+        }
+        (_, v) => Err(invalid(&v)),
+    }
+}
+
+/// Parse an RFC3339 timestamp, or a zone-less `YYYY-MM-DD[ T]HH:MM:SS[.f]` taken as UTC.
+pub(crate) fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(s) {
+        return Some(ts.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .map(|naive| naive.and_utc())
+}
+
+/// Enforce `max_property_bytes` on a string property value, truncating (on a UTF-8
+/// character boundary) or rejecting it according to `oversize_property`.
+fn enforce_property_limit(
+    value: JsonValue,
+    common: &CommonMappingFields,
+    prop_name: &str,
+) -> Result<JsonValue> {
+    let Some(max_bytes) = common.max_property_bytes else {
+        return Ok(value);
+    };
+    let JsonValue::String(s) = value else {
+        return Ok(value);
+    };
+    if s.len() <= max_bytes {
+        return Ok(JsonValue::String(s));
+    }
+
+    match common.oversize_property.unwrap_or_default() {
+        OversizeAction::Truncate => {
+            let mut end = max_bytes;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            tracing::warn!(
+                mapping = %common.name,
+                property = %prop_name,
+                bytes = s.len(),
+                max_bytes,
+                "Truncating oversized property value",
+            );
+            Ok(JsonValue::String(s[..end].to_string()))
+        }
+        OversizeAction::Reject => Err(anyhow!(
+            "Property '{}' is {} bytes, exceeding max_property_bytes {}",
+            prop_name,
+            s.len(),
+            max_bytes
+        )),
+    }
+}
+
+/// Read the row's `changed_columns` mask. `None` (no mask configured, or a null/missing
+/// value) means every property is written.
+fn changed_column_mask(row: &LogicalRow, common: &CommonMappingFields) -> Option<Vec<String>> {
+    let column = common.changed_columns.as_ref()?;
+    match row.get(column)? {
+        JsonValue::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.trim().to_string()))
+                .collect(),
+        ),
+        JsonValue::String(csv) => Some(
+            csv.split(',')
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Whether a property mapped from `column` should be written under `mask`. Column names are
+/// compared case-insensitively, as Snowflake identifiers usually are.
+fn column_in_mask(mask: Option<&[String]>, column: &str) -> bool {
+    mask.is_none_or(|cols| cols.iter().any(|c| c.eq_ignore_ascii_case(column)))
+}
+
+/// Whether a row satisfies a mapping's `when` condition. A missing column never matches.
+pub fn row_matches_when(row: &LogicalRow, when: &WhenCondition) -> bool {
+    row.get(&when.column) == Some(&when.equals)
+}
+
+/// A source row that could not be mapped, together with the reason it was dropped.
+#[derive(Debug, Clone)]
+pub struct SkippedRow {
+    pub row: LogicalRow,
+    pub reason: String,
+}
+
+/// Read, coerce and normalise a node key value from `row`.
+fn node_key_value(
+    idx: usize,
+    row: &LogicalRow,
+    key: &NodeKeySpec,
+    normalize: Option<KeyNormalize>,
+) -> Result<JsonValue> {
+    let key_raw = row
+        .get(&key.column)
+        .cloned()
+        .ok_or_else(|| anyhow!("Row {} is missing key column '{}'", idx, key.column))?;
+    let key_value = coerce_key_value(normalise_property_value(key_raw), key.key_type, &key.column)
+        .map_err(|e| anyhow!("Row {}: {}", idx, e))?;
+    Ok(normalise_key_value(key_value, normalize))
+}
+
+/// Read a node mapping's key from `row`: the bare value for a single key, or an object of
+/// key property -> value for a composite key.
+fn node_key(
+    idx: usize,
+    row: &LogicalRow,
+    key: &NodeKey,
+    normalize: Option<KeyNormalize>,
+) -> Result<JsonValue> {
+    match key {
+        NodeKey::Single(spec) => node_key_value(idx, row, spec, normalize),
+        NodeKey::Composite(specs) => {
+            let mut parts = JsonMap::new();
+            for spec in specs {
+                parts.insert(
+                    spec.property.clone(),
+                    node_key_value(idx, row, spec, normalize)?,
+                );
+            }
+            Ok(JsonValue::Object(parts))
+        }
+    }
+}
+
+/// Copy mapped node properties from `row` into `props`, honouring the row's changed-column
+/// mask and the mapping's property size limit.
+fn insert_node_props<'a>(
+    idx: usize,
+    row: &LogicalRow,
+    common: &CommonMappingFields,
+    specs: impl Iterator<Item = (&'a String, &'a PropertySpec)>,
+    prefix: Option<&str>,
+    props: &mut JsonMap<String, JsonValue>,
+) -> Result<()> {
+    let row_error = |e: anyhow::Error| anyhow!("Row {}: {}", idx, e);
+    let mask = changed_column_mask(row, common);
+    for (prop_name, spec) in specs {
+        if !column_in_mask(mask.as_deref(), &spec.column) {
+            continue;
+        }
+        let Some(val_raw) = property_source_value(row, spec, prop_name).map_err(row_error)? else {
+            continue;
+        };
+        let val = property_value(val_raw, spec)
+            .and_then(|v| enforce_property_limit(v, common, prop_name))
+            .map_err(row_error)?;
+        props.insert(prefixed_name(prefix, prop_name), val);
+    }
+    Ok(())
+}
+
+/// The graph property name for mapped property `name` under an optional `property_prefix`.
+fn prefixed_name(prefix: Option<&str>, name: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{}{}", prefix, name),
+        None => name.to_string(),
+    }
+}
+
+/// Turn a raw source value into the stored property value: normalised and coerced to the
+/// spec's `type`. With `as_list` a scalar is wrapped in a one-element list and each element
+/// of the list is normalised and coerced on its own.
+fn property_value(value: JsonValue, spec: &PropertySpec) -> Result<JsonValue> {
+    if !spec.as_list {
+        return coerce_property_value(normalise_property_value(value), spec.r#type, &spec.column);
+    }
+    let items = match value {
+        JsonValue::Null => return Ok(JsonValue::Null),
+        JsonValue::Array(items) => items,
+        JsonValue::Object(_) => bail!(
+            "Column '{}' holds an object, which can't be stored as a list property",
+            spec.column
+        ),
+        scalar => vec![scalar],
+    };
+    items
+        .into_iter()
+        .map(|v| coerce_property_value(normalise_property_value(v), spec.r#type, &spec.column))
+        .collect::<Result<Vec<_>>>()
+        .map(JsonValue::Array)
+}
+
+/// The raw value for property `prop_name`. When the column is missing, an optional spec
+/// yields its `default` or `None` to leave the property out; a required one is an error.
+fn property_source_value(
+    row: &LogicalRow,
+    spec: &PropertySpec,
+    prop_name: &str,
+) -> Result<Option<JsonValue>> {
+    match row.get(&spec.column) {
+        Some(v) => Ok(Some(v.clone())),
+        None if spec.required => Err(anyhow!(
+            "missing column '{}' required for property '{}'",
+            spec.column,
+            prop_name
+        )),
+        None => Ok(spec.default.clone()),
+    }
+}
+
+/// Map a single row to a node. `idx` is only used for error messages.
+fn map_row_to_node(
+    idx: usize,
+    row: &LogicalRow,
+    mapping: &NodeMappingConfig,
+) -> Result<MappedNode> {
+    let key_value = node_key(idx, row, &mapping.key, mapping.key_normalize)?;
+
+    let (label, label_props) = match &mapping.labels_from_column {
+        Some(column) => {
+            let label = match row.get(column) {
+                Some(JsonValue::String(s)) => s.clone(),
+                Some(other) => {
+                    return Err(anyhow!(
+                        "Row {} has non-string value {} in label column '{}'",
+                        idx,
+                        other,
+                        column
+                    ))
+                }
+                None => return Err(anyhow!("Row {} is missing label column '{}'", idx, column)),
+            };
+            if !mapping.is_allowed_label(&label) {
+                return Err(anyhow!(
+                    "Row {} has label '{}' in column '{}', which is not in allowed_labels or properties_by_label",
+                    idx,
+                    label,
+                    column
+                ));
+            }
+            let label_props = mapping.properties_by_label.get(&label);
+            (Some(label), label_props)
+        }
+        None => (None, None),
+    };
+
+    let mut props = JsonMap::new();
+    // Include the key properties unless the mapping relies on the MERGE predicate alone.
+    if !mapping.exclude_key_from_props {
+        match (&mapping.key, &key_value) {
+            (NodeKey::Composite(_), JsonValue::Object(parts)) => {
+                props.extend(parts.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+            (_, value) => {
+                props.insert(mapping.key.specs()[0].property.clone(), value.clone());
+            }
+        }
+    }
+
+    insert_node_props(
+        idx,
+        row,
+        &mapping.common,
+        mapping
+            .properties
+            .iter()
+            .chain(label_props.into_iter().flatten()),
+        mapping.property_prefix.as_deref(),
+        &mut props,
+    )?;
+
+    Ok(MappedNode {
+        key: key_value,
+        props,
+        label,
+    })
+}
+
+/// Map tabular rows to FalkorDB nodes according to a NodeMappingConfig. `first_row` is the
+/// position of `rows[0]` in the mapping's fetch, so errors name rows by their place in the
+/// whole fetch rather than in one chunk of it.
 pub fn map_rows_to_nodes(
     rows: &[LogicalRow],
     mapping: &NodeMappingConfig,
+    first_row: usize,
 ) -> Result<Vec<MappedNode>> {
     let mut out = Vec::with_capacity(rows.len());
 
     for (idx, row) in rows.iter().enumerate() {
-        let key_raw = row
-            .get(&mapping.key.column)
-            .cloned()
-            .ok_or_else(|| anyhow!("Row {} is missing key column '{}'", idx, mapping.key.column))?;
-        let key_value = normalise_property_value(key_raw);
+        out.push(map_row_to_node(first_row + idx, row, mapping)?);
+    }
 
-        let mut props = JsonMap::new();
-        // Always include key property
-        props.insert(mapping.key.property.clone(), key_value.clone());
+    Ok(out)
+}
 
-        for (prop_name, spec) in &mapping.properties {
-            let val_raw = row.get(&spec.column).cloned().ok_or_else(|| {
-                anyhow!(
-                    "Row {} is missing column '{}' required for property '{}'",
-                    idx,
-                    spec.column,
-                    prop_name
-                )
-            })?;
-            let val = normalise_property_value(val_raw);
-            props.insert(prop_name.clone(), val);
-        }
+/// Like `map_rows_to_nodes`, but rows that fail to map are returned as `SkippedRow`s
+/// instead of aborting the whole mapping.
+pub fn map_rows_to_nodes_skipping(
+    rows: &[LogicalRow],
+    mapping: &NodeMappingConfig,
+    first_row: usize,
+) -> (Vec<MappedNode>, Vec<SkippedRow>) {
+    let mut out = Vec::with_capacity(rows.len());
+    let mut skipped = Vec::new();
 
-        out.push(MappedNode {
-            key: key_value,
-            props,
-        });
+    for (idx, row) in rows.iter().enumerate() {
+        match map_row_to_node(first_row + idx, row, mapping) {
+            Ok(node) => out.push(node),
+            Err(e) => skipped.push(SkippedRow {
+                row: row.clone(),
+                reason: e.to_string(),
+            }),
+        }
     }
 
-    Ok(out)
+    (out, skipped)
 }
 
 /// Build a property map for matching endpoints based on MatchOn specs.
-fn build_match_props(row: &LogicalRow, specs: &[MatchOn]) -> Result<JsonMap<String, JsonValue>> {
+fn build_match_props(
+    row: &LogicalRow,
+    specs: &[MatchOn],
+    normalize: Option<KeyNormalize>,
+) -> Result<JsonMap<String, JsonValue>> {
     let mut props = JsonMap::new();
     for spec in specs {
         let val_raw = row
             .get(&spec.column)
             .cloned()
             .ok_or_else(|| anyhow!("Missing column '{}' for endpoint match", spec.column))?;
-        let val = normalise_property_value(val_raw);
+        let val = coerce_key_value(
+            normalise_property_value(val_raw),
+            spec.key_type,
+            &spec.column,
+        )?;
+        let val = normalise_key_value(val, normalize);
         props.insert(spec.property.clone(), val);
     }
     Ok(props)
 }
 
-/// Map tabular rows to FalkorDB edges according to an EdgeMappingConfig.
+/// Map a single row to an edge. `idx` is the row's index in the source, used in errors.
+fn map_row_to_edge(
+    idx: usize,
+    row: &LogicalRow,
+    mapping: &EdgeMappingConfig,
+) -> Result<MappedEdge> {
+    let row_error = |e: anyhow::Error| anyhow!("Row {}: {}", idx, e);
+    let from_props = build_match_props(row, &mapping.from.match_on, mapping.from.key_normalize)
+        .map_err(row_error)?;
+    let to_props = build_match_props(row, &mapping.to.match_on, mapping.to.key_normalize)
+        .map_err(row_error)?;
+
+    let edge_key = edge_key_value(row, mapping.key.as_ref()).map_err(row_error)?;
+
+    let mut props = JsonMap::new();
+    // Promoted match values go in first so a `properties` entry of the same name wins.
+    for (specs, match_props) in [
+        (&mapping.from.match_on, &from_props),
+        (&mapping.to.match_on, &to_props),
+    ] {
+        for spec in specs {
+            if let (Some(prop_name), Some(val)) =
+                (&spec.copy_match_to_prop, match_props.get(&spec.property))
+            {
+                props.insert(
+                    prefixed_name(mapping.property_prefix.as_deref(), prop_name),
+                    val.clone(),
+                );
+            }
+        }
+    }
+    let mask = changed_column_mask(row, &mapping.common);
+    for (prop_name, spec) in &mapping.properties {
+        if !column_in_mask(mask.as_deref(), &spec.column) {
+            continue;
+        }
+        let Some(val_raw) = property_source_value(row, spec, prop_name).map_err(row_error)? else {
+            continue;
+        };
+        let val = property_value(val_raw, spec)
+            .and_then(|v| enforce_property_limit(v, &mapping.common, prop_name))
+            .map_err(row_error)?;
+        props.insert(
+            prefixed_name(mapping.property_prefix.as_deref(), prop_name),
+            val,
+        );
+    }
+
+    Ok(MappedEdge {
+        from_props,
+        to_props,
+        edge_key,
+        props,
+        source_row: idx,
+    })
+}
+
+/// Edge key value for a row, if the mapping has an edge key.
+fn edge_key_value(row: &LogicalRow, key: Option<&EdgeKeySpec>) -> Result<Option<JsonValue>> {
+    let Some(spec) = key else {
+        return Ok(None);
+    };
+    let value = row
+        .get(&spec.column)
+        .cloned()
+        .ok_or_else(|| anyhow!("Missing column '{}' for edge key", spec.column))?;
+    Ok(Some(normalise_property_value(value)))
+}
+
+/// Map one endpoint of a combined row to a node (the key is always stored as a property).
+fn map_combined_endpoint(
+    idx: usize,
+    row: &LogicalRow,
+    common: &CommonMappingFields,
+    spec: &CombinedNodeSpec,
+) -> Result<MappedNode> {
+    let key = node_key_value(idx, row, &spec.key, spec.key_normalize)?;
+    let mut props = JsonMap::new();
+    props.insert(spec.key.property.clone(), key.clone());
+    insert_node_props(idx, row, common, spec.properties.iter(), None, &mut props)?;
+    Ok(MappedNode {
+        key,
+        props,
+        label: None,
+    })
+}
+
+/// Map a single denormalized row to both endpoint nodes and the relationship between them.
+fn map_row_to_combined(
+    idx: usize,
+    row: &LogicalRow,
+    mapping: &CombinedMappingConfig,
+) -> Result<MappedCombined> {
+    let from = map_combined_endpoint(idx, row, &mapping.common, &mapping.from)?;
+    let to = map_combined_endpoint(idx, row, &mapping.common, &mapping.to)?;
+    let edge_key =
+        edge_key_value(row, mapping.key.as_ref()).map_err(|e| anyhow!("Row {}: {}", idx, e))?;
+
+    let mut props = JsonMap::new();
+    insert_node_props(
+        idx,
+        row,
+        &mapping.common,
+        mapping.properties.iter(),
+        None,
+        &mut props,
+    )?;
+
+    Ok(MappedCombined {
+        from,
+        to,
+        edge_key,
+        props,
+    })
+}
+
+/// Map rows of a combined mapping. The first row that fails to map fails the mapping.
+/// `first_row` numbers the rows as for `map_rows_to_nodes`.
+pub fn map_rows_to_combined(
+    rows: &[LogicalRow],
+    mapping: &CombinedMappingConfig,
+    first_row: usize,
+) -> Result<Vec<MappedCombined>> {
+    rows.iter()
+        .enumerate()
+        .map(|(idx, row)| map_row_to_combined(first_row + idx, row, mapping))
+        .collect()
+}
+
+/// Like `map_rows_to_combined`, but rows that fail to map are returned as `SkippedRow`s.
+pub fn map_rows_to_combined_skipping(
+    rows: &[LogicalRow],
+    mapping: &CombinedMappingConfig,
+    first_row: usize,
+) -> (Vec<MappedCombined>, Vec<SkippedRow>) {
+    let mut out = Vec::with_capacity(rows.len());
+    let mut skipped = Vec::new();
+
+    for (idx, row) in rows.iter().enumerate() {
+        match map_row_to_combined(first_row + idx, row, mapping) {
+            Ok(item) => out.push(item),
+            Err(e) => skipped.push(SkippedRow {
+                row: row.clone(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    (out, skipped)
+}
+
+/// Explode the array in `column` into one row per element. Object elements are merged over
+/// the parent row's columns; any other element is exposed under `column` itself. A null or
+/// missing array yields no rows; a string is parsed as a JSON array.
+fn explode_row(idx: usize, row: &LogicalRow, column: &str) -> Result<Vec<LogicalRow>> {
+    let items = match row.get(column) {
+        None | Some(JsonValue::Null) => return Ok(Vec::new()),
+        Some(JsonValue::Array(items)) => items.clone(),
+        Some(JsonValue::String(s)) => match serde_json::from_str(s) {
+            Ok(JsonValue::Array(items)) => items,
+            _ => {
+                return Err(anyhow!(
+                    "Row {}: column '{}' does not contain a JSON array",
+                    idx,
+                    column
+                ))
+            }
+        },
+        Some(other) => {
+            return Err(anyhow!(
+                "Row {}: column '{}' holds {} instead of an array",
+                idx,
+                column,
+                other
+            ))
+        }
+    };
+
+    let mut parent = row.values.clone();
+    parent.remove(column);
+
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            let mut values = parent.clone();
+            match item {
+                JsonValue::Object(fields) => values.extend(fields),
+                other => {
+                    values.insert(column.to_string(), other);
+                }
+            }
+            LogicalRow { values }
+        })
+        .collect())
+}
+
+/// Whether `endpoint` opts into `skip_on_null` and any of its match values in `row` is null
+/// or missing.
+fn has_null_endpoint(row: &LogicalRow, endpoint: &EdgeEndpointMatch) -> bool {
+    endpoint.skip_on_null
+        && endpoint
+            .match_on
+            .iter()
+            .any(|m| row.get(&m.column).is_none_or(JsonValue::is_null))
+}
+
+/// Edges mapped from a chunk of rows, with the rows that produced none.
+#[derive(Debug, Default)]
+pub struct MappedEdgeRows {
+    pub edges: Vec<MappedEdge>,
+    /// Rows that could not be mapped. Only [`map_rows_to_edges_skipping`] fills this; the
+    /// other functions fail on the first such row.
+    pub skipped: Vec<SkippedRow>,
+    /// Rows (after `explode_column`) left out because a `skip_on_null` endpoint is null. They
+    /// are not errors; the caller decides how to count them.
+    pub null_endpoint_rows: Vec<LogicalRow>,
+}
+
+/// Turn source rows into one row per edge: explode `explode_column` arrays, set aside rows
+/// with a null `skip_on_null` endpoint, then collapse duplicates per `dedupe` unless `deletes`
+/// is set (every delete row is applied, so a delete followed by a re-insert isn't lost). Each
+/// output row is tagged with the index of the source row it came from, counting from
+/// `first_row`. Rows whose array can't be read are returned as skipped.
+fn prepare_edge_rows(
+    rows: &[LogicalRow],
+    mapping: &EdgeMappingConfig,
+    first_row: usize,
+    deletes: bool,
+) -> (Vec<(usize, LogicalRow)>, MappedEdgeRows) {
+    let mut skipped = Vec::new();
+    let rows: Vec<(usize, LogicalRow)> = match &mapping.explode_column {
+        Some(column) => {
+            let mut out = Vec::with_capacity(rows.len());
+            for (idx, row) in rows.iter().enumerate() {
+                let idx = first_row + idx;
+                match explode_row(idx, row, column) {
+                    Ok(items) => out.extend(items.into_iter().map(|item| (idx, item))),
+                    Err(e) => skipped.push(SkippedRow {
+                        row: row.clone(),
+                        reason: e.to_string(),
+                    }),
+                }
+            }
+            out
+        }
+        None => rows
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(idx, row)| (first_row + idx, row))
+            .collect(),
+    };
+
+    let mut null_endpoint_rows = Vec::new();
+    let rows: Vec<(usize, LogicalRow)> = if mapping.from.skip_on_null || mapping.to.skip_on_null {
+        let mut kept = Vec::with_capacity(rows.len());
+        for (idx, row) in rows {
+            if has_null_endpoint(&row, &mapping.from) || has_null_endpoint(&row, &mapping.to) {
+                null_endpoint_rows.push(row);
+            } else {
+                kept.push((idx, row));
+            }
+        }
+        kept
+    } else {
+        rows
+    };
+
+    let rows = match &mapping.dedupe {
+        Some(spec) if !deletes => {
+            let (kept, collapsed) = dedupe_edge_rows(rows, mapping, spec);
+            if collapsed > 0 {
+                tracing::info!(
+                    mapping = %mapping.common.name,
+                    collapsed,
+                    keep = ?spec.keep,
+                    "Collapsed duplicate edge rows",
+                );
+            }
+            kept
+        }
+        _ => rows,
+    };
+
+    (
+        rows,
+        MappedEdgeRows {
+            edges: Vec::new(),
+            skipped,
+            null_endpoint_rows,
+        },
+    )
+}
+
+/// Edge keys of rows dropped for a null `skip_on_null` endpoint (see
+/// [`MappedEdgeRows::null_endpoint_rows`]), `None` for a row whose key can't be read. Such rows
+/// are still in the source, so `reconcile_deletes` counts their keys as present. Empty for
+/// mappings without a `key`.
+pub fn null_endpoint_edge_keys(
+    null_endpoint_rows: &[LogicalRow],
+    mapping: &EdgeMappingConfig,
+) -> Vec<Option<JsonValue>> {
+    if mapping.key.is_none() {
+        return Vec::new();
+    }
+    null_endpoint_rows
+        .iter()
+        .map(|row| edge_key_value(row, mapping.key.as_ref()).ok().flatten())
+        .collect()
+}
+
+/// Map tabular rows to FalkorDB edges according to an EdgeMappingConfig. With
+/// `explode_column`, each row yields one edge per array element. `first_row` numbers the
+/// rows as for `map_rows_to_nodes`.
 pub fn map_rows_to_edges(
     rows: &[LogicalRow],
     mapping: &EdgeMappingConfig,
+    first_row: usize,
 ) -> Result<Vec<MappedEdge>> {
-    let mut out = Vec::with_capacity(rows.len());
+    Ok(map_edge_rows(rows, mapping, first_row, false)?.edges)
+}
 
-    for row in rows {
-        let from_props = build_match_props(row, &mapping.from.match_on)?;
-        let to_props = build_match_props(row, &mapping.to.match_on)?;
+/// Like `map_rows_to_edges`, but also returns the rows left out for a null `skip_on_null`
+/// endpoint. Set `deletes` when `rows` are delete rows, which skip `dedupe`.
+pub fn map_edge_rows(
+    rows: &[LogicalRow],
+    mapping: &EdgeMappingConfig,
+    first_row: usize,
+    deletes: bool,
+) -> Result<MappedEdgeRows> {
+    let (rows, mut out) = prepare_edge_rows(rows, mapping, first_row, deletes);
+    if let Some(first) = out.skipped.drain(..).next() {
+        return Err(anyhow!(first.reason));
+    }
 
-        let edge_key = if let Some(edge_key_spec) = &mapping.key {
-            Some(normalise_property_value(
-                row.get(&edge_key_spec.column).cloned().ok_or_else(|| {
-                    anyhow!("Missing column '{}' for edge key", edge_key_spec.column)
-                })?,
-            ))
-        } else {
-            None
-        };
+    out.edges.reserve(rows.len());
+    for (source_row, row) in &rows {
+        out.edges.push(map_row_to_edge(*source_row, row, mapping)?);
+    }
+
+    Ok(out)
+}
+
+/// Like `map_edge_rows`, but rows that fail to map are returned as `SkippedRow`s.
+pub fn map_rows_to_edges_skipping(
+    rows: &[LogicalRow],
+    mapping: &EdgeMappingConfig,
+    first_row: usize,
+    deletes: bool,
+) -> MappedEdgeRows {
+    let (rows, mut out) = prepare_edge_rows(rows, mapping, first_row, deletes);
+    out.edges.reserve(rows.len());
+
+    for (source_row, row) in rows {
+        match map_row_to_edge(source_row, &row, mapping) {
+            Ok(edge) => out.edges.push(edge),
+            Err(e) => out.skipped.push(SkippedRow {
+                row,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    out
+}
 
-        let mut props = JsonMap::new();
-        for (prop_name, spec) in &mapping.properties {
-            let val_raw = row.get(&spec.column).cloned().ok_or_else(|| {
-                anyhow!(
-                    "Missing column '{}' required for edge property '{}'",
-                    spec.column,
-                    prop_name
-                )
-            })?;
-            let val = normalise_property_value(val_raw);
-            props.insert(prop_name.clone(), val);
-        }
-
-        out.push(MappedEdge {
-            from_props,
-            to_props,
+/// Identity of the edge a row produces (endpoint match values + edge key), or `None` if the
+/// row can't be mapped; such rows are left for the mapping step to report.
+fn edge_identity(row: &LogicalRow, mapping: &EdgeMappingConfig) -> Option<String> {
+    let from = build_match_props(row, &mapping.from.match_on, mapping.from.key_normalize).ok()?;
+    let to = build_match_props(row, &mapping.to.match_on, mapping.to.key_normalize).ok()?;
+    let edge_key = match &mapping.key {
+        Some(spec) => row.get(&spec.column)?.clone(),
+        None => JsonValue::Null,
+    };
+    Some(
+        JsonValue::Array(vec![
+            JsonValue::Object(from),
+            JsonValue::Object(to),
             edge_key,
-            props,
-        });
+        ])
+        .to_string(),
+    )
+}
+
+/// Order two `max_by` column values: numbers numerically, strings lexically (so ISO-8601
+/// timestamps sort correctly); nulls and missing values lose to anything else.
+fn compare_dedupe_values(a: Option<&JsonValue>, b: Option<&JsonValue>) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (a, b) {
+        (Some(JsonValue::Number(x)), Some(JsonValue::Number(y))) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(JsonValue::String(x)), Some(JsonValue::String(y))) => x.cmp(y),
+        (None | Some(JsonValue::Null), None | Some(JsonValue::Null)) => Ordering::Equal,
+        (None | Some(JsonValue::Null), _) => Ordering::Less,
+        (_, None | Some(JsonValue::Null)) => Ordering::Greater,
+        (Some(x), Some(y)) => x.to_string().cmp(&y.to_string()),
     }
+}
 
-    Ok(out)
+/// Collapse rows that would produce the same edge, keeping one per identity according to
+/// `spec.keep`. Returns the surviving rows (in order of first appearance) and the number of
+/// rows dropped.
+fn dedupe_edge_rows(
+    rows: Vec<(usize, LogicalRow)>,
+    mapping: &EdgeMappingConfig,
+    spec: &EdgeDedupeSpec,
+) -> (Vec<(usize, LogicalRow)>, usize) {
+    let total = rows.len();
+    let mut out: Vec<(usize, LogicalRow)> = Vec::with_capacity(total);
+    let mut index_by_identity: std::collections::HashMap<String, usize> =
+        std::collections::HashMap::new();
+
+    for (source_row, row) in rows {
+        let Some(identity) = edge_identity(&row, mapping) else {
+            out.push((source_row, row));
+            continue;
+        };
+        match index_by_identity.get(&identity) {
+            None => {
+                index_by_identity.insert(identity, out.len());
+                out.push((source_row, row));
+            }
+            Some(&i) => {
+                let replace = match spec.keep {
+                    DedupeKeep::First => false,
+                    DedupeKeep::Last => true,
+                    DedupeKeep::MaxBy => {
+                        let column = spec.by_column.as_deref().unwrap_or_default();
+                        compare_dedupe_values(row.get(column), out[i].1.get(column)).is_ge()
+                    }
+                };
+                if replace {
+                    out[i] = (source_row, row);
+                }
+            }
+        }
+    }
+
+    let collapsed = total - out.len();
+    (out, collapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(value: JsonValue) -> LogicalRow {
+        LogicalRow {
+            values: value.as_object().cloned().expect("row must be an object"),
+        }
+    }
+
+    #[test]
+    fn when_condition_matches_exact_value() {
+        let when = WhenCondition {
+            column: "RECORD_TYPE".to_string(),
+            equals: json!("order"),
+        };
+        assert!(row_matches_when(
+            &row(json!({ "RECORD_TYPE": "order" })),
+            &when
+        ));
+        assert!(!row_matches_when(
+            &row(json!({ "RECORD_TYPE": "refund" })),
+            &when
+        ));
+        assert!(!row_matches_when(&row(json!({ "OTHER": "order" })), &when));
+    }
+
+    #[test]
+    fn combined_row_maps_both_endpoints_and_edge() -> Result<()> {
+        let mapping: CombinedMappingConfig = serde_json::from_value(json!({
+            "name": "purchases",
+            "source": { "file": "purchases.json" },
+            "relationship": "PURCHASED",
+            "from": {
+                "labels": ["Customer"],
+                "key": { "column": "CUSTOMER_ID", "property": "id", "key_type": "int" }
+            },
+            "to": {
+                "labels": ["Product"],
+                "key": { "column": "SKU", "property": "sku" },
+                "key_normalize": "upper",
+                "properties": { "name": { "column": "PRODUCT_NAME" } }
+            },
+            "key": { "column": "ORDER_ID", "property": "order_id" },
+            "properties": { "qty": { "column": "QTY" } }
+        }))?;
+
+        let rows = vec![row(json!({
+            "CUSTOMER_ID": "7",
+            "SKU": "ab-1",
+            "PRODUCT_NAME": "Widget",
+            "ORDER_ID": 1001,
+            "QTY": 3
+        }))];
+        let mapped = map_rows_to_combined(&rows, &mapping, 0)?;
+
+        assert_eq!(mapped[0].from.key, json!(7));
+        assert_eq!(mapped[0].from.props.get("id"), Some(&json!(7)));
+        assert_eq!(mapped[0].to.key, json!("AB-1"));
+        assert_eq!(mapped[0].to.props.get("name"), Some(&json!("Widget")));
+        assert_eq!(mapped[0].edge_key, Some(json!(1001)));
+        assert_eq!(mapped[0].props.get("qty"), Some(&json!(3)));
+
+        let (ok, skipped) =
+            map_rows_to_combined_skipping(&[row(json!({ "CUSTOMER_ID": 1 }))], &mapping, 0);
+        assert!(ok.is_empty());
+        assert_eq!(skipped.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn key_normalize_collapses_case_variants() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "customers",
+            "source": { "file": "customers.json" },
+            "labels": ["Customer"],
+            "key": { "column": "EMAIL", "property": "email" },
+            "key_normalize": "lower",
+            "properties": {}
+        }))?;
+
+        let rows = vec![
+            row(json!({ "EMAIL": "Alice@x.com" })),
+            row(json!({ "EMAIL": "alice@x.com" })),
+        ];
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+
+        assert_eq!(nodes[0].key, json!("alice@x.com"));
+        assert_eq!(nodes[1].key, json!("alice@x.com"));
+        assert_eq!(nodes[0].props.get("email"), Some(&json!("alice@x.com")));
+        Ok(())
+    }
+
+    #[test]
+    fn key_normalize_applies_to_edge_endpoints() -> Result<()> {
+        let mapping: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "customer_orders",
+            "source": { "file": "orders.json" },
+            "relationship": "PURCHASED",
+            "from": {
+                "node_mapping": "customers",
+                "match_on": [{ "column": "EMAIL", "property": "email" }],
+                "key_normalize": "lower"
+            },
+            "to": {
+                "node_mapping": "orders",
+                "match_on": [{ "column": "ORDER_ID", "property": "order_id" }]
+            },
+            "properties": {}
+        }))?;
+
+        let rows = vec![row(json!({ "EMAIL": "Alice@X.com", "ORDER_ID": "A-1" }))];
+        let edges = map_rows_to_edges(&rows, &mapping, 0)?;
+
+        assert_eq!(
+            edges[0].from_props.get("email"),
+            Some(&json!("alice@x.com"))
+        );
+        // No normalization configured on the `to` side: value is left untouched.
+        assert_eq!(edges[0].to_props.get("order_id"), Some(&json!("A-1")));
+        Ok(())
+    }
+
+    #[test]
+    fn copy_match_to_prop_promotes_match_values_onto_the_edge() -> Result<()> {
+        let mapping: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "customer_orders",
+            "source": { "file": "orders.json" },
+            "relationship": "PURCHASED",
+            "from": {
+                "node_mapping": "customers",
+                "match_on": [{
+                    "column": "EMAIL",
+                    "property": "email",
+                    "copy_match_to_prop": "customer_email"
+                }],
+                "key_normalize": "lower"
+            },
+            "to": {
+                "node_mapping": "orders",
+                "match_on": [{
+                    "column": "ORDER_ID",
+                    "property": "order_id",
+                    "key_type": "int",
+                    "copy_match_to_prop": "order"
+                }]
+            },
+            "properties": { "order": { "column": "ORDER_REF" } },
+            "property_prefix": "e_"
+        }))?;
+
+        let rows = vec![row(
+            json!({ "EMAIL": "Alice@X.com", "ORDER_ID": "17", "ORDER_REF": "ref-17" }),
+        )];
+        let edges = map_rows_to_edges(&rows, &mapping, 0)?;
+
+        // The promoted value is the normalized match value, under the prefixed name.
+        assert_eq!(
+            edges[0].props.get("e_customer_email"),
+            Some(&json!("alice@x.com"))
+        );
+        // A `properties` entry of the same name wins over the promoted match value.
+        assert_eq!(edges[0].props.get("e_order"), Some(&json!("ref-17")));
+        assert_eq!(edges[0].to_props.get("order_id"), Some(&json!(17)));
+        Ok(())
+    }
+
+    #[test]
+    fn key_type_coerces_mixed_int_and_string_keys() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "accounts",
+            "source": { "file": "accounts.json" },
+            "labels": ["Account"],
+            "key": { "column": "ID", "property": "id", "key_type": "int" },
+            "properties": {}
+        }))?;
+
+        let rows = vec![
+            row(json!({ "ID": 42 })),
+            row(json!({ "ID": "42" })),
+            row(json!({ "ID": " 42 " })),
+            row(json!({ "ID": 42.0 })),
+        ];
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+        for node in &nodes {
+            assert_eq!(node.key, json!(42));
+        }
+
+        let bad = vec![row(json!({ "ID": "abc" }))];
+        assert!(map_rows_to_nodes(&bad, &mapping, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn row_numbers_count_from_the_chunk_start() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "accounts",
+            "source": { "file": "accounts.json" },
+            "labels": ["Account"],
+            "key": { "column": "ID", "property": "id" },
+            "properties": {}
+        }))?;
+        let rows = vec![row(json!({ "ID": 1 })), row(json!({ "NAME": "no id" }))];
+
+        let err = map_rows_to_nodes(&rows, &mapping, 1000)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("Row 1001 "), "{}", err);
+
+        let (nodes, skipped) = map_rows_to_nodes_skipping(&rows, &mapping, 1000);
+        assert_eq!(nodes.len(), 1);
+        assert!(
+            skipped[0].reason.starts_with("Row 1001 "),
+            "{}",
+            skipped[0].reason
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn property_type_coerces_strings_to_int_and_bool() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "accounts",
+            "source": { "file": "accounts.json" },
+            "labels": ["Account"],
+            "key": { "column": "ID", "property": "id" },
+            "properties": {
+                "balance": { "column": "BALANCE", "type": "int" },
+                "active": { "column": "ACTIVE", "type": "bool" },
+                "opened": { "column": "OPENED", "type": "timestamp" },
+                "note": { "column": "NOTE" }
+            }
+        }))?;
+
+        let rows = vec![row(json!({
+            "ID": 1,
+            "BALANCE": " 42 ",
+            "ACTIVE": "TRUE",
+            "OPENED": "2024-03-01 12:30:00",
+            "NOTE": "7"
+        }))];
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+        assert_eq!(nodes[0].props.get("balance"), Some(&json!(42)));
+        assert_eq!(nodes[0].props.get("active"), Some(&json!(true)));
+        assert_eq!(
+            nodes[0].props.get("opened"),
+            Some(&json!("2024-03-01T12:30:00Z"))
+        );
+        assert_eq!(nodes[0].props.get("note"), Some(&json!("7")));
+
+        let bad_int = vec![row(json!({
+            "ID": 2, "BALANCE": "4.2x", "ACTIVE": "no", "OPENED": null, "NOTE": null
+        }))];
+        let err = map_rows_to_nodes(&bad_int, &mapping, 0)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("Row 0") && err.contains("'BALANCE'"),
+            "{}",
+            err
+        );
+
+        let bad_bool = vec![row(json!({
+            "ID": 3, "BALANCE": 1, "ACTIVE": "maybe", "OPENED": null, "NOTE": null
+        }))];
+        let err = map_rows_to_nodes(&bad_bool, &mapping, 0)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'ACTIVE'"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn optional_property_uses_default_or_is_skipped_when_column_missing() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "accounts",
+            "source": { "file": "accounts.json" },
+            "labels": ["Account"],
+            "key": { "column": "ID", "property": "id" },
+            "properties": {
+                "tier": { "column": "TIER", "required": false, "default": "basic" },
+                "nickname": { "column": "NICKNAME", "required": false },
+                "name": { "column": "NAME" }
+            }
+        }))?;
+
+        let nodes = map_rows_to_nodes(&[row(json!({ "ID": 1, "NAME": "Ann" }))], &mapping, 0)?;
+        assert_eq!(nodes[0].props.get("tier"), Some(&json!("basic")));
+        assert!(!nodes[0].props.contains_key("nickname"));
+
+        let nodes = map_rows_to_nodes(
+            &[row(
+                json!({ "ID": 2, "NAME": "Bo", "TIER": "gold", "NICKNAME": null }),
+            )],
+            &mapping,
+            0,
+        )?;
+        assert_eq!(nodes[0].props.get("tier"), Some(&json!("gold")));
+        assert_eq!(nodes[0].props.get("nickname"), Some(&JsonValue::Null));
+
+        let err = map_rows_to_nodes(&[row(json!({ "ID": 3 }))], &mapping, 0)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("missing column 'NAME'"), "{}", err);
+
+        let edges: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "knows",
+            "source": { "file": "knows.json" },
+            "relationship": "KNOWS",
+            "from": { "node_mapping": "accounts", "match_on": [{ "column": "A", "property": "id" }] },
+            "to": { "node_mapping": "accounts", "match_on": [{ "column": "B", "property": "id" }] },
+            "properties": {
+                "weight": { "column": "WEIGHT", "required": false, "default": "1.5", "type": "float" },
+                "since": { "column": "SINCE", "required": false }
+            }
+        }))?;
+        let mapped = map_rows_to_edges(&[row(json!({ "A": 1, "B": 2 }))], &edges, 0)?;
+        assert_eq!(mapped[0].props.get("weight"), Some(&json!(1.5)));
+        assert!(!mapped[0].props.contains_key("since"));
+        Ok(())
+    }
+
+    #[test]
+    fn as_list_wraps_scalars_and_passes_arrays_through() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "products",
+            "source": { "file": "products.json" },
+            "labels": ["Product"],
+            "key": { "column": "ID", "property": "id" },
+            "properties": {
+                "tags": { "column": "TAGS", "as_list": true },
+                "sizes": { "column": "SIZES", "as_list": true, "type": "int" }
+            }
+        }))?;
+
+        let nodes = map_rows_to_nodes(
+            &[
+                row(json!({ "ID": 1, "TAGS": "sale", "SIZES": "42" })),
+                row(json!({ "ID": 2, "TAGS": ["new", "eco"], "SIZES": [38, "40"] })),
+            ],
+            &mapping,
+            0,
+        )?;
+        assert_eq!(nodes[0].props.get("tags"), Some(&json!(["sale"])));
+        assert_eq!(nodes[0].props.get("sizes"), Some(&json!([42])));
+        assert_eq!(nodes[1].props.get("tags"), Some(&json!(["new", "eco"])));
+        assert_eq!(nodes[1].props.get("sizes"), Some(&json!([38, 40])));
+
+        let err = map_rows_to_nodes(
+            &[row(json!({ "ID": 3, "TAGS": { "a": 1 }, "SIZES": [] }))],
+            &mapping,
+            0,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Column 'TAGS' holds an object"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn property_prefix_applies_to_properties_but_not_keys() -> Result<()> {
+        let nodes: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "accounts",
+            "source": { "file": "accounts.json" },
+            "labels": ["Account"],
+            "key": { "column": "ID", "property": "id" },
+            "properties": { "name": { "column": "NAME" } },
+            "property_prefix": "src_"
+        }))?;
+        let mapped = map_rows_to_nodes(&[row(json!({ "ID": 7, "NAME": "Ann" }))], &nodes, 0)?;
+        assert_eq!(mapped[0].key, json!(7));
+        assert_eq!(mapped[0].props.get("src_name"), Some(&json!("Ann")));
+        assert!(!mapped[0].props.contains_key("name"));
+        assert!(!mapped[0].props.contains_key("src_id"));
+
+        let edges: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "knows",
+            "source": { "file": "knows.json" },
+            "relationship": "KNOWS",
+            "from": { "node_mapping": "accounts", "match_on": [{ "column": "A", "property": "id" }] },
+            "to": { "node_mapping": "accounts", "match_on": [{ "column": "B", "property": "id" }] },
+            "key": { "column": "EDGE_ID", "property": "edge_id" },
+            "properties": { "since": { "column": "SINCE" } },
+            "property_prefix": "src_"
+        }))?;
+        let mapped = map_rows_to_edges(
+            &[row(
+                json!({ "A": 1, "B": 2, "EDGE_ID": "e1", "SINCE": 2020 }),
+            )],
+            &edges,
+            0,
+        )?;
+        assert_eq!(mapped[0].edge_key, Some(json!("e1")));
+        assert_eq!(mapped[0].props.get("src_since"), Some(&json!(2020)));
+        assert_eq!(mapped[0].from_props.get("id"), Some(&json!(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn key_type_string_stringifies_numbers() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "accounts",
+            "source": { "file": "accounts.json" },
+            "labels": ["Account"],
+            "key": { "column": "ID", "property": "id", "key_type": "string" },
+            "properties": {}
+        }))?;
+
+        let rows = vec![row(json!({ "ID": 42 })), row(json!({ "ID": "42" }))];
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+        assert_eq!(nodes[0].key, json!("42"));
+        assert_eq!(nodes[1].key, json!("42"));
+        Ok(())
+    }
+
+    #[test]
+    fn composite_key_maps_every_key_column() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "accounts",
+            "source": { "file": "accounts.json" },
+            "labels": ["Account"],
+            "key": [
+                { "column": "REGION", "property": "region" },
+                { "column": "ID", "property": "id", "key_type": "int" }
+            ],
+            "properties": {}
+        }))?;
+
+        let rows = vec![row(json!({ "REGION": "eu", "ID": "42" }))];
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+        assert_eq!(nodes[0].key, json!({ "region": "eu", "id": 42 }));
+        assert_eq!(nodes[0].props.get("region"), Some(&json!("eu")));
+        assert_eq!(nodes[0].props.get("id"), Some(&json!(42)));
+
+        let missing = vec![row(json!({ "ID": 1 }))];
+        assert!(map_rows_to_nodes(&missing, &mapping, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn exclude_key_from_props_omits_key() -> Result<()> {
+        let mut mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "customers",
+            "source": { "file": "customers.json" },
+            "labels": ["Customer"],
+            "key": { "column": "ID", "property": "customer_id" },
+            "properties": { "email": { "column": "EMAIL" } }
+        }))?;
+        let rows = vec![row(json!({ "ID": 7, "EMAIL": "a@x.com" }))];
+
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+        assert_eq!(nodes[0].props.get("customer_id"), Some(&json!(7)));
+
+        mapping.exclude_key_from_props = true;
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+        assert_eq!(nodes[0].key, json!(7));
+        assert!(!nodes[0].props.contains_key("customer_id"));
+        assert_eq!(nodes[0].props.get("email"), Some(&json!("a@x.com")));
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_property_values_are_truncated_or_rejected() -> Result<()> {
+        let mut mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "notes",
+            "source": { "file": "notes.json" },
+            "labels": ["Note"],
+            "key": { "column": "ID", "property": "id" },
+            "max_property_bytes": 5,
+            "properties": { "body": { "column": "BODY" } }
+        }))?;
+
+        // "héllo wörld": the 'é' spans bytes 1..3, so a 5-byte cut lands after "héll".
+        let rows = vec![row(json!({ "ID": 1, "BODY": "héllo wörld" }))];
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+        assert_eq!(nodes[0].props.get("body"), Some(&json!("héll")));
+
+        let short = vec![row(json!({ "ID": 2, "BODY": "hi" }))];
+        let nodes = map_rows_to_nodes(&short, &mapping, 0)?;
+        assert_eq!(nodes[0].props.get("body"), Some(&json!("hi")));
+
+        mapping.common.oversize_property = Some(OversizeAction::Reject);
+        assert!(map_rows_to_nodes(&rows, &mapping, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_edge_property_errors_name_the_row() -> Result<()> {
+        let mapping: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "reviews",
+            "source": { "file": "reviews.json" },
+            "relationship": "REVIEWED",
+            "from": {
+                "node_mapping": "customers",
+                "match_on": [{ "column": "CUSTOMER_ID", "property": "id" }]
+            },
+            "to": {
+                "node_mapping": "products",
+                "match_on": [{ "column": "SKU", "property": "sku" }]
+            },
+            "max_property_bytes": 5,
+            "oversize_property": "reject",
+            "properties": { "text": { "column": "TEXT" } }
+        }))?;
+        let rows = vec![
+            row(json!({ "CUSTOMER_ID": 1, "SKU": "p1", "TEXT": "ok" })),
+            row(json!({ "CUSTOMER_ID": 2, "SKU": "p2", "TEXT": "far too long" })),
+        ];
+
+        let err = map_rows_to_edges(&rows, &mapping, 10).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Row 11: Property 'text' is 12 bytes, exceeding max_property_bytes 5"
+        );
+        let mapped = map_rows_to_edges_skipping(&rows, &mapping, 10, false);
+        assert_eq!(mapped.edges[0].source_row, 10);
+        assert_eq!(mapped.skipped[0].reason, err.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn properties_by_label_selects_columns_per_label() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "parties",
+            "source": { "file": "parties.json" },
+            "labels": ["Party"],
+            "key": { "column": "ID", "property": "id" },
+            "properties": { "name": { "column": "NAME" } },
+            "labels_from_column": "ENTITY_TYPE",
+            "properties_by_label": {
+                "Customer": { "credit_limit": { "column": "CREDIT_LIMIT" } },
+                "Supplier": { "lead_time": { "column": "LEAD_TIME_DAYS" } }
+            }
+        }))?;
+
+        let rows = vec![
+            row(json!({ "ID": 1, "NAME": "Acme", "ENTITY_TYPE": "Customer", "CREDIT_LIMIT": 500 })),
+            row(json!({ "ID": 2, "NAME": "Bolt", "ENTITY_TYPE": "Supplier", "LEAD_TIME_DAYS": 7 })),
+        ];
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+
+        assert_eq!(nodes[0].label.as_deref(), Some("Customer"));
+        assert_eq!(nodes[0].props.get("credit_limit"), Some(&json!(500)));
+        assert!(nodes[0].props.get("lead_time").is_none());
+        assert_eq!(nodes[1].label.as_deref(), Some("Supplier"));
+        assert_eq!(nodes[1].props.get("lead_time"), Some(&json!(7)));
+        assert_eq!(nodes[1].props.get("name"), Some(&json!("Bolt")));
+
+        let unknown = vec![row(
+            json!({ "ID": 3, "NAME": "X", "ENTITY_TYPE": "Partner" }),
+        )];
+        let (nodes, skipped) = map_rows_to_nodes_skipping(&unknown, &mapping, 0);
+        assert!(nodes.is_empty());
+        assert!(skipped[0].reason.contains("Partner"));
+        Ok(())
+    }
+
+    #[test]
+    fn conflicting_edge_rows_are_deduped_by_keep_policy() -> Result<()> {
+        let mapping: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "customer_orders",
+            "source": { "file": "orders.json" },
+            "relationship": "PURCHASED",
+            "from": {
+                "node_mapping": "customers",
+                "match_on": [{ "column": "CUSTOMER_ID", "property": "customer_id" }]
+            },
+            "to": {
+                "node_mapping": "orders",
+                "match_on": [{ "column": "ORDER_ID", "property": "order_id" }]
+            },
+            "properties": { "qty": { "column": "QTY" } }
+        }))?;
+
+        let rows = || {
+            vec![
+                row(json!({ "CUSTOMER_ID": 1, "ORDER_ID": "A", "QTY": 5, "TS": "2024-01-02" })),
+                row(json!({ "CUSTOMER_ID": 2, "ORDER_ID": "B", "QTY": 1, "TS": "2024-01-01" })),
+                row(json!({ "CUSTOMER_ID": 1, "ORDER_ID": "A", "QTY": 7, "TS": "2024-01-03" })),
+                row(json!({ "CUSTOMER_ID": 1, "ORDER_ID": "A", "QTY": 6, "TS": "2024-01-01" })),
+            ]
+        };
+        let rows = || rows().into_iter().enumerate().collect::<Vec<_>>();
+        let qty = |rows: &[(usize, LogicalRow)]| -> Vec<JsonValue> {
+            rows.iter()
+                .map(|(_, r)| r.get("QTY").cloned().unwrap())
+                .collect()
+        };
+
+        let spec = |keep: &str| -> Result<EdgeDedupeSpec> {
+            Ok(serde_json::from_value(
+                json!({ "keep": keep, "by_column": "TS" }),
+            )?)
+        };
+
+        let (kept, collapsed) = dedupe_edge_rows(rows(), &mapping, &spec("first")?);
+        assert_eq!(collapsed, 2);
+        assert_eq!(qty(&kept), vec![json!(5), json!(1)]);
+
+        let (kept, _) = dedupe_edge_rows(rows(), &mapping, &spec("last")?);
+        assert_eq!(qty(&kept), vec![json!(6), json!(1)]);
+
+        let (kept, _) = dedupe_edge_rows(rows(), &mapping, &spec("max_by")?);
+        assert_eq!(qty(&kept), vec![json!(7), json!(1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn dedupe_leaves_delete_rows_alone() -> Result<()> {
+        let mapping: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "customer_orders",
+            "source": { "file": "orders.json" },
+            "relationship": "PURCHASED",
+            "from": {
+                "node_mapping": "customers",
+                "match_on": [{ "column": "CUSTOMER_ID", "property": "customer_id" }]
+            },
+            "to": {
+                "node_mapping": "orders",
+                "match_on": [{ "column": "ORDER_ID", "property": "order_id" }]
+            },
+            "dedupe": { "keep": "last" }
+        }))?;
+        let rows = vec![
+            row(json!({ "CUSTOMER_ID": 1, "ORDER_ID": "A" })),
+            row(json!({ "CUSTOMER_ID": 1, "ORDER_ID": "A" })),
+        ];
+
+        assert_eq!(map_edge_rows(&rows, &mapping, 0, false)?.edges.len(), 1);
+        let deletes = map_edge_rows(&rows, &mapping, 0, true)?.edges;
+        assert_eq!(deletes.len(), 2);
+        assert_eq!((deletes[0].source_row, deletes[1].source_row), (0, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn changed_columns_mask_limits_props() -> Result<()> {
+        let mapping: NodeMappingConfig = serde_json::from_value(json!({
+            "name": "customers",
+            "source": { "file": "customers.json" },
+            "changed_columns": "CHANGED",
+            "labels": ["Customer"],
+            "key": { "column": "ID", "property": "id" },
+            "properties": {
+                "email": { "column": "EMAIL" },
+                "country": { "column": "COUNTRY" }
+            }
+        }))?;
+
+        let rows = vec![
+            row(json!({ "ID": 1, "EMAIL": "a@x.com", "COUNTRY": "DE", "CHANGED": ["email"] })),
+            row(json!({ "ID": 2, "EMAIL": "b@x.com", "CHANGED": "EMAIL, OTHER" })),
+            row(json!({ "ID": 3, "EMAIL": "c@x.com", "COUNTRY": "FR", "CHANGED": null })),
+        ];
+        let nodes = map_rows_to_nodes(&rows, &mapping, 0)?;
+
+        assert_eq!(nodes[0].props.get("email"), Some(&json!("a@x.com")));
+        assert!(nodes[0].props.get("country").is_none());
+        // Unchanged columns may be absent from the row entirely.
+        assert_eq!(nodes[1].props.get("email"), Some(&json!("b@x.com")));
+        assert!(nodes[1].props.get("country").is_none());
+        // No mask: every property is set. The key is always present.
+        assert_eq!(nodes[2].props.len(), 3);
+        assert_eq!(nodes[1].props.get("id"), Some(&json!(2)));
+        Ok(())
+    }
+
+    #[test]
+    fn explode_column_yields_one_edge_per_element() -> Result<()> {
+        let mapping: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "order_products",
+            "source": { "file": "orders.json" },
+            "relationship": "CONTAINS",
+            "explode_column": "LINE_ITEMS",
+            "from": {
+                "node_mapping": "orders",
+                "match_on": [{ "column": "ORDER_ID", "property": "order_id" }]
+            },
+            "to": {
+                "node_mapping": "products",
+                "match_on": [{ "column": "SKU", "property": "sku" }]
+            },
+            "properties": { "qty": { "column": "QTY" } }
+        }))?;
+
+        let rows = vec![
+            row(json!({
+                "ORDER_ID": "A",
+                "LINE_ITEMS": [{ "SKU": "p1", "QTY": 2 }, { "SKU": "p2", "QTY": 1 }]
+            })),
+            row(json!({ "ORDER_ID": "B", "LINE_ITEMS": r#"[{"SKU": "p3", "QTY": 5}]"# })),
+            row(json!({ "ORDER_ID": "C", "LINE_ITEMS": [] })),
+            row(json!({ "ORDER_ID": "D", "LINE_ITEMS": null })),
+        ];
+        let edges = map_rows_to_edges(&rows, &mapping, 0)?;
+
+        assert_eq!(edges.len(), 3);
+        assert_eq!(edges[0].from_props.get("order_id"), Some(&json!("A")));
+        assert_eq!(edges[1].to_props.get("sku"), Some(&json!("p2")));
+        assert_eq!(edges[1].props.get("qty"), Some(&json!(1)));
+        assert_eq!(edges[2].from_props.get("order_id"), Some(&json!("B")));
+
+        let bad = vec![row(json!({ "ORDER_ID": "E", "LINE_ITEMS": 7 }))];
+        let mapped = map_rows_to_edges_skipping(&bad, &mapping, 0, false);
+        assert!(mapped.edges.is_empty());
+        assert!(mapped.skipped[0].reason.contains("LINE_ITEMS"));
+        Ok(())
+    }
+
+    #[test]
+    fn skip_on_null_drops_rows_with_null_endpoint_keys() -> Result<()> {
+        let mut mapping: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "employee_manager",
+            "source": { "file": "employees.json" },
+            "relationship": "REPORTS_TO",
+            "from": {
+                "node_mapping": "employees",
+                "match_on": [{ "column": "EMP_ID", "property": "emp_id" }]
+            },
+            "to": {
+                "node_mapping": "employees",
+                "match_on": [{ "column": "MANAGER_ID", "property": "emp_id" }],
+                "skip_on_null": true
+            },
+            "properties": {}
+        }))?;
+
+        let rows = vec![
+            row(json!({ "EMP_ID": 1, "MANAGER_ID": null })),
+            row(json!({ "EMP_ID": 2, "MANAGER_ID": 1 })),
+            row(json!({ "EMP_ID": 3 })),
+        ];
+        let mapped = map_edge_rows(&rows, &mapping, 0, false)?;
+        assert_eq!(mapped.edges.len(), 1);
+        assert_eq!(mapped.edges[0].from_props.get("emp_id"), Some(&json!(2)));
+        // The dropped rows are handed back for the caller to count, not reported as errors.
+        assert_eq!(mapped.null_endpoint_rows.len(), 2);
+        assert!(mapped.skipped.is_empty());
+
+        // Without skip_on_null a missing match column is still an error.
+        mapping.to.skip_on_null = false;
+        assert!(map_rows_to_edges(&rows, &mapping, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn null_endpoint_rows_keep_their_edge_keys() -> Result<()> {
+        let mut mapping: EdgeMappingConfig = serde_json::from_value(json!({
+            "name": "employee_manager",
+            "source": { "file": "employees.json" },
+            "relationship": "REPORTS_TO",
+            "from": {
+                "node_mapping": "employees",
+                "match_on": [{ "column": "EMP_ID", "property": "emp_id" }]
+            },
+            "to": {
+                "node_mapping": "employees",
+                "match_on": [{ "column": "MANAGER_ID", "property": "emp_id" }],
+                "skip_on_null": true
+            },
+            "key": { "column": "REL_ID", "property": "rel_id" },
+            "properties": {}
+        }))?;
+
+        let rows = vec![
+            row(json!({ "REL_ID": "r1", "EMP_ID": 1, "MANAGER_ID": null })),
+            row(json!({ "REL_ID": "r2", "EMP_ID": 2, "MANAGER_ID": 1 })),
+            row(json!({ "EMP_ID": 3 })),
+        ];
+        let dropped = map_edge_rows(&rows, &mapping, 0, false)?.null_endpoint_rows;
+        assert_eq!(
+            null_endpoint_edge_keys(&dropped, &mapping),
+            vec![Some(json!("r1")), None]
+        );
+
+        mapping.key = None;
+        assert!(null_endpoint_edge_keys(&dropped, &mapping).is_empty());
+        Ok(())
+    }
 }
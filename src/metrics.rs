@@ -1,11 +1,14 @@
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use chrono::Utc;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Request, Response, Server};
+use hyper::{Body, Request, Response, Server, StatusCode};
 use once_cell::sync::Lazy;
 
 pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::default);
@@ -17,6 +20,20 @@ pub struct MappingStats {
     pub rows_fetched: u64,
     pub rows_written: u64,
     pub rows_deleted: u64,
+    pub rows_skipped: u64,
+    pub missing_from_endpoints: u64,
+    pub missing_to_endpoints: u64,
+    /// Cumulative seconds spent per phase across runs.
+    pub fetch_seconds: f64,
+    pub map_seconds: f64,
+    pub write_seconds: f64,
+    /// Batch retries after transient write failures, and the seconds spent waiting on them.
+    pub batches_retried: u64,
+    pub retry_sleep_seconds: f64,
+    /// Wall-clock seconds the last successful run of the mapping took.
+    pub duration_seconds: f64,
+    /// When the mapping last completed successfully; `None` until it has.
+    pub last_success_unix_timestamp: Option<i64>,
 }
 
 #[derive(Default)]
@@ -26,7 +43,14 @@ pub struct Metrics {
     pub rows_fetched: AtomicU64,
     pub rows_written: AtomicU64,
     pub rows_deleted: AtomicU64,
+    pub rows_skipped: AtomicU64,
+    pub rows_unmatched: AtomicU64,
+    pub batches_retried: AtomicU64,
+    /// Total backoff sleep before batch retries, in milliseconds.
+    pub batch_retry_sleep_ms: AtomicU64,
     pub per_mapping: Mutex<HashMap<String, MappingStats>>,
+    /// Set once a sync run has completed successfully; drives `/readyz`.
+    pub ready: AtomicBool,
 }
 
 impl Metrics {
@@ -45,6 +69,27 @@ impl Metrics {
     pub fn add_rows_deleted(&self, n: u64) {
         self.rows_deleted.fetch_add(n, Ordering::Relaxed);
     }
+    pub fn add_rows_skipped(&self, n: u64) {
+        self.rows_skipped.fetch_add(n, Ordering::Relaxed);
+    }
+    pub fn add_rows_unmatched(&self, n: u64) {
+        self.rows_unmatched.fetch_add(n, Ordering::Relaxed);
+    }
+    /// Record one batch retry that waits `sleep` first, attributed to `mapping` when known.
+    pub fn record_batch_retry(&self, mapping: Option<&str>, sleep: Duration) {
+        self.batches_retried.fetch_add(1, Ordering::Relaxed);
+        self.batch_retry_sleep_ms
+            .fetch_add(sleep.as_millis() as u64, Ordering::Relaxed);
+        if let Some(mapping) = mapping {
+            self.with_mapping(mapping, |m| {
+                m.batches_retried += 1;
+                m.retry_sleep_seconds += sleep.as_secs_f64();
+            });
+        }
+    }
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed);
+    }
 
     fn with_mapping<F>(&self, mapping: &str, f: F)
     where
@@ -70,65 +115,477 @@ impl Metrics {
     pub fn add_mapping_rows_deleted(&self, mapping: &str, n: u64) {
         self.with_mapping(mapping, |m| m.rows_deleted += n);
     }
+    pub fn add_mapping_rows_skipped(&self, mapping: &str, n: u64) {
+        self.with_mapping(mapping, |m| m.rows_skipped += n);
+    }
+    pub fn add_mapping_missing_endpoints(&self, mapping: &str, from: u64, to: u64) {
+        self.with_mapping(mapping, |m| {
+            m.missing_from_endpoints += from;
+            m.missing_to_endpoints += to;
+        });
+    }
+    pub fn add_mapping_phase_seconds(&self, mapping: &str, fetch: f64, map: f64, write: f64) {
+        self.with_mapping(mapping, |m| {
+            m.fetch_seconds += fetch;
+            m.map_seconds += map;
+            m.write_seconds += write;
+        });
+    }
+    /// Record a successful mapping run that took `duration`, finishing now.
+    pub fn record_mapping_success(&self, mapping: &str, duration: Duration) {
+        let now = Utc::now().timestamp();
+        self.with_mapping(mapping, |m| {
+            m.duration_seconds = duration.as_secs_f64();
+            m.last_success_unix_timestamp = Some(now);
+        });
+    }
 }
 
-async fn handle_metrics(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
-    let m = &*METRICS;
-    let mut body = String::new();
+/// Check the request's bearer token. Always true when no token is configured.
+fn is_authorized(req: &Request<Body>, token: Option<&str>) -> bool {
+    let Some(expected) = token else {
+        return true;
+    };
 
+    let provided = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match provided {
+        // Compare without short-circuiting on the first differing byte.
+        Some(p) if p.len() == expected.len() => {
+            p.bytes()
+                .zip(expected.bytes())
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                == 0
+        }
+        _ => false,
+    }
+}
+
+fn unauthorized() -> Response<Body> {
+    let mut resp = Response::new(Body::from("unauthorized\n"));
+    *resp.status_mut() = StatusCode::UNAUTHORIZED;
+    resp.headers_mut().insert(
+        WWW_AUTHENTICATE,
+        hyper::header::HeaderValue::from_static("Bearer"),
+    );
+    resp
+}
+
+fn plain_response(status: StatusCode, body: &'static str) -> Response<Body> {
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = status;
+    resp
+}
+
+async fn handle_metrics(
+    req: Request<Body>,
+    auth_token: Arc<Option<String>>,
+) -> Result<Response<Body>, Infallible> {
+    Ok(route(&req, &METRICS, auth_token.as_deref()))
+}
+
+/// Dispatch a request. The probes are unauthenticated so Kubernetes can call them without
+/// credentials; metrics (at `/metrics`, and `/` for older scrape configs) need the token.
+fn route(req: &Request<Body>, m: &Metrics, auth_token: Option<&str>) -> Response<Body> {
+    match req.uri().path() {
+        "/healthz" => plain_response(StatusCode::OK, "ok\n"),
+        "/readyz" if m.ready.load(Ordering::Relaxed) => plain_response(StatusCode::OK, "ready\n"),
+        "/readyz" => plain_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "waiting for the first successful sync\n",
+        ),
+        "/metrics" | "/" if !is_authorized(req, auth_token) => unauthorized(),
+        "/metrics" | "/" => metrics_response(m),
+        _ => plain_response(StatusCode::NOT_FOUND, "not found\n"),
+    }
+}
+
+/// `Content-Type` of the metrics response: the Prometheus text exposition format.
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+fn metrics_response(m: &Metrics) -> Response<Body> {
+    let mut resp = Response::new(Body::from(render_metrics(m)));
+    resp.headers_mut().insert(
+        CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static(METRICS_CONTENT_TYPE),
+    );
+    resp
+}
+
+/// Append the `# HELP` and `# TYPE` lines that precede a metric family's samples.
+fn push_family(body: &mut String, name: &str, kind: &str, help: &str) {
     body.push_str(&format!(
-        "snowflake_to_falkordb_runs {}\n",
-        m.runs.load(Ordering::Relaxed),
-    ));
-    body.push_str(&format!(
-        "snowflake_to_falkordb_failed_runs {}\n",
-        m.failed_runs.load(Ordering::Relaxed),
-    ));
-    body.push_str(&format!(
-        "snowflake_to_falkordb_rows_fetched {}\n",
-        m.rows_fetched.load(Ordering::Relaxed),
-    ));
-    body.push_str(&format!(
-        "snowflake_to_falkordb_rows_written {}\n",
-        m.rows_written.load(Ordering::Relaxed),
+        "# HELP snowflake_to_falkordb_{name} {help}\n# TYPE snowflake_to_falkordb_{name} {kind}\n"
     ));
+}
+
+/// Escape a label value for the text format, where `\`, `"` and newlines must be written as
+/// `\\`, `\"` and `\n`.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Per-mapping counters: family name, help text and the formatted value.
+type MappingCounter = (&'static str, &'static str, fn(&MappingStats) -> String);
+
+const MAPPING_COUNTERS: &[MappingCounter] = &[
+    ("mapping_runs_total", "Runs of the mapping.", |s| {
+        s.runs.to_string()
+    }),
+    (
+        "mapping_failed_runs_total",
+        "Failed runs of the mapping.",
+        |s| s.failed_runs.to_string(),
+    ),
+    (
+        "mapping_rows_fetched_total",
+        "Rows fetched for the mapping.",
+        |s| s.rows_fetched.to_string(),
+    ),
+    (
+        "mapping_rows_written_total",
+        "Rows the mapping wrote to FalkorDB.",
+        |s| s.rows_written.to_string(),
+    ),
+    (
+        "mapping_rows_deleted_total",
+        "Rows the mapping deleted from FalkorDB.",
+        |s| s.rows_deleted.to_string(),
+    ),
+    (
+        "mapping_rows_skipped_total",
+        "Rows the mapping skipped.",
+        |s| s.rows_skipped.to_string(),
+    ),
+    (
+        "mapping_fetch_seconds_total",
+        "Seconds spent fetching the mapping's rows.",
+        |s| format!("{:.3}", s.fetch_seconds),
+    ),
+    (
+        "mapping_map_seconds_total",
+        "Seconds spent mapping the mapping's rows.",
+        |s| format!("{:.3}", s.map_seconds),
+    ),
+    (
+        "mapping_write_seconds_total",
+        "Seconds spent writing the mapping to FalkorDB.",
+        |s| format!("{:.3}", s.write_seconds),
+    ),
+    (
+        "mapping_batches_retried_total",
+        "Batch writes of the mapping retried after a transient failure.",
+        |s| s.batches_retried.to_string(),
+    ),
+    (
+        "mapping_batch_retry_sleep_seconds_total",
+        "Seconds the mapping spent in backoff before batch retries.",
+        |s| format!("{:.3}", s.retry_sleep_seconds),
+    ),
+];
+
+/// Render `m` in the Prometheus text format, with `# HELP`/`# TYPE` lines for every family
+/// and a `_total` suffix on counters.
+fn render_metrics(m: &Metrics) -> String {
+    let mut body = String::new();
+
+    let counters = [
+        ("runs_total", "Sync runs started.", &m.runs),
+        (
+            "failed_runs_total",
+            "Sync runs that failed.",
+            &m.failed_runs,
+        ),
+        (
+            "rows_fetched_total",
+            "Rows fetched from sources.",
+            &m.rows_fetched,
+        ),
+        (
+            "rows_written_total",
+            "Rows written to FalkorDB.",
+            &m.rows_written,
+        ),
+        (
+            "rows_deleted_total",
+            "Rows deleted from FalkorDB.",
+            &m.rows_deleted,
+        ),
+        (
+            "rows_skipped_total",
+            "Rows skipped instead of written.",
+            &m.rows_skipped,
+        ),
+        (
+            "rows_unmatched_total",
+            "Rows of a shared source that no mapping condition selected.",
+            &m.rows_unmatched,
+        ),
+        (
+            "batches_retried_total",
+            "Batch writes retried after a transient failure.",
+            &m.batches_retried,
+        ),
+    ];
+    for (name, help, value) in counters {
+        push_family(&mut body, name, "counter", help);
+        body.push_str(&format!(
+            "snowflake_to_falkordb_{name} {}\n",
+            value.load(Ordering::Relaxed)
+        ));
+    }
+    push_family(
+        &mut body,
+        "batch_retry_sleep_seconds_total",
+        "counter",
+        "Seconds spent in backoff before batch retries.",
+    );
     body.push_str(&format!(
-        "snowflake_to_falkordb_rows_deleted {}\n",
-        m.rows_deleted.load(Ordering::Relaxed),
+        "snowflake_to_falkordb_batch_retry_sleep_seconds_total {:.3}\n",
+        m.batch_retry_sleep_ms.load(Ordering::Relaxed) as f64 / 1000.0,
     ));
 
     let guard = m.per_mapping.lock().unwrap();
+    for (name, help, value) in MAPPING_COUNTERS {
+        push_family(&mut body, name, "counter", help);
+        for (mapping, stats) in guard.iter() {
+            let mapping = escape_label_value(mapping);
+            body.push_str(&format!(
+                "snowflake_to_falkordb_{name}{{mapping=\"{mapping}\"}} {}\n",
+                value(stats)
+            ));
+        }
+    }
+
+    push_family(
+        &mut body,
+        "mapping_missing_endpoints_total",
+        "counter",
+        "Edge endpoints that endpoint checks found missing from the graph.",
+    );
     for (name, stats) in guard.iter() {
+        let name = escape_label_value(name);
         body.push_str(&format!(
-            "snowflake_to_falkordb_mapping_runs{{mapping=\"{}\"}} {}\n",
-            name, stats.runs
-        ));
-        body.push_str(&format!(
-            "snowflake_to_falkordb_mapping_failed_runs{{mapping=\"{}\"}} {}\n",
-            name, stats.failed_runs
+            "snowflake_to_falkordb_mapping_missing_endpoints_total{{mapping=\"{}\",side=\"from\"}} {}\n",
+            name, stats.missing_from_endpoints
         ));
         body.push_str(&format!(
-            "snowflake_to_falkordb_mapping_rows_fetched{{mapping=\"{}\"}} {}\n",
-            name, stats.rows_fetched
-        ));
-        body.push_str(&format!(
-            "snowflake_to_falkordb_mapping_rows_written{{mapping=\"{}\"}} {}\n",
-            name, stats.rows_written
-        ));
-        body.push_str(&format!(
-            "snowflake_to_falkordb_mapping_rows_deleted{{mapping=\"{}\"}} {}\n",
-            name, stats.rows_deleted
+            "snowflake_to_falkordb_mapping_missing_endpoints_total{{mapping=\"{}\",side=\"to\"}} {}\n",
+            name, stats.missing_to_endpoints
         ));
     }
 
-    Ok(Response::new(Body::from(body)))
+    push_family(
+        &mut body,
+        "mapping_duration_seconds",
+        "gauge",
+        "Wall-clock seconds of the mapping's last successful run.",
+    );
+    for (name, stats) in guard.iter() {
+        let name = escape_label_value(name);
+        if stats.last_success_unix_timestamp.is_some() {
+            body.push_str(&format!(
+                "snowflake_to_falkordb_mapping_duration_seconds{{mapping=\"{}\"}} {:.3}\n",
+                name, stats.duration_seconds
+            ));
+        }
+    }
+    push_family(
+        &mut body,
+        "mapping_last_success_unix_timestamp",
+        "gauge",
+        "Unix time the mapping last completed successfully.",
+    );
+    for (name, stats) in guard.iter() {
+        let name = escape_label_value(name);
+        if let Some(ts) = stats.last_success_unix_timestamp {
+            body.push_str(&format!(
+                "snowflake_to_falkordb_mapping_last_success_unix_timestamp{{mapping=\"{}\"}} {}\n",
+                name, ts
+            ));
+        }
+    }
+
+    body
 }
 
-pub async fn serve_metrics(addr: SocketAddr) {
-    let make_svc =
-        make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handle_metrics)) });
+/// Serve metrics over HTTP. If `auth_token` is set, requests without a matching
+/// `Authorization: Bearer` header get a 401.
+pub async fn serve_metrics(addr: SocketAddr, auth_token: Option<String>) {
+    let auth_token = Arc::new(auth_token);
+    let make_svc = make_service_fn(move |_conn| {
+        let auth_token = auth_token.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle_metrics(req, auth_token.clone())
+            }))
+        }
+    });
 
     if let Err(e) = Server::bind(&addr).serve(make_svc).await {
         tracing::error!(error = %e, "metrics server error");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with_auth(header: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri("/metrics");
+        if let Some(h) = header {
+            builder = builder.header(AUTHORIZATION, h);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn metrics_require_bearer_token_when_configured() {
+        let token = Arc::new(Some("s3cret".to_string()));
+
+        let resp = handle_metrics(request_with_auth(None), token.clone())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = handle_metrics(request_with_auth(Some("Bearer wrong")), token.clone())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = handle_metrics(request_with_auth(Some("Bearer s3cret")), token)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    fn get(path: &str) -> Request<Body> {
+        Request::builder().uri(path).body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn probes_and_metrics_are_routed_by_path() {
+        let m = Metrics::default();
+        let token = Some("s3cret");
+
+        assert_eq!(route(&get("/healthz"), &m, token).status(), StatusCode::OK);
+        assert_eq!(
+            route(&get("/readyz"), &m, token).status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        m.mark_ready();
+        assert_eq!(route(&get("/readyz"), &m, token).status(), StatusCode::OK);
+
+        assert_eq!(route(&get("/metrics"), &m, None).status(), StatusCode::OK);
+        assert_eq!(route(&get("/"), &m, None).status(), StatusCode::OK);
+        assert_eq!(
+            route(&get("/metrics"), &m, token).status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            route(&get("/metricsz"), &m, None).status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn mapping_duration_and_last_success_are_rendered() {
+        let m = Metrics::default();
+        m.inc_mapping_run("pending");
+        m.record_mapping_success("orders", Duration::from_millis(1500));
+
+        let body = render_metrics(&m);
+        assert!(body.contains("# TYPE snowflake_to_falkordb_mapping_duration_seconds gauge\n"));
+        assert!(body.contains(
+            "snowflake_to_falkordb_mapping_duration_seconds{mapping=\"orders\"} 1.500\n"
+        ));
+        assert!(body
+            .contains("# TYPE snowflake_to_falkordb_mapping_last_success_unix_timestamp gauge\n"));
+        assert!(body.contains(
+            "snowflake_to_falkordb_mapping_last_success_unix_timestamp{mapping=\"orders\"} "
+        ));
+        // A mapping that never succeeded has no samples for either gauge.
+        assert!(!body.contains("_duration_seconds{mapping=\"pending\"}"));
+        assert!(!body.contains("_last_success_unix_timestamp{mapping=\"pending\"}"));
+    }
+
+    #[test]
+    fn mapping_label_values_are_escaped() {
+        let m = Metrics::default();
+        m.add_mapping_missing_endpoints("a\\b \"c\"\nd", 1, 0);
+
+        let body = render_metrics(&m);
+        assert!(body.contains(
+            "snowflake_to_falkordb_mapping_runs_total{mapping=\"a\\\\b \\\"c\\\"\\nd\"} 0\n"
+        ));
+        assert!(body.contains(
+            "snowflake_to_falkordb_mapping_missing_endpoints_total{mapping=\"a\\\\b \\\"c\\\"\\nd\",side=\"from\"} 1\n"
+        ));
+        // The raw newline never reaches the output, so every sample stays on one line.
+        assert!(body.lines().all(|line| !line.ends_with("a\\b \"c\"")));
+    }
+
+    #[test]
+    fn batch_retries_are_counted_globally_and_per_mapping() {
+        let m = Metrics::default();
+        m.record_batch_retry(Some("orders"), Duration::from_millis(250));
+        m.record_batch_retry(None, Duration::from_millis(500));
+
+        let body = render_metrics(&m);
+        assert!(body.contains("# TYPE snowflake_to_falkordb_batches_retried_total counter\n"));
+        assert!(body.contains("snowflake_to_falkordb_batches_retried_total 2\n"));
+        assert!(body.contains("snowflake_to_falkordb_batch_retry_sleep_seconds_total 0.750\n"));
+        assert!(body.contains(
+            "snowflake_to_falkordb_mapping_batches_retried_total{mapping=\"orders\"} 1\n"
+        ));
+        assert!(body.contains(
+            "snowflake_to_falkordb_mapping_batch_retry_sleep_seconds_total{mapping=\"orders\"} 0.250\n"
+        ));
+    }
+
+    #[test]
+    fn every_family_has_help_and_type_lines() {
+        let m = Metrics::default();
+        m.inc_runs();
+        m.add_mapping_missing_endpoints("orders", 0, 12);
+
+        let resp = route(&get("/metrics"), &m, None);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "text/plain; version=0.0.4; charset=utf-8"
+        );
+        let body = render_metrics(&m);
+        assert!(body.contains("# TYPE snowflake_to_falkordb_runs_total counter\n"));
+        assert!(body.contains("snowflake_to_falkordb_runs_total 1\n"));
+        assert!(body.contains(
+            "snowflake_to_falkordb_mapping_missing_endpoints_total{mapping=\"orders\",side=\"to\"} 12\n"
+        ));
+        // Every sample belongs to a family declared just before it, and counters end in _total.
+        let mut family = "";
+        for line in body.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert!(kind == "gauge" || name.ends_with("_total"), "{line}");
+                family = name;
+            } else if !line.starts_with("# HELP ") {
+                let name = line.split(['{', ' ']).next().unwrap();
+                assert_eq!(name, family, "{line}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn metrics_are_open_without_token() {
+        let resp = handle_metrics(request_with_auth(None), Arc::new(None))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
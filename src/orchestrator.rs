@@ -1,18 +1,77 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tokio::sync::watch;
 
-use crate::config::{Config, EntityMapping, NodeMappingConfig};
-use crate::mapping::{map_rows_to_edges, map_rows_to_nodes};
+use crate::config::{
+    problem_report, CombinedMappingConfig, CommonMappingFields, Config, DeadLetterConfig,
+    EdgeEndpointMatch, EdgeMappingConfig, EntityMapping, GuardAction, MaxRowsAction, Mode,
+    NodeMappingConfig, UnmatchedRowsAction, WhenCondition,
+};
+use crate::dead_letter::{append_skipped_rows, begin_run};
+use crate::emit::{CypherEmitter, DryRunLog, StatementSink};
+use crate::mapping::{
+    map_edge_rows, map_rows_to_combined, map_rows_to_combined_skipping, map_rows_to_edges_skipping,
+    map_rows_to_nodes, map_rows_to_nodes_skipping, null_endpoint_edge_keys, parse_timestamp,
+    row_matches_when, MappedEdgeRows, SkippedRow,
+};
 use crate::metrics::METRICS;
-use crate::sink::MappedNode;
+use crate::sink::{node_label_clause, MappedNode};
 use crate::sink_async::{
-    connect_falkordb_async, delete_edges_in_batches_async, delete_nodes_in_batches_async,
-    write_edges_in_batches_async, write_nodes_in_batches_async, MappedEdge,
+    combined_batch_statement, combined_type_pattern, connect_falkordb_async,
+    count_missing_endpoints, delete_combined_in_batches_async, delete_edges_in_batches_async,
+    delete_nodes_in_batches_async, distinct_endpoints, edge_batch_ranges, edge_batch_statement,
+    edge_key_text, edge_type_pattern, fetch_graph_schema, is_connection_error,
+    node_batch_statements, node_key_text, query_annotation, query_count, reap_tombstones,
+    reconcile_edge_deletes, reconcile_node_deletes, warm_combined_plans, warm_edge_plans,
+    warm_node_plans, with_query_timeout, write_combined_in_batches_async,
+    write_edges_in_batches_async, write_nodes_concurrently_async, FalkorConnection, MappedCombined,
+    MappedEdge, WriteOptions,
+};
+use crate::source::{
+    build_sql, fetch_rows_for_mapping, fetch_stream_rows_for_consume, open_row_stream, LogicalRow,
+    RowStream, StreamConsumer,
+};
+use crate::state::{
+    load_configured_watermarks, load_watermarks_with_retry, purge_graph_cypher, save_watermark,
+    StateConnections,
 };
-use crate::source::{fetch_rows_for_mapping, LogicalRow};
-use crate::state::{load_watermarks, save_watermarks};
+use crate::transform::{apply_transforms, transform_problem};
+
+/// Wall-clock time a mapping spent in each phase: fetching from the source, mapping rows in
+/// Rust, and writing (including deletes and endpoint checks) to FalkorDB.
+#[derive(Debug, Default)]
+struct PhaseTimings {
+    fetch: Duration,
+    map: Duration,
+    write: Duration,
+}
+
+impl PhaseTimings {
+    /// Log the breakdown and add it to the per-mapping phase metrics.
+    fn record(&self, mapping: &str) {
+        tracing::info!(
+            mapping = %mapping,
+            fetch_secs = self.fetch.as_secs_f64(),
+            map_secs = self.map.as_secs_f64(),
+            write_secs = self.write.as_secs_f64(),
+            "Mapping phase timings",
+        );
+        METRICS.add_mapping_phase_seconds(
+            mapping,
+            self.fetch.as_secs_f64(),
+            self.map.as_secs_f64(),
+            self.write.as_secs_f64(),
+        );
+    }
+}
 
 fn compute_max_watermark(rows: &[LogicalRow], updated_at_column: &str) -> Option<DateTime<Utc>> {
     use chrono::{NaiveDateTime, TimeZone};
@@ -45,427 +104,3512 @@ fn compute_max_watermark(rows: &[LogicalRow], updated_at_column: &str) -> Option
     max_ts
 }
 
-fn partition_by_deleted<'a>(
-    rows: &'a [LogicalRow],
-    delta: &crate::config::DeltaSpec,
+/// Split rows into active and deleted ones by the delta spec's deleted flag. Without a delta
+/// spec (or flag) every row is active.
+fn partition_by_deleted(
+    rows: Vec<LogicalRow>,
+    delta: Option<&crate::config::DeltaSpec>,
 ) -> (Vec<LogicalRow>, Vec<LogicalRow>) {
-    let mut active = Vec::new();
-    let mut deleted = Vec::new();
-
-    if let Some(flag_col) = &delta.deleted_flag_column {
-        if let Some(flag_val) = &delta.deleted_flag_value {
-            for row in rows {
-                let is_deleted = row.get(flag_col).map(|v| v == flag_val).unwrap_or(false);
-                if is_deleted {
-                    deleted.push(row.clone());
-                } else {
-                    active.push(row.clone());
-                }
-            }
-        } else {
-            // No explicit value configured; treat as active-only for now.
-            active.extend(rows.iter().cloned());
-        }
-    } else {
-        active.extend(rows.iter().cloned());
-    }
+    let Some(delta) = delta else {
+        return (rows, Vec::new());
+    };
+    let flag = delta
+        .deleted_flag_column
+        .as_ref()
+        .zip(delta.deleted_flag_value.as_ref());
+    // No explicit value configured; treat as active-only for now.
+    let Some((flag_col, flag_val)) = flag else {
+        return (rows, Vec::new());
+    };
 
-    (active, deleted)
+    rows.into_iter().partition(|row| {
+        row.get(flag_col)
+            .is_none_or(|v| !deleted_flag_matches(v, flag_val, delta))
+    })
 }
 
-async fn purge_graph(graph: &mut falkordb::AsyncGraph) -> Result<()> {
-    tracing::warn!("Purging entire graph prior to load");
-    graph.query("MATCH (n) DETACH DELETE n").execute().await?;
-    Ok(())
+/// Whether a row's deleted-flag `value` equals the configured `flag`: exact JSON equality,
+/// relaxed by `deleted_flag_ignore_case` and `deleted_flag_coerce`.
+fn deleted_flag_matches(
+    value: &JsonValue,
+    flag: &JsonValue,
+    delta: &crate::config::DeltaSpec,
+) -> bool {
+    if value == flag {
+        return true;
+    }
+    if let (JsonValue::String(a), JsonValue::String(b)) = (value, flag) {
+        return delta.deleted_flag_ignore_case && a.to_lowercase() == b.to_lowercase();
+    }
+    delta.deleted_flag_coerce && (coerced_flag_eq(value, flag) || coerced_flag_eq(flag, value))
 }
 
-/// Ensure indexes exist for node key properties used in MERGE/MATCH.
-///
-/// For each node mapping, we create an index on (labels, key.property). We de-duplicate
-/// by (labels, property) combination and treat failures as non-fatal (for example,
-/// when the index already exists on the server).
-async fn ensure_node_indexes(
-    graph: &mut falkordb::AsyncGraph,
-    mappings: &[EntityMapping],
-) -> Result<()> {
-    let mut seen: HashSet<(String, String)> = HashSet::new();
-
-    for mapping in mappings {
-        if let EntityMapping::Node(node_cfg) = mapping {
-            if node_cfg.labels.is_empty() {
-                continue;
-            }
-
-            let label_clause = node_cfg.labels.join(":");
-            let prop = node_cfg.key.property.clone();
-            let key = (label_clause.clone(), prop.clone());
-
-            if !seen.insert(key) {
-                continue;
-            }
+/// `a == b` across JSON types, for `deleted_flag_coerce`. Only checks `a` in the role of the
+/// number or boolean; callers try both orders.
+fn coerced_flag_eq(a: &JsonValue, b: &JsonValue) -> bool {
+    match (a, b) {
+        (JsonValue::Number(n), JsonValue::Number(m)) => n.as_f64() == m.as_f64(),
+        (JsonValue::Number(n), JsonValue::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .is_ok_and(|parsed| n.as_f64() == Some(parsed)),
+        (JsonValue::Bool(v), JsonValue::String(s)) => {
+            s.trim()
+                .eq_ignore_ascii_case(if *v { "true" } else { "false" })
+        }
+        (JsonValue::Bool(v), JsonValue::Number(n)) => {
+            n.as_f64() == Some(if *v { 1.0 } else { 0.0 })
+        }
+        _ => false,
+    }
+}
 
-            let cypher = format!(
-                "CREATE INDEX ON :{labels}({prop})",
-                labels = label_clause,
-                prop = prop
-            );
+/// The watermark a mapping fetches from: the stored one, or for an incremental mapping with
+/// none stored and `delta.initial_full_load: false`, the current time, so the first run skips
+/// existing rows and only later changes are pulled. `None` means a full load.
+fn starting_watermark(common: &CommonMappingFields, stored: Option<&String>) -> Option<String> {
+    if let Some(stored) = stored {
+        return Some(stored.clone());
+    }
+    let skip_existing = matches!(common.mode, Mode::Incremental)
+        && common
+            .delta
+            .as_ref()
+            .is_some_and(|d| d.initial_full_load == Some(false));
+    skip_existing.then(|| Utc::now().to_rfc3339())
+}
 
+/// The watermark a mapping fetches from in this run: its `--from-watermark` override (see
+/// [`RunOptions::override_watermarks`]) if there is one, otherwise [`starting_watermark`].
+fn run_watermark(
+    opts: &RunOptions,
+    common: &CommonMappingFields,
+    stored: Option<&String>,
+) -> Option<String> {
+    match opts.watermark_overrides.get(&common.name) {
+        Some(from) => {
             tracing::info!(
-                mapping = %node_cfg.common.name,
-                labels = %label_clause,
-                property = %prop,
-                "Ensuring index for node label on key property",
+                mapping = %common.name,
+                watermark = %from,
+                stored = ?stored,
+                "Fetching from overridden watermark",
             );
-
-            if let Err(e) = graph.query(&cypher).execute().await {
-                tracing::warn!(
-                    mapping = %node_cfg.common.name,
-                    labels = %label_clause,
-                    property = %prop,
-                    error = %e,
-                    "Failed to create index for node label (it may already exist)",
-                );
-            }
+            Some(from.clone())
         }
+        None => starting_watermark(common, stored),
     }
+}
 
-    Ok(())
+/// Whether a fetch reads the whole source: `mode: full`, or an incremental mapping's first
+/// run, which has no watermark to resume from.
+fn is_full_load(common: &CommonMappingFields, watermark: Option<&str>) -> bool {
+    matches!(common.mode, Mode::Full) || watermark.is_none()
 }
 
-async fn purge_mapping(
-    graph: &mut falkordb::AsyncGraph,
-    mapping: &EntityMapping,
-    node_by_name: &HashMap<&str, &NodeMappingConfig>,
+/// Check a full load's fetched row count against the mapping's `min_rows`. Incremental runs
+/// resuming from a watermark are exempt, since zero new rows is normal there.
+fn check_min_rows(
+    common: &CommonMappingFields,
+    fetched: usize,
+    watermark: Option<&str>,
 ) -> Result<()> {
-    match mapping {
-        EntityMapping::Node(node_cfg) => {
-            let label_clause = node_cfg.labels.join(":");
-            let cypher = format!("MATCH (n:{}) DETACH DELETE n", label_clause);
-            tracing::warn!(mapping = %node_cfg.common.name, "Purging node mapping");
-            graph.query(&cypher).execute().await?;
-        }
-        EntityMapping::Edge(edge_cfg) => {
-            let from_node = node_by_name
-                .get(edge_cfg.from.node_mapping.as_str())
-                .copied()
-                .ok_or_else(|| {
-                    anyhow!(
-                        "Edge mapping '{}' refers to unknown from.node_mapping '{}'",
-                        edge_cfg.common.name,
-                        edge_cfg.from.node_mapping
-                    )
-                })?;
-            let to_node = node_by_name
-                .get(edge_cfg.to.node_mapping.as_str())
-                .copied()
-                .ok_or_else(|| {
-                    anyhow!(
-                        "Edge mapping '{}' refers to unknown to.node_mapping '{}'",
-                        edge_cfg.common.name,
-                        edge_cfg.to.node_mapping
-                    )
-                })?;
-
-            let from_labels = edge_cfg
-                .from
-                .label_override
-                .clone()
-                .unwrap_or_else(|| from_node.labels.clone());
-            let to_labels = edge_cfg
-                .to
-                .label_override
-                .clone()
-                .unwrap_or_else(|| to_node.labels.clone());
+    let min_rows = common.min_rows.unwrap_or(0);
+    if !is_full_load(common, watermark) || fetched >= min_rows {
+        return Ok(());
+    }
 
-            let from_label = from_labels.join(":");
-            let to_label = to_labels.join(":");
-            let cypher = format!(
-                "MATCH (src:{from})-[r:{rel}]->(tgt:{to}) DELETE r",
-                from = from_label,
-                to = to_label,
-                rel = edge_cfg.relationship,
+    match common.min_rows_action {
+        GuardAction::Fail => Err(anyhow!(
+            "Mapping '{}' fetched {} rows, fewer than min_rows {}; check the source table and filters",
+            common.name,
+            fetched,
+            min_rows
+        )),
+        GuardAction::Warn => {
+            tracing::warn!(
+                mapping = %common.name,
+                rows = fetched,
+                min_rows,
+                "Fetched fewer rows than min_rows; check the source table and filters",
             );
-            tracing::warn!(mapping = %edge_cfg.common.name, "Purging edge mapping");
-            graph.query(&cypher).execute().await?;
+            Ok(())
         }
     }
-    Ok(())
 }
 
-/// Run a single full or incremental synchronization over all mappings.
-pub async fn run_once(
-    cfg: &Config,
-    purge_graph_flag: bool,
-    purge_mappings: &[String],
-) -> Result<()> {
-    let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
-    let mut watermarks = load_watermarks(cfg)?;
+/// Whether a `reconcile_deletes` pass may delete whatever is missing from `source_keys`.
+/// Not when `skipped` rows were left out of the keys, since their nodes or relationships
+/// would be deleted although the source still has them, and not when no keys were read at
+/// all unless `min_rows` is set, since an empty fetch is more often a broken source than an
+/// empty one.
+fn reconcile_allowed(
+    common: &CommonMappingFields,
+    source_keys: &HashSet<String>,
+    skipped: usize,
+) -> bool {
+    if skipped > 0 {
+        tracing::warn!(
+            mapping = %common.name,
+            skipped,
+            "Not reconciling deletes: skipped rows' keys are unknown",
+        );
+        return false;
+    }
+    if source_keys.is_empty() && common.min_rows.is_none() {
+        tracing::warn!(
+            mapping = %common.name,
+            "Not reconciling deletes: no keys were fetched; set min_rows to reconcile an empty source",
+        );
+        return false;
+    }
+    true
+}
 
-    METRICS.inc_runs();
+/// Rows fetched for mappings with a `when` condition, keyed by source and watermark, so
+/// mappings that split one heterogeneous source read it only once per run.
+#[derive(Default)]
+struct SharedFetches {
+    rows: HashMap<(String, Option<String>), Vec<LogicalRow>>,
+    /// Open stream transactions of `stream_consume` mappings, committed once the mapping's
+    /// rows are written.
+    streams: HashMap<String, StreamConsumer>,
+}
 
-    // Index node mappings by name so edges can look up endpoint labels.
-    let mut node_by_name: HashMap<&str, &NodeMappingConfig> = HashMap::new();
-    for mapping in &cfg.mappings {
-        if let EntityMapping::Node(node) = mapping {
-            node_by_name.insert(node.common.name.as_str(), node);
+impl SharedFetches {
+    /// What a mapping's fetch reads: the file, the S3 object or prefix, or the SQL sent to
+    /// Snowflake. Mappings with the same key read the same rows.
+    fn source_key(common: &CommonMappingFields, watermark: Option<&str>) -> Result<String> {
+        if let Some(file) = &common.source.file {
+            return Ok(format!("file:{}", file));
+        }
+        if let Some(s3) = &common.source.s3 {
+            return Ok(match (&s3.key, &s3.prefix) {
+                (Some(key), _) => format!("s3://{}/{}", s3.bucket, key),
+                (None, prefix) => {
+                    format!("s3://{}/{}*", s3.bucket, prefix.as_deref().unwrap_or(""))
+                }
+            });
         }
+        build_sql(common, watermark)
     }
 
-    // Handle purge options
-    if purge_graph_flag {
-        purge_graph(&mut graph).await?;
-    } else if !purge_mappings.is_empty() {
-        for name in purge_mappings {
-            if let Some(mapping) = cfg.mappings.iter().find(|m| match m {
-                EntityMapping::Node(n) => &n.common.name == name,
-                EntityMapping::Edge(e) => &e.common.name == name,
-            }) {
-                purge_mapping(&mut graph, mapping, &node_by_name).await?;
+    /// Start reading a mapping's rows `chunk_rows` at a time. Mappings without `when` stream
+    /// from their own source; those with one reuse an earlier fetch of the same source, which
+    /// is kept whole for the other mappings, and stream only their matching rows.
+    /// `stream_consume` reads happen in one query inside the open transaction.
+    async fn open<'a>(
+        &mut self,
+        cfg: &Config,
+        common: &'a CommonMappingFields,
+        watermark: Option<&str>,
+        chunk_rows: usize,
+    ) -> Result<MappingRows<'a>> {
+        let stream = if common.source.stream_consume {
+            let (rows, consumer) = fetch_stream_rows_for_consume(cfg, common).await?;
+            self.streams.insert(common.name.clone(), consumer);
+            RowStream::from_rows(rows, chunk_rows)
+        } else if let Some(when) = &common.when {
+            let key = (
+                Self::source_key(common, watermark)?,
+                watermark.map(str::to_string),
+            );
+            if !self.rows.contains_key(&key) {
+                let rows = fetch_rows_for_mapping(cfg, common, watermark).await?;
+                self.rows.insert(key.clone(), rows);
             } else {
-                tracing::warn!(mapping = %name, "Requested purge for unknown mapping");
+                tracing::info!(mapping = %common.name, "Reusing rows fetched for an earlier mapping");
             }
+            let matching = self.rows[&key]
+                .iter()
+                .filter(|row| row_matches_when(row, when))
+                .cloned()
+                .collect();
+            RowStream::from_rows(matching, chunk_rows)
+        } else {
+            open_row_stream(cfg, common, watermark, chunk_rows).await?
+        };
+
+        Ok(MappingRows {
+            common,
+            stream,
+            fetched: 0,
+            newest: None,
+            pending: VecDeque::new(),
+            handed_out: 0,
+            chunk_start: 0,
+        })
+    }
+
+    /// Advance the stream of a `stream_consume` mapping whose rows have been written.
+    async fn commit_stream(&mut self, mapping: &str) -> Result<()> {
+        if let Some(consumer) = self.streams.remove(mapping) {
+            consumer.commit().await?;
+            tracing::info!(mapping = %mapping, "Advanced Snowflake stream offset");
         }
+        Ok(())
     }
+}
 
-    // Ensure we have indexes on node key properties before writing data. This improves
-    // MERGE/MATCH performance and is safe to run repeatedly.
-    ensure_node_indexes(&mut graph, &cfg.mappings).await?;
+/// A mapping's rows as they are read, chunk by chunk, with its `transforms` applied to each
+/// chunk. Tracks the row count and the newest `updated_at` seen so far, so the watermark
+/// saved after the last chunk covers all of them.
+struct MappingRows<'a> {
+    common: &'a CommonMappingFields,
+    stream: RowStream,
+    fetched: usize,
+    newest: Option<DateTime<Utc>>,
+    /// Chunks read ahead by [`MappingRows::check_guards`] and not handed out yet.
+    pending: VecDeque<Vec<LogicalRow>>,
+    handed_out: usize,
+    /// Position in the fetch of the first row of the chunk handed out last.
+    chunk_start: usize,
+}
 
-    let batch_size = cfg.falkordb.max_unwind_batch_size.unwrap_or(1000).max(1);
+impl MappingRows<'_> {
+    async fn next_chunk(&mut self) -> Result<Option<Vec<LogicalRow>>> {
+        let chunk = match self.pending.pop_front() {
+            Some(chunk) => Some(chunk),
+            None => self.read_chunk().await?,
+        };
+        if let Some(chunk) = &chunk {
+            self.chunk_start = self.handed_out;
+            self.handed_out += chunk.len();
+        }
+        Ok(chunk)
+    }
 
-    // For now run mappings sequentially; concurrency can be added later.
-    for mapping in &cfg.mappings {
-        match mapping {
-            EntityMapping::Node(node_cfg) => {
-                tracing::info!(mapping = %node_cfg.common.name, "Processing node mapping");
-                METRICS.inc_mapping_run(&node_cfg.common.name);
-
-                let watermark = watermarks.get(&node_cfg.common.name).map(|s| s.as_str());
-                let rows = fetch_rows_for_mapping(cfg, &node_cfg.common, watermark).await?;
-                METRICS.add_rows_fetched(rows.len() as u64);
-                METRICS.add_mapping_rows_fetched(&node_cfg.common.name, rows.len() as u64);
-                tracing::info!(mapping = %node_cfg.common.name, rows = rows.len(), "Fetched rows");
-
-                let (active_rows, deleted_rows) = if let Some(delta) = &node_cfg.common.delta {
-                    partition_by_deleted(&rows, delta)
-                } else {
-                    (rows.clone(), Vec::new())
-                };
+    /// Read ahead far enough to apply the row-count guards before anything is written, then
+    /// check `min_rows`. A full load reads up to `min_rows` rows ahead, and with
+    /// `max_rows_action: fail` rows are read past `max_rows_per_mapping`, so an oversized
+    /// fetch fails here rather than after part of it was loaded. The rows read ahead are held
+    /// until handed out, so memory grows to the larger of the two.
+    async fn check_guards(&mut self, cfg: &Config, watermark: Option<&str>) -> Result<()> {
+        let mut ahead = 0;
+        if is_full_load(self.common, watermark) {
+            ahead = self.common.min_rows.unwrap_or(0);
+        }
+        if let (Some(max), MaxRowsAction::Fail) = (cfg.max_rows_per_mapping, cfg.max_rows_action) {
+            ahead = ahead.max(max.saturating_add(1));
+        }
+        while self.fetched < ahead {
+            let Some(chunk) = self.read_chunk().await? else {
+                break;
+            };
+            self.pending.push_back(chunk);
+        }
+        check_min_rows(self.common, self.fetched, watermark)
+    }
 
-                let nodes: Vec<MappedNode> = map_rows_to_nodes(&active_rows, node_cfg)?;
-                METRICS.add_rows_written(nodes.len() as u64);
-                METRICS.add_mapping_rows_written(&node_cfg.common.name, nodes.len() as u64);
-                tracing::info!(mapping = %node_cfg.common.name, rows = nodes.len(), "Writing nodes");
-                write_nodes_in_batches_async(&mut graph, node_cfg, nodes, batch_size, 3).await?;
+    async fn read_chunk(&mut self) -> Result<Option<Vec<LogicalRow>>> {
+        let Some(mut rows) = self.stream.next_chunk().await? else {
+            return Ok(None);
+        };
+        apply_transforms(&self.common.transforms, &mut rows)
+            .with_context(|| format!("Transforms of mapping '{}' failed", self.common.name))?;
+        self.fetched += rows.len();
+        if let Some(delta) = &self.common.delta {
+            self.newest = self
+                .newest
+                .max(compute_max_watermark(&rows, &delta.updated_at_column));
+        }
+        Ok(Some(rows))
+    }
 
-                if !deleted_rows.is_empty() {
-                    let deleted_nodes: Vec<MappedNode> =
-                        map_rows_to_nodes(&deleted_rows, node_cfg)?;
-                    METRICS.add_rows_deleted(deleted_nodes.len() as u64);
-                    METRICS.add_mapping_rows_deleted(
-                        &node_cfg.common.name,
-                        deleted_nodes.len() as u64,
-                    );
-                    tracing::info!(mapping = %node_cfg.common.name, rows = deleted_nodes.len(), "Deleting nodes");
-                    delete_nodes_in_batches_async(
-                        &mut graph,
-                        node_cfg,
-                        deleted_nodes,
-                        batch_size,
-                        3,
-                    )
-                    .await?;
-                }
+    /// All remaining rows as one chunk, for mappings that need to see every row at once.
+    async fn rest(&mut self) -> Result<Option<Vec<LogicalRow>>> {
+        let start = self.handed_out;
+        let mut all = Vec::new();
+        while let Some(chunk) = self.next_chunk().await? {
+            all.extend(chunk);
+        }
+        self.chunk_start = start;
+        Ok((!all.is_empty()).then_some(all))
+    }
+}
 
-                if let Some(delta) = &node_cfg.common.delta {
-                    if let Some(max_ts) = compute_max_watermark(&rows, &delta.updated_at_column) {
-                        watermarks.insert(node_cfg.common.name.clone(), max_ts.to_rfc3339());
-                        save_watermarks(cfg, &watermarks)?;
-                    }
+/// Rows that none of the given `when` conditions select.
+fn unmatched_rows<'a>(
+    rows: &'a [LogicalRow],
+    conditions: &[&WhenCondition],
+) -> Vec<&'a LogicalRow> {
+    rows.iter()
+        .filter(|row| !conditions.iter().any(|when| row_matches_when(row, when)))
+        .collect()
+}
+
+/// After all mappings ran, count (and optionally dead-letter) shared-source rows that no
+/// mapping's `when` condition selected.
+fn report_unmatched_rows(cfg: &Config, shared: &SharedFetches) -> Result<()> {
+    if cfg.unmatched_rows == UnmatchedRowsAction::Ignore {
+        return Ok(());
+    }
+
+    for ((source_key, watermark), rows) in &shared.rows {
+        let mut names = Vec::new();
+        let mut conditions = Vec::new();
+        for mapping in &cfg.mappings {
+            let common = mapping.common();
+            if let Some(when) = &common.when {
+                if SharedFetches::source_key(common, watermark.as_deref())? == *source_key {
+                    names.push(common.name.as_str());
+                    conditions.push(when);
                 }
             }
-            EntityMapping::Edge(edge_cfg) => {
-                tracing::info!(mapping = %edge_cfg.common.name, "Processing edge mapping");
-                METRICS.inc_mapping_run(&edge_cfg.common.name);
-
-                let from_node = node_by_name
-                    .get(edge_cfg.from.node_mapping.as_str())
-                    .copied()
-                    .ok_or_else(|| {
-                        anyhow!(
-                            "Edge mapping '{}' refers to unknown from.node_mapping '{}'",
-                            edge_cfg.common.name,
-                            edge_cfg.from.node_mapping
-                        )
-                    })?;
-                let to_node = node_by_name
-                    .get(edge_cfg.to.node_mapping.as_str())
-                    .copied()
-                    .ok_or_else(|| {
-                        anyhow!(
-                            "Edge mapping '{}' refers to unknown to.node_mapping '{}'",
-                            edge_cfg.common.name,
-                            edge_cfg.to.node_mapping
-                        )
-                    })?;
-
-                let from_labels = edge_cfg
-                    .from
-                    .label_override
-                    .clone()
-                    .unwrap_or_else(|| from_node.labels.clone());
-                let to_labels = edge_cfg
-                    .to
-                    .label_override
-                    .clone()
-                    .unwrap_or_else(|| to_node.labels.clone());
-
-                let watermark = watermarks.get(&edge_cfg.common.name).map(|s| s.as_str());
-                let rows = fetch_rows_for_mapping(cfg, &edge_cfg.common, watermark).await?;
-                METRICS.add_rows_fetched(rows.len() as u64);
-                METRICS.add_mapping_rows_fetched(&edge_cfg.common.name, rows.len() as u64);
-                tracing::info!(mapping = %edge_cfg.common.name, rows = rows.len(), "Fetched rows");
-
-                let (active_rows, deleted_rows) = if let Some(delta) = &edge_cfg.common.delta {
-                    partition_by_deleted(&rows, delta)
-                } else {
-                    (rows.clone(), Vec::new())
-                };
+        }
 
-                let edges: Vec<MappedEdge> = map_rows_to_edges(&active_rows, edge_cfg)?;
-                METRICS.add_rows_written(edges.len() as u64);
-                METRICS.add_mapping_rows_written(&edge_cfg.common.name, edges.len() as u64);
-                tracing::info!(mapping = %edge_cfg.common.name, rows = edges.len(), "Writing edges");
-                write_edges_in_batches_async(
-                    &mut graph,
-                    edge_cfg,
-                    edges,
-                    from_labels.clone(),
-                    to_labels.clone(),
-                    batch_size,
-                    3,
-                )
-                .await?;
+        let unmatched = unmatched_rows(rows, &conditions);
+        if unmatched.is_empty() {
+            continue;
+        }
 
-                if !deleted_rows.is_empty() {
-                    let deleted_edges: Vec<MappedEdge> =
-                        map_rows_to_edges(&deleted_rows, edge_cfg)?;
-                    METRICS.add_rows_deleted(deleted_edges.len() as u64);
-                    METRICS.add_mapping_rows_deleted(
-                        &edge_cfg.common.name,
-                        deleted_edges.len() as u64,
-                    );
-                    tracing::info!(mapping = %edge_cfg.common.name, rows = deleted_edges.len(), "Deleting edges");
-                    delete_edges_in_batches_async(
-                        &mut graph,
-                        edge_cfg,
-                        deleted_edges,
-                        from_labels.clone(),
-                        to_labels.clone(),
-                        batch_size,
-                        3,
-                    )
-                    .await?;
-                }
+        let group = names.join(",");
+        tracing::warn!(
+            mappings = %group,
+            rows = unmatched.len(),
+            "Rows matched no mapping's when condition",
+        );
+        METRICS.add_rows_unmatched(unmatched.len() as u64);
 
-                if let Some(delta) = &edge_cfg.common.delta {
-                    if let Some(max_ts) = compute_max_watermark(&rows, &delta.updated_at_column) {
-                        watermarks.insert(edge_cfg.common.name.clone(), max_ts.to_rfc3339());
-                        save_watermarks(cfg, &watermarks)?;
-                    }
-                }
-            }
+        if let (UnmatchedRowsAction::DeadLetter, Some(dl)) = (cfg.unmatched_rows, &cfg.dead_letter)
+        {
+            let skipped: Vec<SkippedRow> = unmatched
+                .into_iter()
+                .map(|row| SkippedRow {
+                    row: row.clone(),
+                    reason: "Row matched no mapping's when condition".to_string(),
+                })
+                .collect();
+            append_skipped_rows(dl, &group, &skipped)?;
         }
     }
 
     Ok(())
 }
 
-/// Run daemon mode: repeatedly call run_once at a fixed interval. Purge options are applied only
-/// on the first run.
-pub async fn run_daemon(
-    cfg: &Config,
-    purge_graph_flag: bool,
-    purge_mappings: &[String],
-    interval_secs: u64,
-) -> Result<()> {
-    use tokio::time::{interval, Duration};
+/// Count skipped rows and append them to the dead-letter file.
+fn record_skipped_rows(dl: &DeadLetterConfig, mapping: &str, skipped: &[SkippedRow]) -> Result<()> {
+    if skipped.is_empty() {
+        return Ok(());
+    }
 
-    let mut ticker = interval(Duration::from_secs(interval_secs));
-    let mut first = true;
+    tracing::warn!(
+        mapping = %mapping,
+        rows = skipped.len(),
+        path = %dl.path,
+        "Skipping rows that could not be mapped; written to dead-letter file",
+    );
+    METRICS.add_rows_skipped(skipped.len() as u64);
+    METRICS.add_mapping_rows_skipped(mapping, skipped.len() as u64);
+    append_skipped_rows(dl, mapping, skipped)
+}
 
-    loop {
-        ticker.tick().await;
+/// Map node rows. With a dead-letter file configured, unmappable rows are skipped and
+/// recorded; otherwise the first bad row fails the mapping. Also returns how many rows were
+/// skipped. `first_row` is the position of `rows[0]` in the fetch, for row numbers in errors.
+fn map_nodes(
+    cfg: &Config,
+    node_cfg: &NodeMappingConfig,
+    rows: &[LogicalRow],
+    first_row: usize,
+) -> Result<(Vec<MappedNode>, usize)> {
+    let Some(dl) = &cfg.dead_letter else {
+        return Ok((map_rows_to_nodes(rows, node_cfg, first_row)?, 0));
+    };
+    let (nodes, skipped) = map_rows_to_nodes_skipping(rows, node_cfg, first_row);
+    record_skipped_rows(dl, &node_cfg.common.name, &skipped)?;
+    Ok((nodes, skipped.len()))
+}
 
-        let pg = if first { purge_graph_flag } else { false };
-        let pm: Vec<String> = if first {
-            purge_mappings.to_vec()
-        } else {
-            Vec::new()
-        };
+/// Edge counterpart of `map_nodes`. Unmappable rows are recorded before returning; rows with a
+/// null `skip_on_null` endpoint are left for [`record_null_endpoint_rows`]. `deleted` marks
+/// delete rows, which are not deduplicated.
+fn map_edges(
+    cfg: &Config,
+    edge_cfg: &EdgeMappingConfig,
+    rows: &[LogicalRow],
+    first_row: usize,
+    deleted: bool,
+) -> Result<MappedEdgeRows> {
+    let Some(dl) = &cfg.dead_letter else {
+        return map_edge_rows(rows, edge_cfg, first_row, deleted);
+    };
+    let mapped = map_rows_to_edges_skipping(rows, edge_cfg, first_row, deleted);
+    record_skipped_rows(dl, &edge_cfg.common.name, &mapped.skipped)?;
+    Ok(mapped)
+}
 
-        tracing::info!("Starting sync run");
-        if let Err(e) = run_once(cfg, pg, &pm).await {
-            tracing::error!(error = %e, "Sync run failed");
-            METRICS.inc_failed_runs();
-            // Mapping-level failure increments are handled where errors are detected
-        }
+/// Count edge rows left out for a null `skip_on_null` endpoint. Such a row is expected data,
+/// not a failure: an active one is counted as skipped, since no edge is written for it, while
+/// a deleted one names an edge that can't exist and is only logged.
+fn record_null_endpoint_rows(common: &CommonMappingFields, rows: usize, deleted: bool) {
+    if rows == 0 {
+        return;
+    }
+    if deleted {
+        tracing::info!(
+            mapping = %common.name,
+            rows,
+            "Ignoring edge deletes with a null optional endpoint",
+        );
+        return;
+    }
+    tracing::info!(
+        mapping = %common.name,
+        rows,
+        "Skipping edge rows with a null optional endpoint",
+    );
+    METRICS.add_rows_skipped(rows as u64);
+    METRICS.add_mapping_rows_skipped(&common.name, rows as u64);
+}
 
-        first = false;
+/// Add the keys of `edges`, and of `null_endpoint_rows` dropped for a null `skip_on_null`
+/// endpoint, to a `reconcile_deletes` mapping's source keys. Returns how many dropped rows had
+/// no readable key, which reconciling has to treat as skipped.
+fn extend_edge_source_keys(
+    keys: &mut HashSet<String>,
+    edge_cfg: &EdgeMappingConfig,
+    edges: &[MappedEdge],
+    null_endpoint_rows: &[LogicalRow],
+) -> usize {
+    keys.extend(
+        edges
+            .iter()
+            .filter_map(|e| e.edge_key.as_ref().and_then(edge_key_text)),
+    );
+    let mut unreadable = 0;
+    for key in null_endpoint_edge_keys(null_endpoint_rows, edge_cfg) {
+        match key.as_ref().and_then(edge_key_text) {
+            Some(text) => {
+                keys.insert(text);
+            }
+            None => unreadable += 1,
+        }
     }
+    unreadable
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::{
-        CommonMappingFields, EntityMapping, FalkorConfig, Mode, NodeKeySpec, NodeMappingConfig,
-        PropertySpec, SourceConfig, StateBackendKind, StateConfig,
+/// Combined-mapping counterpart of `map_nodes`.
+fn map_combined(
+    cfg: &Config,
+    combined_cfg: &CombinedMappingConfig,
+    rows: &[LogicalRow],
+    first_row: usize,
+) -> Result<Vec<MappedCombined>> {
+    let Some(dl) = &cfg.dead_letter else {
+        return map_rows_to_combined(rows, combined_cfg, first_row);
     };
-    use std::collections::HashMap;
+    let (mapped, skipped) = map_rows_to_combined_skipping(rows, combined_cfg, first_row);
+    record_skipped_rows(dl, &combined_cfg.common.name, &skipped)?;
+    Ok(mapped)
+}
 
-    /// Optional end-to-end test that loads a small JSON file into FalkorDB.
-    ///
-    /// Requires FALKORDB_ENDPOINT to be set. If it's missing, the test is skipped
-    /// by returning Ok(()) immediately.
-    #[tokio::test]
-    async fn end_to_end_file_load_into_falkordb() -> Result<()> {
-        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
-            Ok(v) => v,
-            Err(_) => return Ok(()),
-        };
-        let graph = std::env::var("FALKORDB_GRAPH")
-            .unwrap_or_else(|_| "snowflake_to_falkordb_load_test".to_string());
+async fn purge_graph(graph: &mut falkordb::AsyncGraph, cfg: &Config) -> Result<()> {
+    tracing::warn!("Purging entire graph prior to load");
+    with_query_timeout(cfg.falkordb.query_timeout(), async {
+        graph
+            .query(&purge_graph_cypher(cfg))
+            .execute()
+            .await
+            .map_err(anyhow::Error::from)
+    })
+    .await?;
+    forget_schema_commands(cfg);
+    Ok(())
+}
 
-        // Prepare a tiny in-memory config pointing at a temp JSON file.
-        let tmp_dir = std::env::temp_dir();
-        let input_path = tmp_dir.join("snowflake_to_falkordb_nodes.json");
-        std::fs::write(
-            &input_path,
-            r#"[
-                {"id": 1, "name": "Alice"},
-                {"id": 2, "name": "Bob"}
-            ]"#,
-        )?;
+/// Index and constraint commands this process has already sent, per graph, so daemon ticks
+/// don't repeat them every run.
+static SENT_SCHEMA_COMMANDS: Lazy<Mutex<HashSet<(String, String)>>> = Lazy::new(Default::default);
 
-        let source = SourceConfig {
-            file: Some(input_path.to_string_lossy().to_string()),
-            table: None,
-            stream: None,
-            select: None,
-            r#where: None,
-        };
+/// The graph a config writes to: endpoint, logical database and graph name. Graphs with the
+/// same name in different `db_index` databases are different graphs.
+fn graph_identity(cfg: &Config) -> String {
+    format!(
+        "{}#db{}/{}",
+        cfg.falkordb.endpoint,
+        cfg.falkordb.db_index.unwrap_or(0),
+        cfg.falkordb.graph
+    )
+}
 
-        let common = CommonMappingFields {
-            name: "test_nodes".to_string(),
-            source,
-            mode: Mode::Full,
-            delta: None,
-        };
+/// Whether `command` has yet to succeed against the config's graph in this process.
+fn schema_command_is_new(cfg: &Config, command: &str) -> bool {
+    !SENT_SCHEMA_COMMANDS
+        .lock()
+        .unwrap()
+        .contains(&(graph_identity(cfg), command.to_string()))
+}
+
+/// Record that `command` took effect on the config's graph, so later runs skip it. Only
+/// called once the command succeeded (or found its index already there); a failed command is
+/// sent again next run.
+fn record_schema_command(cfg: &Config, command: &str) {
+    SENT_SCHEMA_COMMANDS
+        .lock()
+        .unwrap()
+        .insert((graph_identity(cfg), command.to_string()));
+}
+
+/// Whether a schema command failed only because what it creates already exists.
+fn is_already_exists(err: &impl std::fmt::Display) -> bool {
+    err.to_string().to_ascii_lowercase().contains("already")
+}
+
+/// Forget the schema commands sent to the config's graph, e.g. after purging or dropping
+/// indexes, so the next run sends them again.
+fn forget_schema_commands(cfg: &Config) {
+    let graph = graph_identity(cfg);
+    SENT_SCHEMA_COMMANDS
+        .lock()
+        .unwrap()
+        .retain(|(g, _)| *g != graph);
+}
+
+/// Indexes the loader manages, as (mapping name, label clause, key property).
+///
+/// Each node mapping gets an index on (fixed labels, key property) for every key property;
+/// combined mappings get one per endpoint. Targets are de-duplicated by (labels, property); the
+/// first mapping wins.
+fn node_index_targets(mappings: &[EntityMapping]) -> Vec<(&str, String, String)> {
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    let mut out = Vec::new();
+
+    for mapping in mappings {
+        let candidates: Vec<(String, String)> = match mapping {
+            EntityMapping::Node(node_cfg) if !node_cfg.labels.is_empty() => node_cfg
+                .key
+                .specs()
+                .iter()
+                .map(|k| (node_label_clause(node_cfg), k.property.clone()))
+                .collect(),
+            EntityMapping::Combined(combined_cfg) => [&combined_cfg.from, &combined_cfg.to]
+                .into_iter()
+                .map(|spec| (spec.labels.join(":"), spec.key.property.clone()))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        for (label_clause, prop) in candidates {
+            if seen.insert((label_clause.clone(), prop.clone())) {
+                out.push((mapping.common().name.as_str(), label_clause, prop));
+            }
+        }
+    }
+
+    out
+}
+
+/// Every index the loader creates: the key indexes of [`node_index_targets`] followed by
+/// `falkordb.indexes`, which are attributed to "falkordb.indexes" and skipped when a key index
+/// already covers them.
+fn index_targets(cfg: &Config) -> Vec<(&str, String, String)> {
+    let mut out = node_index_targets(&cfg.mappings);
+    for index in &cfg.falkordb.indexes {
+        if !out
+            .iter()
+            .any(|(_, labels, prop)| *labels == index.label && *prop == index.property)
+        {
+            out.push((
+                "falkordb.indexes",
+                index.label.clone(),
+                index.property.clone(),
+            ));
+        }
+    }
+    out
+}
+
+/// The indexes to create, as (owner, labels, property, command); none when
+/// `falkordb.manage_indexes` is false.
+fn index_creation_commands(cfg: &Config) -> Vec<(&str, String, String, String)> {
+    if !cfg.falkordb.manages_indexes() {
+        return Vec::new();
+    }
+    index_targets(cfg)
+        .into_iter()
+        .map(|(owner, labels, prop)| {
+            let cypher = format!(
+                "CREATE INDEX ON :{labels}({prop})",
+                labels = labels,
+                prop = prop
+            );
+            (owner, labels, prop, cypher)
+        })
+        .collect()
+}
+
+/// Ensure indexes exist for node key properties used in MERGE/MATCH, and for
+/// `falkordb.indexes`. Each index is only requested on the first run of the process, and none
+/// are with `falkordb.manage_indexes: false`.
+///
+/// Failures are treated as non-fatal (for example, when the index already exists on the
+/// server).
+async fn ensure_node_indexes(graph: &mut falkordb::AsyncGraph, cfg: &Config) -> Result<()> {
+    for (mapping, labels, prop, cypher) in index_creation_commands(cfg) {
+        if !schema_command_is_new(cfg, &cypher) {
+            continue;
+        }
+
+        tracing::info!(
+            mapping = %mapping,
+            labels = %labels,
+            property = %prop,
+            "Ensuring index for node label and property",
+        );
+
+        let created = with_query_timeout(cfg.falkordb.query_timeout(), async {
+            graph
+                .query(&cypher)
+                .execute()
+                .await
+                .map_err(anyhow::Error::from)
+        })
+        .await;
+        match created {
+            Ok(_) => record_schema_command(cfg, &cypher),
+            Err(e) if is_already_exists(&e) => {
+                tracing::debug!(mapping = %mapping, labels = %labels, property = %prop, "Index already exists");
+                record_schema_command(cfg, &cypher);
+            }
+            Err(e) => tracing::warn!(
+                mapping = %mapping,
+                labels = %labels,
+                property = %prop,
+                error = %e,
+                "Failed to create index for node label; retrying next run",
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Unique constraints the loader manages, as (mapping name, label, key properties): one per
+/// node mapping with `unique_key`, on its first label and all of its key properties.
+fn unique_constraint_targets(mappings: &[EntityMapping]) -> Vec<(&str, &str, Vec<&str>)> {
+    mappings
+        .iter()
+        .filter_map(|mapping| match mapping {
+            EntityMapping::Node(node_cfg) if node_cfg.unique_key => {
+                let label = node_cfg.labels.first()?;
+                let props = node_cfg
+                    .key
+                    .specs()
+                    .iter()
+                    .map(|k| k.property.as_str())
+                    .collect();
+                Some((node_cfg.common.name.as_str(), label.as_str(), props))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The `GRAPH.CONSTRAINT` command the driver sends to create a unique node constraint; used
+/// to log the constraint and to remember it as sent.
+fn unique_constraint_command(graph: &str, label: &str, props: &[&str]) -> String {
+    format!(
+        "GRAPH.CONSTRAINT CREATE {} UNIQUE NODE {} PROPERTIES {} {}",
+        graph,
+        label,
+        props.len(),
+        props.join(" ")
+    )
+}
+
+/// Ensure unique constraints exist for mappings with `unique_key`, once per process. The
+/// driver also creates the exact-match index a constraint needs. A constraint that already
+/// exists is fine; any other failure fails the run, since writes would otherwise go ahead
+/// without the uniqueness the mapping asked for.
+async fn ensure_unique_constraints(graph: &mut falkordb::AsyncGraph, cfg: &Config) -> Result<()> {
+    for (mapping, label, props) in unique_constraint_targets(&cfg.mappings) {
+        let command = unique_constraint_command(&cfg.falkordb.graph, label, &props);
+        if !schema_command_is_new(cfg, &command) {
+            continue;
+        }
+        tracing::info!(mapping = %mapping, "Ensuring unique constraint: {}", command);
+
+        let created = with_query_timeout(cfg.falkordb.query_timeout(), async {
+            graph
+                .create_unique_constraint(falkordb::EntityType::Node, label.to_string(), &props)
+                .await
+                .map_err(anyhow::Error::from)
+        })
+        .await;
+        match created {
+            Ok(_) => record_schema_command(cfg, &command),
+            Err(e) if is_already_exists(&e) => {
+                tracing::debug!(mapping = %mapping, label = %label, "Unique constraint already exists");
+                record_schema_command(cfg, &command);
+            }
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to create unique constraint on :{}({}) for mapping '{}': {}",
+                    label,
+                    props.join(", "),
+                    mapping,
+                    e
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Drop the indexes `ensure_node_indexes` would create for this config. Missing indexes are
+/// logged and skipped. Returns the number of indexes dropped.
+pub async fn drop_node_indexes(cfg: &Config) -> Result<usize> {
+    let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+    let mut dropped = 0usize;
+
+    for (mapping, labels, prop) in index_targets(cfg) {
+        let cypher = format!(
+            "DROP INDEX ON :{labels}({prop})",
+            labels = labels,
+            prop = prop
+        );
+
+        let result = with_query_timeout(cfg.falkordb.query_timeout(), async {
+            graph
+                .query(&cypher)
+                .execute()
+                .await
+                .map_err(anyhow::Error::from)
+        })
+        .await;
+        match result {
+            Ok(_) => {
+                dropped += 1;
+                tracing::info!(
+                    mapping = %mapping,
+                    labels = %labels,
+                    property = %prop,
+                    "Dropped node index",
+                );
+            }
+            Err(e) => tracing::warn!(
+                mapping = %mapping,
+                labels = %labels,
+                property = %prop,
+                error = %e,
+                "Failed to drop node index (it may not exist)",
+            ),
+        }
+    }
+    forget_schema_commands(cfg);
+
+    Ok(dropped)
+}
+
+/// Compare the schema the config expects (fixed node labels and key indexes) with what the
+/// graph has, describing every expected item that is missing.
+fn schema_mismatches(
+    mappings: &[EntityMapping],
+    labels: &HashSet<String>,
+    indexes: &HashSet<(String, String)>,
+) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut seen_labels = HashSet::new();
+
+    for mapping in mappings {
+        let expected: Vec<&String> = match mapping {
+            EntityMapping::Node(node_cfg) => node_cfg.labels.iter().collect(),
+            EntityMapping::Combined(combined_cfg) => combined_cfg
+                .from
+                .labels
+                .iter()
+                .chain(&combined_cfg.to.labels)
+                .collect(),
+            EntityMapping::Edge(_) => Vec::new(),
+        };
+        for label in expected {
+            if seen_labels.insert(label.as_str()) && !labels.contains(label) {
+                out.push(format!(
+                    "label '{}' (mapping '{}') does not exist",
+                    label,
+                    mapping.common().name
+                ));
+            }
+        }
+    }
+
+    for (mapping, label_clause, prop) in node_index_targets(mappings) {
+        let indexed = label_clause
+            .split(':')
+            .any(|label| indexes.contains(&(label.to_string(), prop.clone())));
+        if !indexed {
+            out.push(format!(
+                "no index on :{}({}) (mapping '{}')",
+                label_clause, prop, mapping
+            ));
+        }
+    }
+
+    out
+}
+
+/// Check a config before a run: [`Config::validate`] plus the mappings' `transforms`, which
+/// must each name a registered transform that can be built from the entry. Every problem
+/// found is listed in the returned error.
+pub fn validate_config(cfg: &Config) -> Result<()> {
+    let mut problems = cfg.problems();
+    problems.extend(transform_problems(cfg));
+    problem_report(problems)
+}
+
+/// [`validate_config`] plus the local filesystem checks of [`Config::lint`], for the
+/// `validate` subcommand.
+pub fn lint_config(cfg: &Config) -> Result<()> {
+    let mut problems = cfg.lint_problems();
+    problems.extend(transform_problems(cfg));
+    problem_report(problems)
+}
+
+fn transform_problems(cfg: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    for mapping in &cfg.mappings {
+        let common = mapping.common();
+        for transform in &common.transforms {
+            if let Some(problem) = transform_problem(transform) {
+                problems.push(format!("mapping '{}': {}", common.name, problem));
+            }
+        }
+    }
+    problems
+}
+
+/// `--verify-schema` preflight: check that the target graph already has the labels and key
+/// indexes this config expects, to catch pointing the loader at the wrong graph. Aborts or
+/// warns according to `falkordb.verify_schema_action`.
+pub async fn verify_schema(cfg: &Config) -> Result<()> {
+    let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+    let (labels, indexes) =
+        with_query_timeout(cfg.falkordb.query_timeout(), fetch_graph_schema(&mut graph)).await?;
+
+    let mismatches = schema_mismatches(&cfg.mappings, &labels, &indexes);
+    if mismatches.is_empty() {
+        tracing::info!(graph = %cfg.falkordb.graph, "Graph schema matches config");
+        return Ok(());
+    }
+
+    for mismatch in &mismatches {
+        tracing::warn!(graph = %cfg.falkordb.graph, "Schema mismatch: {}", mismatch);
+    }
+    match cfg.falkordb.verify_schema_action {
+        GuardAction::Fail => Err(anyhow!(
+            "Graph '{}' does not match the configured schema ({} mismatch(es)): {}",
+            cfg.falkordb.graph,
+            mismatches.len(),
+            mismatches.join("; ")
+        )),
+        GuardAction::Warn => Ok(()),
+    }
+}
+
+/// Describe which configured graph size limits the current counts exceed.
+fn graph_size_violations(cfg: &Config, nodes: u64, edges: u64) -> Vec<String> {
+    let mut out = Vec::new();
+    if let Some(max) = cfg.falkordb.max_graph_nodes.filter(|max| nodes > *max) {
+        out.push(format!("{} nodes exceeds max_graph_nodes {}", nodes, max));
+    }
+    if let Some(max) = cfg.falkordb.max_graph_edges.filter(|max| edges > *max) {
+        out.push(format!(
+            "{} relationships exceeds max_graph_edges {}",
+            edges, max
+        ));
+    }
+    out
+}
+
+/// Pre-load guardrail: refuse (or warn) when the graph is already larger than the configured
+/// `max_graph_nodes`/`max_graph_edges`, which usually means keys are creating duplicates.
+async fn check_graph_size(graph: &mut falkordb::AsyncGraph, cfg: &Config) -> Result<()> {
+    let falkor = &cfg.falkordb;
+    if falkor.max_graph_nodes.is_none() && falkor.max_graph_edges.is_none() {
+        return Ok(());
+    }
+
+    let timeout = falkor.query_timeout();
+    let nodes = match falkor.max_graph_nodes {
+        Some(_) => {
+            with_query_timeout(timeout, query_count(graph, "MATCH (n) RETURN count(n)")).await?
+        }
+        None => 0,
+    };
+    let edges = match falkor.max_graph_edges {
+        Some(_) => {
+            with_query_timeout(
+                timeout,
+                query_count(graph, "MATCH ()-[r]->() RETURN count(r)"),
+            )
+            .await?
+        }
+        None => 0,
+    };
+
+    let violations = graph_size_violations(cfg, nodes, edges);
+    if violations.is_empty() {
+        tracing::info!(nodes, edges, "Graph size within configured limits");
+        return Ok(());
+    }
+
+    let message = format!(
+        "Graph '{}' is over its size limit: {}",
+        falkor.graph,
+        violations.join("; ")
+    );
+    match falkor.graph_size_action {
+        GuardAction::Fail => Err(anyhow!("{}; refusing to load", message)),
+        GuardAction::Warn => {
+            tracing::warn!("{}", message);
+            Ok(())
+        }
+    }
+}
+
+/// Selected node mappings that configure a `tombstone_ttl_secs`.
+fn tombstone_reap_targets<'a>(cfg: &'a Config, opts: &RunOptions) -> Vec<&'a NodeMappingConfig> {
+    cfg.mappings
+        .iter()
+        .filter_map(|mapping| match mapping {
+            EntityMapping::Node(node_cfg)
+                if opts.selects(&node_cfg.common.name)
+                    && node_cfg
+                        .soft_delete()
+                        .is_some_and(|sd| sd.tombstone_ttl_secs.is_some()) =>
+            {
+                Some(node_cfg)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Maintenance step: hard-delete expired soft-delete tombstones for every selected node mapping
+/// that configures a `tombstone_ttl_secs`.
+async fn reap_expired_tombstones(
+    graph: &mut falkordb::AsyncGraph,
+    cfg: &Config,
+    opts: &RunOptions,
+) -> Result<()> {
+    for node_cfg in tombstone_reap_targets(cfg, opts) {
+        let reaped = with_query_timeout(
+            cfg.falkordb.query_timeout(),
+            reap_tombstones(graph, node_cfg),
+        )
+        .await?;
+        if reaped > 0 {
+            tracing::info!(mapping = %node_cfg.common.name, nodes = reaped, "Reaped expired tombstones");
+        }
+    }
+    Ok(())
+}
+
+/// Cypher purging an edge mapping's relationships. With `orphans`, the endpoints left without
+/// any relationship are deleted too; an endpoint still connected to anything else, or a node
+/// that wasn't an endpoint of a purged relationship, is kept.
+fn edge_purge_cypher(
+    edge_cfg: &EdgeMappingConfig,
+    from_labels: &[String],
+    to_labels: &[String],
+    orphans: bool,
+) -> String {
+    let pattern = edge_type_pattern(edge_cfg, from_labels, to_labels);
+    if orphans {
+        format!(
+            "MATCH {} DELETE r WITH src, tgt UNWIND [src, tgt] AS n \
+             WITH DISTINCT n WHERE NOT (n)--() DETACH DELETE n",
+            pattern
+        )
+    } else {
+        format!("MATCH {} DELETE r", pattern)
+    }
+}
+
+/// `--purge-mapping` Cypher for a combined mapping. Only the relationships are deleted, in the
+/// mapping's direction; endpoint nodes may be shared with other mappings.
+fn combined_purge_cypher(combined_cfg: &CombinedMappingConfig) -> String {
+    format!("MATCH {} DELETE r", combined_type_pattern(combined_cfg))
+}
+
+async fn purge_mapping(
+    graph: &mut falkordb::AsyncGraph,
+    mapping: &EntityMapping,
+    node_by_name: &HashMap<&str, &NodeMappingConfig>,
+    orphans: bool,
+    query_timeout: Option<Duration>,
+) -> Result<()> {
+    let cypher = match mapping {
+        EntityMapping::Node(node_cfg) => {
+            let label_clause = node_cfg.labels.join(":");
+            tracing::warn!(mapping = %node_cfg.common.name, "Purging node mapping");
+            format!("MATCH (n:{}) DETACH DELETE n", label_clause)
+        }
+        EntityMapping::Edge(edge_cfg) => {
+            let from_node = node_by_name
+                .get(edge_cfg.from.node_mapping.as_str())
+                .copied()
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Edge mapping '{}' refers to unknown from.node_mapping '{}'",
+                        edge_cfg.common.name,
+                        edge_cfg.from.node_mapping
+                    )
+                })?;
+            let to_node = node_by_name
+                .get(edge_cfg.to.node_mapping.as_str())
+                .copied()
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Edge mapping '{}' refers to unknown to.node_mapping '{}'",
+                        edge_cfg.common.name,
+                        edge_cfg.to.node_mapping
+                    )
+                })?;
+
+            let from_labels = edge_cfg
+                .from
+                .label_override
+                .clone()
+                .unwrap_or_else(|| from_node.labels.clone());
+            let to_labels = edge_cfg
+                .to
+                .label_override
+                .clone()
+                .unwrap_or_else(|| to_node.labels.clone());
+
+            tracing::warn!(
+                mapping = %edge_cfg.common.name,
+                orphaned_endpoints = orphans,
+                "Purging edge mapping"
+            );
+            edge_purge_cypher(edge_cfg, &from_labels, &to_labels, orphans)
+        }
+        EntityMapping::Combined(combined_cfg) => {
+            tracing::warn!(mapping = %combined_cfg.common.name, "Purging combined mapping");
+            combined_purge_cypher(combined_cfg)
+        }
+    };
+    with_query_timeout(query_timeout, async {
+        graph
+            .query(&cypher)
+            .execute()
+            .await
+            .map_err(anyhow::Error::from)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Labels of an edge mapping's from/to endpoints: the `label_override`, or the referenced
+/// node mapping's labels.
+fn edge_endpoint_labels(
+    edge_cfg: &EdgeMappingConfig,
+    node_by_name: &HashMap<&str, &NodeMappingConfig>,
+) -> Result<(Vec<String>, Vec<String>)> {
+    let labels = |side: &str, endpoint: &EdgeEndpointMatch| {
+        if let Some(labels) = &endpoint.label_override {
+            return Ok(labels.clone());
+        }
+        node_by_name
+            .get(endpoint.node_mapping.as_str())
+            .map(|node| node.labels.clone())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Edge mapping '{}' refers to unknown {}.node_mapping '{}'",
+                    edge_cfg.common.name,
+                    side,
+                    endpoint.node_mapping
+                )
+            })
+    };
+    Ok((labels("from", &edge_cfg.from)?, labels("to", &edge_cfg.to)?))
+}
+
+/// Run the fetch/map pipeline for every mapping and write the statements a load would
+/// execute, index creation first, to `path` instead of sending them to FalkorDB. Nothing
+/// connects to the graph: watermarks are neither read nor advanced, so the file always holds
+/// a full load, from `--from-watermark` overrides aside. Returns the number of statements
+/// written.
+pub async fn emit_cypher(cfg: &Config, opts: &RunOptions, path: &Path) -> Result<u64> {
+    let mut out = CypherEmitter::create(path)?;
+    run_statements(cfg, opts, HashMap::new(), &mut out).await?;
+    out.finish()
+}
+
+/// Fetch and map every mapping from its current watermark and log the statements a load
+/// would execute (`--dry-run`). No index, MERGE or DELETE statement reaches the graph and
+/// watermarks are not advanced. Returns the statement and parameter row counts.
+pub async fn dry_run(cfg: &Config, opts: &RunOptions) -> Result<DryRunLog> {
+    let watermarks = load_configured_watermarks(cfg).await?;
+    let mut log = DryRunLog::default();
+    run_statements(cfg, opts, watermarks, &mut log).await?;
+    Ok(log)
+}
+
+/// Sync the mappings `opts` selects from `watermarks` (a full load when empty) the way a run
+/// would, but hand
+/// each statement to `out` instead of FalkorDB: index creation first, then each mapping's
+/// write and delete batches. Steps that read the graph (reconcile, endpoint checks, plan
+/// warm-up) are skipped, watermarks are not saved and `stream_consume` streams are not
+/// advanced. The first failing mapping fails the whole call.
+async fn run_statements(
+    cfg: &Config,
+    opts: &RunOptions,
+    watermarks: HashMap<String, String>,
+    out: &mut (dyn StatementSink + Send),
+) -> Result<()> {
+    for (_, _, _, cypher) in index_creation_commands(cfg) {
+        out.emit(&cypher, None)?;
+    }
+
+    let mut ctx = RunContext {
+        cfg,
+        opts,
+        target: RunTarget::Statements(out),
+        watermarks,
+        state: StateConnections::default(),
+        shared: SharedFetches::default(),
+        node_by_name: cfg.node_mappings_by_name(),
+        write: WriteOptions::from_config(&cfg.falkordb),
+        write_pool: Vec::new(),
+    };
+    let (_shutdown_tx, shutdown) = watch::channel(false);
+    run_mappings(&mut ctx, opts.execution_order(cfg)?, false, &shutdown)
+        .await
+        .into_result()?;
+    report_unmatched_rows(cfg, &ctx.shared)
+}
+
+/// Hand one write or delete batch to `out`, prefixed with the comment the batch writers add
+/// under `annotate_queries`.
+fn emit_batch(
+    out: &mut dyn StatementSink,
+    opts: &WriteOptions,
+    mapping: &str,
+    batch_no: usize,
+    cypher: &str,
+    rows: &JsonValue,
+) -> Result<()> {
+    let annotation = if opts.annotate {
+        query_annotation(mapping, batch_no)
+    } else {
+        String::new()
+    };
+    out.emit(&format!("{}{}", annotation, cypher), Some(rows))
+}
+
+/// Hand a node mapping's write (or delete) batches to `out`.
+fn emit_node_batches(
+    out: &mut dyn StatementSink,
+    node_cfg: &NodeMappingConfig,
+    nodes: &[MappedNode],
+    batch_size: usize,
+    opts: &WriteOptions,
+    delete: bool,
+) -> Result<()> {
+    for (idx, batch) in nodes.chunks(batch_size.max(1)).enumerate() {
+        for (cypher, rows) in node_batch_statements(node_cfg, batch, delete) {
+            emit_batch(out, opts, &node_cfg.common.name, idx + 1, &cypher, &rows)?;
+        }
+    }
+    Ok(())
+}
+
+/// Hand an edge mapping's write (or delete) batches to `out`.
+#[allow(clippy::too_many_arguments)]
+fn emit_edge_batches(
+    out: &mut dyn StatementSink,
+    edge_cfg: &EdgeMappingConfig,
+    mut edges: Vec<MappedEdge>,
+    from_labels: &[String],
+    to_labels: &[String],
+    batch_size: usize,
+    opts: &WriteOptions,
+    delete: bool,
+) -> Result<()> {
+    let ranges = edge_batch_ranges(&mut edges, batch_size, edge_cfg.batch_by_row);
+    for (idx, range) in ranges.into_iter().enumerate() {
+        let (cypher, rows) =
+            edge_batch_statement(edge_cfg, &edges[range], from_labels, to_labels, delete)?;
+        emit_batch(out, opts, &edge_cfg.common.name, idx + 1, &cypher, &rows)?;
+    }
+    Ok(())
+}
+
+/// Hand a combined mapping's write (or delete) batches to `out`.
+fn emit_combined_batches(
+    out: &mut dyn StatementSink,
+    combined_cfg: &CombinedMappingConfig,
+    mapped: &[MappedCombined],
+    batch_size: usize,
+    opts: &WriteOptions,
+    delete: bool,
+) -> Result<()> {
+    for (idx, batch) in mapped.chunks(batch_size.max(1)).enumerate() {
+        let (cypher, rows) = combined_batch_statement(combined_cfg, batch, delete);
+        emit_batch(
+            out,
+            opts,
+            &combined_cfg.common.name,
+            idx + 1,
+            &cypher,
+            &rows,
+        )?;
+    }
+    Ok(())
+}
+
+/// Rows handled by one mapping during a run, and the error that stopped it, if any.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct MappingSummary {
+    pub name: String,
+    pub rows_fetched: u64,
+    pub rows_written: u64,
+    pub rows_deleted: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of a sync run: per-mapping counts plus run-wide totals. Printed by
+/// `--json-summary`.
+#[derive(Debug, Default, Serialize)]
+pub struct RunSummary {
+    pub mappings: Vec<MappingSummary>,
+    pub rows_fetched: u64,
+    pub rows_written: u64,
+    pub rows_deleted: u64,
+    pub mappings_failed: usize,
+    /// A shutdown request stopped the run before every mapping ran.
+    pub interrupted: bool,
+}
+
+impl RunSummary {
+    fn record(&mut self, mapping: MappingSummary) {
+        self.rows_fetched += mapping.rows_fetched;
+        self.rows_written += mapping.rows_written;
+        self.rows_deleted += mapping.rows_deleted;
+        if mapping.error.is_some() {
+            self.mappings_failed += 1;
+        }
+        self.mappings.push(mapping);
+    }
+
+    pub fn failed(&self) -> bool {
+        self.mappings_failed > 0
+    }
+
+    /// One error naming every failed mapping, or `Ok(())` when all succeeded.
+    pub fn into_result(self) -> Result<Self> {
+        let failures: Vec<String> = self
+            .mappings
+            .iter()
+            .filter_map(|m| m.error.as_ref().map(|e| format!("'{}': {}", m.name, e)))
+            .collect();
+        if failures.is_empty() {
+            Ok(self)
+        } else {
+            Err(anyhow!(
+                "{} mapping(s) failed: {}",
+                failures.len(),
+                failures.join("; ")
+            ))
+        }
+    }
+}
+
+/// Where a run's statements go.
+enum RunTarget<'a> {
+    /// Executed against FalkorDB.
+    Graph(&'a mut falkordb::AsyncGraph),
+    /// Handed to a sink instead (`--dry-run`, `--emit-cypher`); nothing touches the graph.
+    Statements(&'a mut (dyn StatementSink + Send)),
+}
+
+/// State shared by the mappings of one `run_sync`.
+struct RunContext<'a> {
+    cfg: &'a Config,
+    opts: &'a RunOptions,
+    target: RunTarget<'a>,
+    watermarks: HashMap<String, String>,
+    /// The state backend connection watermarks are saved over, kept for the whole run.
+    state: StateConnections,
+    shared: SharedFetches,
+    node_by_name: HashMap<&'a str, &'a NodeMappingConfig>,
+    write: WriteOptions,
+    /// Extra connections for `falkordb.write_concurrency`, opened by the first node mapping
+    /// that writes and kept for the rest of the run.
+    write_pool: Vec<FalkorConnection>,
+}
+
+impl RunContext<'_> {
+    /// The run's FalkorDB connection, or `None` when statements go to a sink.
+    fn graph(&mut self) -> Option<&mut falkordb::AsyncGraph> {
+        match &mut self.target {
+            RunTarget::Graph(graph) => Some(&mut **graph),
+            RunTarget::Statements(_) => None,
+        }
+    }
+
+    /// Connections a node mapping writes over: the run's own plus `write_concurrency - 1`
+    /// from the pool.
+    async fn node_writers(&mut self) -> Result<Vec<&mut falkordb::AsyncGraph>> {
+        while self.write_pool.len() + 1 < self.write.concurrency {
+            self.write_pool
+                .push(connect_falkordb_async(&self.cfg.falkordb).await?);
+        }
+        let RunTarget::Graph(graph) = &mut self.target else {
+            return Err(anyhow!("Node writers need a FalkorDB connection"));
+        };
+        let mut graphs: Vec<&mut falkordb::AsyncGraph> = vec![&mut **graph];
+        graphs.extend(self.write_pool.iter_mut().map(|conn| &mut **conn));
+        Ok(graphs)
+    }
+
+    async fn write_nodes(
+        &mut self,
+        node_cfg: &NodeMappingConfig,
+        nodes: Vec<MappedNode>,
+        batch_size: usize,
+    ) -> Result<()> {
+        let opts = self.write;
+        if let RunTarget::Statements(out) = &mut self.target {
+            return emit_node_batches(&mut **out, node_cfg, &nodes, batch_size, &opts, false);
+        }
+        let writers = self.node_writers().await?;
+        write_nodes_concurrently_async(writers, node_cfg, nodes, batch_size, &opts).await
+    }
+
+    async fn delete_nodes(
+        &mut self,
+        node_cfg: &NodeMappingConfig,
+        nodes: Vec<MappedNode>,
+        batch_size: usize,
+    ) -> Result<()> {
+        let opts = self.write;
+        match &mut self.target {
+            RunTarget::Graph(graph) => {
+                delete_nodes_in_batches_async(graph, node_cfg, nodes, batch_size, &opts).await
+            }
+            RunTarget::Statements(out) => {
+                emit_node_batches(&mut **out, node_cfg, &nodes, batch_size, &opts, true)
+            }
+        }
+    }
+
+    async fn write_edges(
+        &mut self,
+        edge_cfg: &EdgeMappingConfig,
+        edges: Vec<MappedEdge>,
+        from_labels: &[String],
+        to_labels: &[String],
+        batch_size: usize,
+    ) -> Result<()> {
+        let opts = self.write;
+        match &mut self.target {
+            RunTarget::Graph(graph) => {
+                write_edges_in_batches_async(
+                    graph,
+                    edge_cfg,
+                    edges,
+                    from_labels.to_vec(),
+                    to_labels.to_vec(),
+                    batch_size,
+                    &opts,
+                )
+                .await
+            }
+            RunTarget::Statements(out) => emit_edge_batches(
+                &mut **out,
+                edge_cfg,
+                edges,
+                from_labels,
+                to_labels,
+                batch_size,
+                &opts,
+                false,
+            ),
+        }
+    }
+
+    async fn delete_edges(
+        &mut self,
+        edge_cfg: &EdgeMappingConfig,
+        edges: Vec<MappedEdge>,
+        from_labels: &[String],
+        to_labels: &[String],
+        batch_size: usize,
+    ) -> Result<()> {
+        let opts = self.write;
+        match &mut self.target {
+            RunTarget::Graph(graph) => {
+                delete_edges_in_batches_async(
+                    graph,
+                    edge_cfg,
+                    edges,
+                    from_labels.to_vec(),
+                    to_labels.to_vec(),
+                    batch_size,
+                    &opts,
+                )
+                .await
+            }
+            RunTarget::Statements(out) => emit_edge_batches(
+                &mut **out,
+                edge_cfg,
+                edges,
+                from_labels,
+                to_labels,
+                batch_size,
+                &opts,
+                true,
+            ),
+        }
+    }
+
+    async fn write_combined(
+        &mut self,
+        combined_cfg: &CombinedMappingConfig,
+        mapped: Vec<MappedCombined>,
+        batch_size: usize,
+    ) -> Result<()> {
+        let opts = self.write;
+        match &mut self.target {
+            RunTarget::Graph(graph) => {
+                write_combined_in_batches_async(graph, combined_cfg, mapped, batch_size, &opts)
+                    .await
+            }
+            RunTarget::Statements(out) => {
+                emit_combined_batches(&mut **out, combined_cfg, &mapped, batch_size, &opts, false)
+            }
+        }
+    }
+
+    async fn delete_combined(
+        &mut self,
+        combined_cfg: &CombinedMappingConfig,
+        mapped: Vec<MappedCombined>,
+        batch_size: usize,
+    ) -> Result<()> {
+        let opts = self.write;
+        match &mut self.target {
+            RunTarget::Graph(graph) => {
+                delete_combined_in_batches_async(graph, combined_cfg, mapped, batch_size, &opts)
+                    .await
+            }
+            RunTarget::Statements(out) => {
+                emit_combined_batches(&mut **out, combined_cfg, &mapped, batch_size, &opts, true)
+            }
+        }
+    }
+}
+
+/// Choices for one run that come from the command line rather than the config: purges,
+/// which mappings run and where they fetch from. The default runs every mapping from its
+/// stored watermark without purging anything.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Delete the whole graph before loading (`--purge-graph`).
+    pub purge_graph: bool,
+    /// Purge these mappings before loading (`--purge-mapping`). Ignored with `purge_graph`.
+    pub purge_mappings: Vec<String>,
+    /// Purging an edge mapping also deletes the endpoint nodes that the purge leaves without
+    /// any relationship (`--purge-orphans`).
+    pub purge_orphaned_endpoints: bool,
+    /// Keep running the remaining mappings after one fails (`--continue-on-error`). The
+    /// config's `continue_on_error` turns this on too.
+    pub continue_on_error: bool,
+    /// Run only these mappings; every mapping when empty. Set by [`RunOptions::select_mappings`].
+    pub only_mappings: Vec<String>,
+    /// Leave these mappings out of the run. Set by [`RunOptions::select_mappings`].
+    pub skip_mappings: Vec<String>,
+    /// Watermarks to fetch from instead of the stored ones, by mapping name. Set by
+    /// [`RunOptions::override_watermarks`]; nothing is saved unless the mapping advances
+    /// normally.
+    pub watermark_overrides: BTreeMap<String, String>,
+}
+
+impl RunOptions {
+    /// Run only the mappings named in `only` (every mapping when it is empty) that aren't
+    /// named in `skip`, for `--only-mapping`/`--skip-mapping`. A name matching no mapping in
+    /// `cfg` is an error. Edges whose endpoint node mapping is left out still run, matching
+    /// nodes already in the graph, with a warning.
+    pub fn select_mappings(
+        &mut self,
+        cfg: &Config,
+        only: &[String],
+        skip: &[String],
+    ) -> Result<()> {
+        let unknown: Vec<&str> = only
+            .iter()
+            .chain(skip)
+            .filter(|name| !cfg.mappings.iter().any(|m| &m.common().name == *name))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(anyhow!("Unknown mapping name(s): {}", unknown.join(", ")));
+        }
+        self.only_mappings = only.to_vec();
+        self.skip_mappings = skip.to_vec();
+
+        for mapping in &cfg.mappings {
+            let EntityMapping::Edge(edge) = mapping else {
+                continue;
+            };
+            if !self.selects(&edge.common.name) {
+                continue;
+            }
+            for endpoint in [&edge.from, &edge.to] {
+                if !self.selects(&endpoint.node_mapping) {
+                    tracing::warn!(
+                        mapping = %edge.common.name,
+                        node_mapping = %endpoint.node_mapping,
+                        "Endpoint node mapping is not selected; edges only match nodes already in the graph",
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the mapping named `name` runs.
+    pub fn selects(&self, name: &str) -> bool {
+        (self.only_mappings.is_empty() || self.only_mappings.iter().any(|n| n == name))
+            && !self.skip_mappings.iter().any(|n| n == name)
+    }
+
+    /// The selected mappings of `cfg`, in [`Config::execution_order`].
+    fn execution_order<'c>(&self, cfg: &'c Config) -> Result<Vec<&'c EntityMapping>> {
+        Ok(cfg
+            .execution_order()?
+            .into_iter()
+            .filter(|m| self.selects(&m.common().name))
+            .collect())
+    }
+
+    /// Parse `--from-watermark NAME=VALUE` overrides into `watermark_overrides`. `NAME` must be
+    /// a selected mapping with a `delta` section and `VALUE` a timestamp (RFC3339, zone-less
+    /// `YYYY-MM-DD HH:MM:SS[.f]` or `YYYY-MM-DD`, taken as UTC); it is stored as RFC3339.
+    /// Every bad entry is listed in the returned error.
+    pub fn override_watermarks(&mut self, cfg: &Config, overrides: &[String]) -> Result<()> {
+        let mut problems = Vec::new();
+        for entry in overrides {
+            let Some((name, value)) = entry.split_once('=') else {
+                problems.push(format!("'{}' is not NAME=VALUE", entry));
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            match cfg.mappings.iter().find(|m| m.common().name == name) {
+                None => problems.push(format!("unknown mapping '{}'", name)),
+                Some(_) if !self.selects(name) => {
+                    problems.push(format!("mapping '{}' is not selected", name))
+                }
+                Some(m) if m.common().delta.is_none() => problems.push(format!(
+                    "mapping '{}' has no delta section, so it has no watermark",
+                    name
+                )),
+                Some(_) => {
+                    let ts = parse_timestamp(value).or_else(|| {
+                        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                            .ok()
+                            .and_then(|d| d.and_hms_opt(0, 0, 0))
+                            .map(|naive| naive.and_utc())
+                    });
+                    match ts {
+                        Some(ts) => {
+                            self.watermark_overrides
+                                .insert(name.to_string(), ts.to_rfc3339());
+                        }
+                        None => problems.push(format!(
+                            "watermark '{}' for mapping '{}' is not a timestamp",
+                            value, name
+                        )),
+                    }
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid --from-watermark: {}", problems.join("; ")))
+        }
+    }
+}
+
+/// Run a single full or incremental synchronization over the mappings `opts` selects.
+///
+/// A failing mapping stops the run unless `opts.continue_on_error` (or the config's
+/// `continue_on_error`) is set, in which case the remaining mappings still run and mappings
+/// that succeed still save their watermarks. Either way the failure is recorded in the returned summary
+/// rather than returned as an error; errors before the first mapping (connecting, loading
+/// watermarks, purging) are returned as errors.
+pub async fn run_once(cfg: &Config, opts: &RunOptions) -> Result<RunSummary> {
+    let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+    run_once_with_graph(&mut graph, cfg, opts).await
+}
+
+/// `run_once` over an existing FalkorDB connection, e.g. one kept open across daemon ticks.
+pub async fn run_once_with_graph(
+    graph: &mut falkordb::AsyncGraph,
+    cfg: &Config,
+    opts: &RunOptions,
+) -> Result<RunSummary> {
+    let (_shutdown_tx, shutdown) = watch::channel(false);
+    let summary = run_sync(graph, cfg, opts, &shutdown).await?;
+    if summary.failed() {
+        METRICS.inc_failed_runs();
+    }
+    Ok(summary)
+}
+
+/// `run_once`, stopping before the next mapping once `shutdown` turns true. Mappings that
+/// already ran keep their writes and watermarks.
+async fn run_sync(
+    graph: &mut falkordb::AsyncGraph,
+    cfg: &Config,
+    opts: &RunOptions,
+    shutdown: &watch::Receiver<bool>,
+) -> Result<RunSummary> {
+    let order = opts.execution_order(cfg)?;
+    let continue_on_error = opts.continue_on_error || cfg.continue_on_error;
+
+    METRICS.inc_runs();
+
+    if let Some(dl) = &cfg.dead_letter {
+        begin_run(dl)?;
+    }
+
+    // Index node mappings by name so edges can look up endpoint labels.
+    let node_by_name = cfg.node_mappings_by_name();
+
+    // Handle purge options
+    if opts.purge_graph {
+        purge_graph(graph, cfg).await?;
+    } else if !opts.purge_mappings.is_empty() {
+        for name in &opts.purge_mappings {
+            if let Some(mapping) = cfg.mappings.iter().find(|m| &m.common().name == name) {
+                purge_mapping(
+                    graph,
+                    mapping,
+                    &node_by_name,
+                    opts.purge_orphaned_endpoints,
+                    cfg.falkordb.query_timeout(),
+                )
+                .await?;
+            } else {
+                tracing::warn!(mapping = %name, "Requested purge for unknown mapping");
+            }
+        }
+    }
+
+    // Load watermarks after the purge: with `state.purge_with_graph` it removes them, and
+    // ones read beforehand would limit the reload of the emptied graph to changed rows.
+    let mut state = StateConnections::default();
+    let watermarks = load_watermarks_with_retry(cfg, graph, &mut state).await?;
+
+    // Refuse to grow a graph that is already over its configured size limits.
+    check_graph_size(graph, cfg).await?;
+
+    // Ensure we have indexes on node key properties before writing data. This improves
+    // MERGE/MATCH performance; later runs of the same process skip indexes already requested.
+    ensure_node_indexes(graph, cfg).await?;
+    ensure_unique_constraints(graph, cfg).await?;
+
+    // Remove soft-deleted nodes whose grace period has expired.
+    reap_expired_tombstones(graph, cfg, opts).await?;
+
+    if cfg.falkordb.warm_plans && !cfg.falkordb.warms_plans() {
+        tracing::warn!("falkordb.warm_plans is ignored with annotate_queries, whose per-batch comments give every batch its own query plan");
+    }
+
+    let mut ctx = RunContext {
+        cfg,
+        opts,
+        target: RunTarget::Graph(graph),
+        watermarks,
+        state,
+        shared: SharedFetches::default(),
+        node_by_name,
+        write: WriteOptions::from_config(&cfg.falkordb),
+        write_pool: Vec::new(),
+    };
+    let summary = run_mappings(&mut ctx, order, continue_on_error, shutdown).await;
+    // A run cut short by a shutdown or a failure leaves rows of later mappings unread.
+    if summary.interrupted || (summary.failed() && !continue_on_error) {
+        return Ok(summary);
+    }
+
+    report_unmatched_rows(cfg, &ctx.shared)?;
+    // Only a clean run makes the loader ready; a run with failed mappings leaves it unready.
+    if !summary.failed() {
+        METRICS.mark_ready();
+    }
+
+    Ok(summary)
+}
+
+/// Sync `order`'s mappings one after another and record each outcome. Stops after the first
+/// failure unless `continue_on_error`, and before the next mapping once `shutdown` turns true.
+async fn run_mappings(
+    ctx: &mut RunContext<'_>,
+    order: Vec<&EntityMapping>,
+    continue_on_error: bool,
+    shutdown: &watch::Receiver<bool>,
+) -> RunSummary {
+    let mut summary = RunSummary::default();
+
+    // For now run mappings sequentially, in `depends_on` order; concurrency can be added later.
+    for mapping in order {
+        let name = &mapping.common().name;
+        if *shutdown.borrow() {
+            tracing::info!(
+                mapping = %name,
+                "Shutdown requested; skipping this and the remaining mappings",
+            );
+            summary.interrupted = true;
+            return summary;
+        }
+
+        let started = Instant::now();
+        let mut outcome = MappingSummary {
+            name: name.clone(),
+            ..Default::default()
+        };
+        let result = match sync_mapping(ctx, mapping, &mut outcome).await {
+            // Statements handed to a sink never reach the graph, so the stream stays put.
+            Ok(()) if matches!(ctx.target, RunTarget::Graph(_)) => {
+                ctx.shared.commit_stream(name).await
+            }
+            other => other,
+        };
+        match result {
+            Ok(()) => {
+                METRICS.record_mapping_success(name, started.elapsed());
+                summary.record(outcome);
+            }
+            Err(e) => {
+                // Roll back an unconsumed stream read so its changes are re-read next run.
+                ctx.shared.streams.remove(name);
+                METRICS.inc_mapping_failed_run(name);
+                if continue_on_error {
+                    tracing::error!(
+                        mapping = %name,
+                        error = %format!("{:#}", e),
+                        "Mapping failed; continuing with the next mapping",
+                    );
+                } else {
+                    tracing::error!(mapping = %name, error = %format!("{:#}", e), "Mapping failed");
+                }
+                outcome.error = Some(format!("{:#}", e));
+                summary.record(outcome);
+                if !continue_on_error {
+                    break;
+                }
+            }
+        }
+    }
+
+    summary
+}
+
+/// Advance a delta mapping's watermark to `newest`, the newest `updated_at` among the rows it
+/// fetched, and persist it. Only called once every write and delete for those rows has
+/// succeeded, so a failed write leaves the stored watermark where it was and the next run
+/// fetches the rows again.
+/// Only this mapping's watermark is written (see [`crate::state::StateStore::save_one`]), and the in-memory
+/// one moves only after the save succeeds. When no row carries a timestamp and nothing is
+/// stored yet, the `start` watermark the run fetched from is saved, so a mapping that began
+/// at the current time keeps that starting point.
+async fn advance_watermark(
+    ctx: &mut RunContext<'_>,
+    common: &CommonMappingFields,
+    newest: Option<DateTime<Utc>>,
+    start: Option<&str>,
+) -> Result<()> {
+    if common.delta.is_none() {
+        return Ok(());
+    }
+    let next = match newest {
+        Some(max_ts) => max_ts.to_rfc3339(),
+        None => match start {
+            Some(start) if !ctx.watermarks.contains_key(&common.name) => start.to_string(),
+            _ => return Ok(()),
+        },
+    };
+    let RunTarget::Graph(graph) = &mut ctx.target else {
+        // Statements handed to a sink were never written, so the watermark stays.
+        return Ok(());
+    };
+    save_watermark(ctx.cfg, graph, &mut ctx.state, &common.name, &next).await?;
+    ctx.watermarks.insert(common.name.clone(), next);
+    Ok(())
+}
+
+/// Fetch, map and write one mapping, saving its watermark afterwards. Rows are handled a chunk
+/// at a time (see [`RowStream`]): each chunk is mapped and written before the next is read.
+/// Row counts go into `summary` as each chunk is done, so a failed mapping still reports how
+/// far it got.
+async fn sync_mapping(
+    ctx: &mut RunContext<'_>,
+    mapping: &EntityMapping,
+    summary: &mut MappingSummary,
+) -> Result<()> {
+    let cfg = ctx.cfg;
+    let batch_size = cfg.batch_size(mapping.common());
+    let write_opts = ctx.write;
+
+    match mapping {
+        EntityMapping::Node(node_cfg) => {
+            let common = &node_cfg.common;
+            tracing::info!(mapping = %common.name, "Processing node mapping");
+            METRICS.inc_mapping_run(&common.name);
+
+            let mut timings = PhaseTimings::default();
+            let watermark = run_watermark(ctx.opts, common, ctx.watermarks.get(&common.name));
+            let watermark = watermark.as_deref();
+            let phase = Instant::now();
+            // A chunk holds one batch per writer, so concurrent writers all have work.
+            let chunk_rows = batch_size.saturating_mul(write_opts.concurrency);
+            let mut rows = ctx.shared.open(cfg, common, watermark, chunk_rows).await?;
+            rows.check_guards(cfg, watermark).await?;
+            timings.fetch += phase.elapsed();
+            let mut source_keys: Option<HashSet<String>> =
+                node_cfg.reconcile_deletes.then(HashSet::new);
+            let mut skipped_rows = 0;
+            let mut warm_plans = cfg.falkordb.warms_plans();
+
+            loop {
+                let phase = Instant::now();
+                let Some(chunk) = rows.next_chunk().await? else {
+                    break;
+                };
+                timings.fetch += phase.elapsed();
+                record_fetched(common, chunk.len(), summary);
+
+                let phase = Instant::now();
+                let (active_rows, deleted_rows) =
+                    partition_by_deleted(chunk, common.delta.as_ref());
+                let (nodes, skipped) = map_nodes(cfg, node_cfg, &active_rows, rows.chunk_start)?;
+                skipped_rows += skipped;
+                if let Some(keys) = &mut source_keys {
+                    keys.extend(nodes.iter().filter_map(|n| node_key_text(node_cfg, &n.key)));
+                }
+                timings.map += phase.elapsed();
+                let phase = Instant::now();
+                if std::mem::take(&mut warm_plans) {
+                    if let Some(graph) = ctx.graph() {
+                        warm_node_plans(graph, node_cfg, cfg.falkordb.query_timeout()).await;
+                    }
+                }
+                let written = nodes.len();
+                tracing::info!(mapping = %common.name, rows = written, "Writing nodes");
+                ctx.write_nodes(node_cfg, nodes, batch_size).await?;
+                record_written(common, written, summary);
+                timings.write += phase.elapsed();
+
+                if !deleted_rows.is_empty() {
+                    let phase = Instant::now();
+                    let (deleted_nodes, skipped) =
+                        map_nodes(cfg, node_cfg, &deleted_rows, rows.chunk_start)?;
+                    skipped_rows += skipped;
+                    // Soft-deleted rows are still in the source; `soft_delete` mappings keep
+                    // their nodes, which reconciling must not then remove.
+                    if let Some(keys) = &mut source_keys {
+                        keys.extend(
+                            deleted_nodes
+                                .iter()
+                                .filter_map(|n| node_key_text(node_cfg, &n.key)),
+                        );
+                    }
+                    timings.map += phase.elapsed();
+                    let deleted = deleted_nodes.len();
+                    tracing::info!(mapping = %common.name, rows = deleted, "Deleting nodes");
+                    let phase = Instant::now();
+                    ctx.delete_nodes(node_cfg, deleted_nodes, batch_size)
+                        .await?;
+                    record_deleted(common, deleted, summary);
+                    timings.write += phase.elapsed();
+                }
+            }
+
+            tracing::info!(mapping = %common.name, rows = rows.fetched, "Fetched rows");
+            if rows.fetched == 0 && !node_cfg.reconcile_deletes {
+                return finish_empty_mapping(ctx, common, watermark, &timings).await;
+            }
+
+            let source_keys =
+                source_keys.filter(|keys| reconcile_allowed(common, keys, skipped_rows));
+            if let (Some(source_keys), Some(graph)) = (source_keys, ctx.graph()) {
+                let phase = Instant::now();
+                let reconciled =
+                    reconcile_node_deletes(graph, node_cfg, &source_keys, batch_size, &write_opts)
+                        .await?;
+                timings.write += phase.elapsed();
+                record_deleted(common, reconciled as usize, summary);
+                tracing::info!(mapping = %common.name, nodes = reconciled, "Deleted nodes missing from the source");
+            }
+
+            advance_watermark(ctx, common, rows.newest, watermark).await?;
+
+            timings.record(&common.name);
+        }
+        EntityMapping::Edge(edge_cfg) => {
+            let common = &edge_cfg.common;
+            tracing::info!(mapping = %common.name, "Processing edge mapping");
+            METRICS.inc_mapping_run(&common.name);
+
+            let (from_labels, to_labels) = edge_endpoint_labels(edge_cfg, &ctx.node_by_name)?;
+
+            let mut timings = PhaseTimings::default();
+            let watermark = run_watermark(ctx.opts, common, ctx.watermarks.get(&common.name));
+            let watermark = watermark.as_deref();
+            let phase = Instant::now();
+            let mut rows = ctx.shared.open(cfg, common, watermark, batch_size).await?;
+            rows.check_guards(cfg, watermark).await?;
+            timings.fetch += phase.elapsed();
+            let mut source_keys: Option<HashSet<String>> =
+                edge_cfg.reconcile_deletes.then(HashSet::new);
+            let mut skipped_rows = 0;
+            let mut endpoints = edge_cfg.check_endpoints.then(EndpointKeys::default);
+            let mut warm_plans = cfg.falkordb.warms_plans();
+
+            loop {
+                let phase = Instant::now();
+                // Duplicates are collapsed within what is mapped at once, so `dedupe` mappings
+                // read all their rows as one chunk.
+                let chunk = if edge_cfg.dedupe.is_some() {
+                    rows.rest().await?
+                } else {
+                    rows.next_chunk().await?
+                };
+                let Some(chunk) = chunk else {
+                    break;
+                };
+                timings.fetch += phase.elapsed();
+                record_fetched(common, chunk.len(), summary);
+
+                let phase = Instant::now();
+                let (active_rows, deleted_rows) =
+                    partition_by_deleted(chunk, common.delta.as_ref());
+                let mapped = map_edges(cfg, edge_cfg, &active_rows, rows.chunk_start, false)?;
+                skipped_rows += mapped.skipped.len();
+                record_null_endpoint_rows(common, mapped.null_endpoint_rows.len(), false);
+                if let Some(keys) = &mut source_keys {
+                    skipped_rows += extend_edge_source_keys(
+                        keys,
+                        edge_cfg,
+                        &mapped.edges,
+                        &mapped.null_endpoint_rows,
+                    );
+                }
+                let edges = mapped.edges;
+                if let Some(endpoints) = &mut endpoints {
+                    endpoints.extend(&edges);
+                }
+                timings.map += phase.elapsed();
+                let phase = Instant::now();
+                if std::mem::take(&mut warm_plans) {
+                    if let Some(graph) = ctx.graph() {
+                        warm_edge_plans(
+                            graph,
+                            edge_cfg,
+                            &from_labels,
+                            &to_labels,
+                            cfg.falkordb.query_timeout(),
+                        )
+                        .await;
+                    }
+                }
+                let written = edges.len();
+                tracing::info!(mapping = %common.name, rows = written, "Writing edges");
+                ctx.write_edges(edge_cfg, edges, &from_labels, &to_labels, batch_size)
+                    .await?;
+                record_written(common, written, summary);
+                timings.write += phase.elapsed();
+
+                if !deleted_rows.is_empty() {
+                    let phase = Instant::now();
+                    let mapped = map_edges(cfg, edge_cfg, &deleted_rows, rows.chunk_start, true)?;
+                    skipped_rows += mapped.skipped.len();
+                    record_null_endpoint_rows(common, mapped.null_endpoint_rows.len(), true);
+                    if let Some(keys) = &mut source_keys {
+                        skipped_rows += extend_edge_source_keys(
+                            keys,
+                            edge_cfg,
+                            &mapped.edges,
+                            &mapped.null_endpoint_rows,
+                        );
+                    }
+                    let deleted_edges = mapped.edges;
+                    timings.map += phase.elapsed();
+                    let deleted = deleted_edges.len();
+                    tracing::info!(mapping = %common.name, rows = deleted, "Deleting edges");
+                    let phase = Instant::now();
+                    ctx.delete_edges(
+                        edge_cfg,
+                        deleted_edges,
+                        &from_labels,
+                        &to_labels,
+                        batch_size,
+                    )
+                    .await?;
+                    record_deleted(common, deleted, summary);
+                    timings.write += phase.elapsed();
+                }
+            }
+
+            tracing::info!(mapping = %common.name, rows = rows.fetched, "Fetched rows");
+            if rows.fetched == 0 && !edge_cfg.reconcile_deletes {
+                return finish_empty_mapping(ctx, common, watermark, &timings).await;
+            }
+
+            if let (Some(endpoints), Some(graph)) = (endpoints, ctx.graph()) {
+                let phase = Instant::now();
+                let missing_from = count_missing_endpoints(
+                    graph,
+                    &from_labels,
+                    &endpoints.from,
+                    batch_size,
+                    write_opts.query_timeout,
+                )
+                .await?;
+                let missing_to = count_missing_endpoints(
+                    graph,
+                    &to_labels,
+                    &endpoints.to,
+                    batch_size,
+                    write_opts.query_timeout,
+                )
+                .await?;
+                timings.write += phase.elapsed();
+                METRICS.add_mapping_missing_endpoints(&common.name, missing_from, missing_to);
+                if missing_from > 0 || missing_to > 0 {
+                    tracing::warn!(
+                        mapping = %common.name,
+                        missing_from,
+                        checked_from = endpoints.from.len(),
+                        missing_to,
+                        checked_to = endpoints.to.len(),
+                        "Edge rows reference endpoints that don't exist in the graph",
+                    );
+                } else {
+                    tracing::info!(mapping = %common.name, "All edge endpoints exist");
+                }
+            }
+
+            let source_keys =
+                source_keys.filter(|keys| reconcile_allowed(common, keys, skipped_rows));
+            if let (Some(source_keys), Some(graph)) = (source_keys, ctx.graph()) {
+                let phase = Instant::now();
+                let reconciled = reconcile_edge_deletes(
+                    graph,
+                    edge_cfg,
+                    &from_labels,
+                    &to_labels,
+                    &source_keys,
+                    batch_size,
+                    &write_opts,
+                )
+                .await?;
+                timings.write += phase.elapsed();
+                record_deleted(common, reconciled as usize, summary);
+                tracing::info!(mapping = %common.name, edges = reconciled, "Deleted edges missing from the source");
+            }
+
+            advance_watermark(ctx, common, rows.newest, watermark).await?;
+
+            timings.record(&common.name);
+        }
+        EntityMapping::Combined(combined_cfg) => {
+            let common = &combined_cfg.common;
+            let name = &common.name;
+            tracing::info!(mapping = %name, "Processing combined mapping");
+            METRICS.inc_mapping_run(name);
+
+            let mut timings = PhaseTimings::default();
+            let watermark = run_watermark(ctx.opts, common, ctx.watermarks.get(name));
+            let watermark = watermark.as_deref();
+            let phase = Instant::now();
+            let mut rows = ctx.shared.open(cfg, common, watermark, batch_size).await?;
+            rows.check_guards(cfg, watermark).await?;
+            timings.fetch += phase.elapsed();
+            let mut warm_plans = cfg.falkordb.warms_plans();
+
+            loop {
+                let phase = Instant::now();
+                let Some(chunk) = rows.next_chunk().await? else {
+                    break;
+                };
+                timings.fetch += phase.elapsed();
+                record_fetched(common, chunk.len(), summary);
+
+                let phase = Instant::now();
+                let (active_rows, deleted_rows) =
+                    partition_by_deleted(chunk, common.delta.as_ref());
+                let mapped = map_combined(cfg, combined_cfg, &active_rows, rows.chunk_start)?;
+                let deleted = map_combined(cfg, combined_cfg, &deleted_rows, rows.chunk_start)?;
+                timings.map += phase.elapsed();
+
+                let phase = Instant::now();
+                if std::mem::take(&mut warm_plans) {
+                    if let Some(graph) = ctx.graph() {
+                        warm_combined_plans(graph, combined_cfg, cfg.falkordb.query_timeout())
+                            .await;
+                    }
+                }
+                let written = mapped.len();
+                tracing::info!(mapping = %name, rows = written, "Writing nodes and edges");
+                ctx.write_combined(combined_cfg, mapped, batch_size).await?;
+                record_written(common, written, summary);
+
+                if !deleted.is_empty() {
+                    let deleted_count = deleted.len();
+                    tracing::info!(mapping = %name, rows = deleted_count, "Deleting edges");
+                    ctx.delete_combined(combined_cfg, deleted, batch_size)
+                        .await?;
+                    record_deleted(common, deleted_count, summary);
+                }
+                timings.write += phase.elapsed();
+            }
+
+            tracing::info!(mapping = %name, rows = rows.fetched, "Fetched rows");
+            if rows.fetched == 0 {
+                return finish_empty_mapping(ctx, common, watermark, &timings).await;
+            }
+
+            advance_watermark(ctx, common, rows.newest, watermark).await?;
+
+            timings.record(name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Count a fetched chunk in the metrics and the mapping's summary.
+fn record_fetched(common: &CommonMappingFields, rows: usize, summary: &mut MappingSummary) {
+    METRICS.add_rows_fetched(rows as u64);
+    METRICS.add_mapping_rows_fetched(&common.name, rows as u64);
+    summary.rows_fetched += rows as u64;
+}
+
+/// Count rows whose write has completed, so a failed write never shows up as written.
+fn record_written(common: &CommonMappingFields, rows: usize, summary: &mut MappingSummary) {
+    METRICS.add_rows_written(rows as u64);
+    METRICS.add_mapping_rows_written(&common.name, rows as u64);
+    summary.rows_written += rows as u64;
+}
+
+/// Count rows whose delete has completed.
+fn record_deleted(common: &CommonMappingFields, rows: usize, summary: &mut MappingSummary) {
+    METRICS.add_rows_deleted(rows as u64);
+    METRICS.add_mapping_rows_deleted(&common.name, rows as u64);
+    summary.rows_deleted += rows as u64;
+}
+
+/// Distinct edge endpoints gathered across a `check_endpoints` mapping's chunks, checked
+/// once every chunk is written.
+#[derive(Default)]
+struct EndpointKeys {
+    seen: HashSet<(bool, String)>,
+    from: Vec<JsonMap<String, JsonValue>>,
+    to: Vec<JsonMap<String, JsonValue>>,
+}
+
+impl EndpointKeys {
+    fn extend(&mut self, edges: &[MappedEdge]) {
+        let (from, to) = distinct_endpoints(edges);
+        for (is_to, keys) in [(false, from), (true, to)] {
+            for key in keys {
+                if self
+                    .seen
+                    .insert((is_to, JsonValue::Object(key.clone()).to_string()))
+                {
+                    if is_to {
+                        self.to.push(key);
+                    } else {
+                        self.from.push(key);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Finish a mapping whose fetch returned no rows without sending any write or delete queries.
+/// The per-mapping fetched, written and deleted counters still get (zero) samples, and the
+/// watermark is handled as for any other run.
+async fn finish_empty_mapping(
+    ctx: &mut RunContext<'_>,
+    common: &CommonMappingFields,
+    watermark: Option<&str>,
+    timings: &PhaseTimings,
+) -> Result<()> {
+    tracing::info!(mapping = %common.name, "No rows fetched; nothing to write");
+    METRICS.add_mapping_rows_fetched(&common.name, 0);
+    METRICS.add_mapping_rows_written(&common.name, 0);
+    METRICS.add_mapping_rows_deleted(&common.name, 0);
+    advance_watermark(ctx, common, None, watermark).await?;
+    timings.record(&common.name);
+    Ok(())
+}
+
+/// A channel that turns true on Ctrl-C or, on Unix, SIGTERM.
+pub fn shutdown_on_signal() -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut term) => {
+                    tokio::select! {
+                        _ = tokio::signal::ctrl_c() => {}
+                        _ = term.recv() => {}
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to install SIGTERM handler");
+                    let _ = tokio::signal::ctrl_c().await;
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+
+        tracing::info!("Shutdown signal received; finishing the current mapping before exiting");
+        let _ = tx.send(true);
+    });
+    rx
+}
+
+/// Resolve once `shutdown` turns true. Never resolves if the sender goes away first.
+async fn shutdown_requested(shutdown: &mut watch::Receiver<bool>) {
+    if shutdown.wait_for(|stop| *stop).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Run daemon mode: repeatedly call run_once at a fixed interval. The purges in `opts` are
+/// applied only on the first run.
+///
+/// One FalkorDB connection is opened on the first tick and reused by every run. It is only
+/// replaced when a run fails with a connection-level error; a failed connect is retried on
+/// the next tick.
+///
+/// With `max_runs`, the daemon returns once that many runs have been attempted, whether they
+/// succeeded or failed (a failed connect counts as a failed run).
+///
+/// When `shutdown` turns true the daemon finishes the mapping in progress, skips the rest of
+/// that run and returns; while idle it returns immediately. Returns the number of runs
+/// attempted.
+pub async fn run_daemon(
+    cfg: &Config,
+    opts: &RunOptions,
+    interval_secs: u64,
+    max_runs: Option<u64>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<u64> {
+    use tokio::time::{interval, Duration};
+
+    let mut ticker = interval(Duration::from_secs(interval_secs));
+    let mut opts = opts.clone();
+    let mut connection: Option<FalkorConnection> = None;
+    let mut runs: u64 = 0;
+
+    loop {
+        if max_runs.is_some_and(|max| runs >= max) {
+            tracing::info!(runs, "Reached the maximum number of runs");
+            break;
+        }
+        tokio::select! {
+            biased;
+            _ = shutdown_requested(&mut shutdown) => break,
+            _ = ticker.tick() => {}
+        }
+        runs += 1;
+
+        if connection.is_none() {
+            match connect_falkordb_async(&cfg.falkordb).await {
+                Ok(graph) => connection = Some(graph),
+                Err(e) => {
+                    tracing::error!(error = %e, "Could not connect to FalkorDB; retrying on the next run");
+                    METRICS.inc_failed_runs();
+                    continue;
+                }
+            }
+        }
+        let Some(graph) = connection.as_mut() else {
+            continue;
+        };
+
+        tracing::info!("Starting sync run");
+        if let Err(e) = run_sync(graph, cfg, &opts, &shutdown)
+            .await
+            .and_then(RunSummary::into_result)
+        {
+            tracing::error!(error = %e, "Sync run failed");
+            METRICS.inc_failed_runs();
+            if is_connection_error(&e) {
+                tracing::warn!("Dropping the FalkorDB connection; reconnecting on the next run");
+                connection = None;
+            }
+        }
+
+        opts.purge_graph = false;
+        opts.purge_mappings.clear();
+    }
+
+    tracing::info!("Daemon shut down cleanly");
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        CommonMappingFields, ConfigFormat, DeltaSpec, EntityMapping, FalkorConfig, Mode,
+        NodeKeySpec, NodeMappingConfig, PropertySpec, SourceConfig, StateBackendKind, StateConfig,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn index_targets_are_deduplicated_by_labels_and_property() -> Result<()> {
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: "g" }
+            mappings:
+              - { type: node, name: a, source: {}, labels: [Customer], key: { column: ID, property: id }, properties: {} }
+              - { type: node, name: b, source: {}, labels: [Customer], key: { column: ID, property: id }, properties: {} }
+              - { type: node, name: c, source: {}, labels: [Customer], key: { column: EMAIL, property: email }, properties: {} }
+              - { type: node, name: d, source: {}, labels: [], key: { column: ID, property: id }, properties: {} }
+            "#,
+            None,
+        )?;
+
+        let targets = node_index_targets(&cfg.mappings);
+        assert_eq!(
+            targets,
+            vec![
+                ("a", "Customer".to_string(), "id".to_string()),
+                ("c", "Customer".to_string(), "email".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn extra_indexes_follow_key_indexes_unless_indexes_are_unmanaged() -> Result<()> {
+        let config = |falkordb: &str| {
+            Config::from_str(
+                &format!(
+                    r#"
+                    falkordb: {{ endpoint: "falkor://127.0.0.1:6379", graph: "g", {} }}
+                    mappings:
+                      - {{ type: node, name: a, source: {{}}, labels: [Customer], key: {{ column: ID, property: id }}, properties: {{}} }}
+                    "#,
+                    falkordb
+                ),
+                None,
+            )
+        };
+
+        let cfg = config(
+            "indexes: [{ label: Customer, property: email }, { label: Customer, property: id }, { label: Order, property: placed_at }]",
+        )?;
+        cfg.validate()?;
+        let commands: Vec<(&str, String)> = index_creation_commands(&cfg)
+            .into_iter()
+            .map(|(owner, _, _, cypher)| (owner, cypher))
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                ("a", "CREATE INDEX ON :Customer(id)".to_string()),
+                (
+                    "falkordb.indexes",
+                    "CREATE INDEX ON :Customer(email)".to_string()
+                ),
+                (
+                    "falkordb.indexes",
+                    "CREATE INDEX ON :Order(placed_at)".to_string()
+                ),
+            ]
+        );
+
+        let unmanaged = config("manage_indexes: false")?;
+        assert!(index_creation_commands(&unmanaged).is_empty());
+        // --drop-indexes still knows which indexes the loader would own.
+        assert_eq!(index_targets(&unmanaged).len(), 1);
+
+        let err = config("manage_indexes: false, indexes: [{ label: Customer, property: email }]")?
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("falkordb.indexes is set but falkordb.manage_indexes is false"));
+        Ok(())
+    }
+
+    #[test]
+    fn validate_config_checks_transforms_that_config_validation_skips() -> Result<()> {
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: "g" }
+            mappings:
+              - type: node
+                name: people
+                source: { file: people.json }
+                labels: [Person]
+                key: { column: ID, property: id }
+                properties: {}
+                transforms: [{ name: rot13 }, { name: sha256 }]
+            "#,
+            None,
+        )?;
+        cfg.validate()?;
+        let err = validate_config(&cfg).unwrap_err().to_string();
+        assert!(err.contains("2 problem(s)"), "{}", err);
+        assert!(
+            err.contains("mapping 'people': unknown transform 'rot13'"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("transform 'sha256' needs a columns list"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dry_run_skips_index_creation_when_indexes_are_unmanaged() -> Result<()> {
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_unmanaged_indexes.json");
+        std::fs::write(&input_path, r#"[{"ID": 1}, {"ID": 2}]"#)?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "falkor://127.0.0.1:1", graph: "g", manage_indexes: false }}
+                state: {{ backend: none }}
+                mappings:
+                  - {{ type: node, name: people, source: {{ file: "{}" }}, labels: [Person], key: {{ column: ID, property: id }}, properties: {{}} }}
+                "#,
+                input_path.display()
+            ),
+            None,
+        )?;
+
+        // Only the write batch; no CREATE INDEX.
+        let log = dry_run(&cfg, &RunOptions::default()).await?;
+        assert_eq!(log.statements, 1);
+        assert_eq!(log.rows, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn unique_key_mappings_get_a_constraint_on_their_first_label() -> Result<()> {
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: "g" }
+            mappings:
+              - { type: node, name: a, source: {}, labels: [Customer, Person], key: { column: ID, property: id }, properties: {}, unique_key: true }
+              - { type: node, name: b, source: {}, labels: [Account], key: [{ column: REGION, property: region }, { column: ID, property: account_id }], properties: {}, unique_key: true }
+              - { type: node, name: c, source: {}, labels: [Order], key: { column: ID, property: id }, properties: {} }
+            "#,
+            None,
+        )?;
+
+        let commands: Vec<String> = unique_constraint_targets(&cfg.mappings)
+            .into_iter()
+            .map(|(_, label, props)| unique_constraint_command("g", label, &props))
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                "GRAPH.CONSTRAINT CREATE g UNIQUE NODE Customer PROPERTIES 1 id",
+                "GRAPH.CONSTRAINT CREATE g UNIQUE NODE Account PROPERTIES 2 region account_id",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn schema_mismatches_report_missing_labels_and_indexes() -> Result<()> {
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: "g" }
+            mappings:
+              - { type: node, name: customers, source: {}, labels: [Customer], key: { column: ID, property: id }, properties: {} }
+              - { type: node, name: orders, source: {}, labels: [Order], key: { column: ID, property: order_id }, properties: {} }
+            "#,
+            None,
+        )?;
+
+        let labels: HashSet<String> = ["Customer".to_string(), "Order".to_string()].into();
+        let mut indexes: HashSet<(String, String)> = HashSet::new();
+        indexes.insert(("Customer".to_string(), "id".to_string()));
+        indexes.insert(("Order".to_string(), "order_id".to_string()));
+        assert!(schema_mismatches(&cfg.mappings, &labels, &indexes).is_empty());
+
+        indexes.remove(&("Order".to_string(), "order_id".to_string()));
+        let labels: HashSet<String> = ["Order".to_string()].into();
+        let mismatches = schema_mismatches(&cfg.mappings, &labels, &indexes);
+        assert_eq!(mismatches.len(), 2);
+        assert!(mismatches[0].contains("label 'Customer'"));
+        assert!(mismatches[1].contains(":Order(order_id)"));
+        Ok(())
+    }
+
+    #[test]
+    fn graph_size_limits_report_each_exceeded_limit() {
+        let mut cfg = Config::default();
+        assert!(graph_size_violations(&cfg, 10, 10).is_empty());
+
+        cfg.falkordb.max_graph_nodes = Some(100);
+        cfg.falkordb.max_graph_edges = Some(50);
+        assert!(graph_size_violations(&cfg, 100, 50).is_empty());
+
+        let violations = graph_size_violations(&cfg, 101, 51);
+        assert_eq!(violations.len(), 2);
+        assert!(violations[0].contains("max_graph_nodes 100"));
+        assert!(violations[1].contains("max_graph_edges 50"));
+    }
+
+    #[test]
+    fn unmatched_rows_are_those_no_condition_selects() {
+        let rows: Vec<LogicalRow> = ["order", "refund", "note"]
+            .iter()
+            .map(|t| LogicalRow {
+                values: serde_json::json!({ "RECORD_TYPE": t })
+                    .as_object()
+                    .cloned()
+                    .unwrap(),
+            })
+            .collect();
+        let orders = WhenCondition {
+            column: "RECORD_TYPE".to_string(),
+            equals: serde_json::json!("order"),
+        };
+        let refunds = WhenCondition {
+            column: "RECORD_TYPE".to_string(),
+            equals: serde_json::json!("refund"),
+        };
+
+        let unmatched = unmatched_rows(&rows, &[&orders, &refunds]);
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(
+            unmatched[0].get("RECORD_TYPE"),
+            Some(&serde_json::json!("note"))
+        );
+    }
+
+    #[test]
+    fn shared_fetches_are_keyed_on_what_is_read() -> Result<()> {
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: "g" }
+            mappings:
+              - type: node
+                name: orders
+                source: { table: EVENTS }
+                mode: incremental
+                delta: { updated_at_column: CREATED_AT }
+                when: { column: RECORD_TYPE, equals: order }
+                labels: [Order]
+                key: { column: ID, property: id }
+                properties: {}
+              - type: node
+                name: refunds
+                source: { table: EVENTS }
+                mode: incremental
+                delta: { updated_at_column: UPDATED_AT }
+                when: { column: RECORD_TYPE, equals: refund }
+                labels: [Refund]
+                key: { column: ID, property: id }
+                properties: {}
+              - type: node
+                name: notes
+                source: { table: EVENTS }
+                mode: incremental
+                delta: { updated_at_column: UPDATED_AT }
+                when: { column: RECORD_TYPE, equals: note }
+                labels: [Note]
+                key: { column: ID, property: id }
+                properties: {}
+            "#,
+            Some(ConfigFormat::Yaml),
+        )?;
+        let key =
+            |i: usize| SharedFetches::source_key(cfg.mappings[i].common(), Some("2024-01-01"));
+
+        // Same table, but a different delta column selects different rows.
+        assert_ne!(key(0)?, key(1)?);
+        assert_eq!(key(1)?, key(2)?);
+        assert_eq!(
+            key(1)?,
+            "SELECT * FROM EVENTS WHERE UPDATED_AT > '2024-01-01'"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn emit_cypher_writes_indexes_then_batches() -> Result<()> {
+        let tmp_dir = std::env::temp_dir();
+        let input_path = tmp_dir.join("snowflake_to_falkordb_emit_nodes.json");
+        let output_path = tmp_dir.join("snowflake_to_falkordb_emit.cypher");
+        std::fs::write(
+            &input_path,
+            r#"[{"ID": 1, "NAME": "O'Brien"}, {"ID": 2, "NAME": "Bob"}, {"ID": 3, "NAME": "Eve"}]"#,
+        )?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "falkor://127.0.0.1:6379", graph: "g", max_unwind_batch_size: 2 }}
+                mappings:
+                  - {{ type: node, name: people, source: {{ file: "{}" }}, labels: [Person], key: {{ column: ID, property: id }}, properties: {{ name: {{ column: NAME }} }} }}
+                "#,
+                input_path.display()
+            ),
+            None,
+        )?;
+
+        assert_eq!(
+            emit_cypher(&cfg, &RunOptions::default(), &output_path).await?,
+            3
+        );
+        let text = std::fs::read_to_string(&output_path)?;
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "CREATE INDEX ON :Person(id)");
+        assert!(lines[1].starts_with("CYPHER rows=[{`key`: 1,"));
+        assert!(lines[1].contains("'O\\'Brien'"));
+        assert!(lines[1].contains("MERGE (n:Person { id: row.key })"));
+        assert!(lines[2].starts_with("CYPHER rows=[{`key`: 3,"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dry_run_fetches_rows_without_touching_the_graph() -> Result<()> {
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_dry_run_nodes.json");
+        std::fs::write(
+            &input_path,
+            r#"[{"ID": 1, "NAME": "Ann"}, {"ID": 2, "NAME": "Bob"}, {"ID": 3, "NAME": "Eve"}]"#,
+        )?;
+
+        // Nothing listens on port 1, so any attempt to write to the graph would fail.
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "falkor://127.0.0.1:1", graph: "g", max_unwind_batch_size: 2 }}
+                state: {{ backend: none }}
+                mappings:
+                  - {{ type: node, name: people, source: {{ file: "{}" }}, labels: [Person], key: {{ column: ID, property: id }}, properties: {{ name: {{ column: NAME }} }} }}
+                "#,
+                input_path.display()
+            ),
+            None,
+        )?;
+
+        let log = dry_run(&cfg, &RunOptions::default()).await?;
+        assert_eq!(log.statements, 3);
+        assert_eq!(log.rows, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dry_run_takes_the_sync_path_without_saving_watermarks() -> Result<()> {
+        let tmp_dir = std::env::temp_dir();
+        let input_path = tmp_dir.join("snowflake_to_falkordb_dry_run_sync.json");
+        let state_path = tmp_dir.join("snowflake_to_falkordb_dry_run_sync_state.json");
+        let _ = std::fs::remove_file(&state_path);
+        std::fs::write(
+            &input_path,
+            r#"[
+                {"ID": 1, "UPDATED_AT": "2024-01-01T00:00:00Z", "DELETED": false},
+                {"ID": 2, "UPDATED_AT": "2024-01-02T00:00:00Z", "DELETED": true},
+                {"ID": 3, "UPDATED_AT": "2024-01-03T00:00:00Z", "DELETED": false}
+            ]"#,
+        )?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "falkor://127.0.0.1:1", graph: "g", annotate_queries: true }}
+                state: {{ backend: file, file_path: "{}" }}
+                mappings:
+                  - type: node
+                    name: people
+                    source: {{ file: "{}" }}
+                    mode: incremental
+                    delta: {{ updated_at_column: UPDATED_AT, deleted_flag_column: DELETED, deleted_flag_value: true }}
+                    labels: [Person]
+                    key: {{ column: ID, property: id }}
+                    properties: {{}}
+                "#,
+                state_path.display(),
+                input_path.display()
+            ),
+            None,
+        )?;
+
+        // The index, then the chunk's write batch and its delete batch, as sync_mapping
+        // sends them.
+        let log = dry_run(&cfg, &RunOptions::default()).await?;
+        assert_eq!((log.statements, log.rows), (3, 3));
+        assert!(!state_path.exists(), "a dry run must not save watermarks");
+
+        let output_path = tmp_dir.join("snowflake_to_falkordb_dry_run_sync.cypher");
+        emit_cypher(&cfg, &RunOptions::default(), &output_path).await?;
+        let text = std::fs::read_to_string(&output_path)?;
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].contains("/* mapping=people batch=1 */"));
+        assert!(lines[2].contains("DELETE"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn empty_source_issues_no_write_queries() -> Result<()> {
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_empty_source.json");
+        std::fs::write(&input_path, "[]")?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "falkor://127.0.0.1:1", graph: "g" }}
+                state: {{ backend: none }}
+                mappings:
+                  - {{ type: node, name: people, source: {{ file: "{}" }}, labels: [Person], key: {{ column: ID, property: id }}, properties: {{}} }}
+                "#,
+                input_path.display()
+            ),
+            None,
+        )?;
+
+        // Only the index statement; no UNWIND batches.
+        let log = dry_run(&cfg, &RunOptions::default()).await?;
+        assert_eq!((log.statements, log.rows), (1, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn schema_commands_are_sent_once_per_graph() -> Result<()> {
+        let config = |graph: &str| {
+            Config::from_str(
+                &format!(
+                    r#"{{ "falkordb": {{ "endpoint": "falkor://schema-once:6379", "graph": "{}" }}, "mappings": [] }}"#,
+                    graph
+                ),
+                None,
+            )
+        };
+        let (first, second) = (config("first")?, config("second")?);
+        let mut first_in_db_2 = config("first")?;
+        first_in_db_2.falkordb.db_index = Some(2);
+        let command = "CREATE INDEX ON :Person(id)";
+
+        // Nothing is recorded until the command succeeded, so a failed one is retried.
+        assert!(schema_command_is_new(&first, command));
+        assert!(schema_command_is_new(&first, command));
+        record_schema_command(&first, command);
+        assert!(!schema_command_is_new(&first, command));
+        assert!(schema_command_is_new(&first_in_db_2, command));
+        assert!(schema_command_is_new(&second, command));
+        record_schema_command(&second, command);
+
+        forget_schema_commands(&first);
+        assert!(schema_command_is_new(&first, command));
+        assert!(!schema_command_is_new(&second, command));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mapping_batch_size_overrides_the_global_one() -> Result<()> {
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_batch_size.json");
+        let rows: Vec<serde_json::Value> =
+            (1..=12).map(|id| serde_json::json!({ "ID": id })).collect();
+        std::fs::write(&input_path, serde_json::to_string(&rows)?)?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "falkor://127.0.0.1:1", graph: "g", max_unwind_batch_size: 2 }}
+                state: {{ backend: none }}
+                mappings:
+                  - {{ type: node, name: people, source: {{ file: "{}" }}, labels: [Person], key: {{ column: ID, property: id }}, properties: {{}}, batch_size: 5 }}
+                "#,
+                input_path.display()
+            ),
+            None,
+        )?;
+        assert_eq!(cfg.batch_size(cfg.mappings[0].common()), 5);
+
+        // One index statement, then 12 rows in batches of 5, 5 and 2.
+        let log = dry_run(&cfg, &RunOptions::default()).await?;
+        assert_eq!(log.statements, 4);
+        assert_eq!(log.rows, 12);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn streamed_rows_keep_the_newest_watermark_across_chunks() -> Result<()> {
+        // 2,500 rows whose newest timestamp sits in the first chunk.
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_streamed.jsonl");
+        let lines: String = (1..=2500)
+            .map(|id| {
+                let day = if id == 10 { 28 } else { 1 + id % 20 };
+                format!(
+                    "{{\"ID\": {}, \"NAME\": \"N{}\", \"UPDATED_AT\": \"2024-02-{:02}T00:00:00Z\"}}\n",
+                    id, id, day
+                )
+            })
+            .collect();
+        std::fs::write(&input_path, lines)?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "falkor://127.0.0.1:1", graph: "g" }}
+                state: {{ backend: none }}
+                mappings:
+                  - type: node
+                    name: people
+                    source: {{ file: "{}" }}
+                    mode: incremental
+                    delta: {{ updated_at_column: UPDATED_AT }}
+                    transforms: [{{ name: lowercase, columns: [NAME] }}]
+                    labels: [Person]
+                    key: {{ column: ID, property: id }}
+                    properties: {{}}
+                "#,
+                input_path.display()
+            ),
+            None,
+        )?;
+        let common = cfg.mappings[0].common();
+
+        let mut shared = SharedFetches::default();
+        let mut rows = shared.open(&cfg, common, None, 1000).await?;
+        let mut chunk_sizes = Vec::new();
+        while let Some(chunk) = rows.next_chunk().await? {
+            // Transforms run on every chunk.
+            assert_eq!(
+                chunk[0].get("NAME"),
+                Some(&serde_json::json!(format!(
+                    "n{}",
+                    chunk_sizes.len() * 1000 + 1
+                )))
+            );
+            chunk_sizes.push(chunk.len());
+        }
+
+        assert_eq!(chunk_sizes, vec![1000, 1000, 500]);
+        assert_eq!(rows.fetched, 2500);
+        assert_eq!(
+            rows.newest.map(|ts| ts.to_rfc3339()).as_deref(),
+            Some("2024-02-28T00:00:00+00:00")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn row_guards_are_checked_before_the_first_chunk() -> Result<()> {
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_guards.jsonl");
+        let lines: String = (1..=2500)
+            .map(|id| format!("{{\"ID\": {}}}\n", id))
+            .collect();
+        std::fs::write(&input_path, lines)?;
+        let guarded_config = |min_rows: usize| {
+            Config::from_str(
+                &format!(
+                    r#"
+                    falkordb: {{ endpoint: "falkor://127.0.0.1:1", graph: "g" }}
+                    mappings:
+                      - type: node
+                        name: people
+                        source: {{ file: "{}" }}
+                        mode: full
+                        min_rows: {}
+                        labels: [Person]
+                        key: {{ column: ID, property: id }}
+                        properties: {{}}
+                    "#,
+                    input_path.display(),
+                    min_rows
+                ),
+                None,
+            )
+        };
+
+        // Too few rows fails before any chunk is handed out for writing.
+        let cfg = guarded_config(3000)?;
+        let mut shared = SharedFetches::default();
+        let mut rows = shared
+            .open(&cfg, cfg.mappings[0].common(), None, 1000)
+            .await?;
+        let err = rows.check_guards(&cfg, None).await.unwrap_err().to_string();
+        assert!(err.contains("fewer than min_rows 3000"), "{}", err);
+        assert_eq!(rows.handed_out, 0);
+
+        // Enough rows: the chunks read ahead are handed out in order, numbered from the
+        // start of the fetch.
+        let cfg = guarded_config(1500)?;
+        let mut rows = shared
+            .open(&cfg, cfg.mappings[0].common(), None, 1000)
+            .await?;
+        rows.check_guards(&cfg, None).await?;
+        let mut starts = Vec::new();
+        while let Some(chunk) = rows.next_chunk().await? {
+            assert_eq!(
+                chunk[0].get("ID"),
+                Some(&serde_json::json!(rows.chunk_start + 1))
+            );
+            starts.push(rows.chunk_start);
+        }
+        assert_eq!(starts, vec![0, 1000, 2000]);
+
+        // A fetch over max_rows_per_mapping with `fail` is rejected up front too.
+        let mut cfg = guarded_config(0)?;
+        cfg.max_rows_per_mapping = Some(2000);
+        let mut rows = shared
+            .open(&cfg, cfg.mappings[0].common(), None, 1000)
+            .await?;
+        let err = rows.check_guards(&cfg, None).await.unwrap_err().to_string();
+        assert!(err.contains("max_rows_per_mapping (2000)"), "{}", err);
+        assert_eq!(rows.handed_out, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn daemon_stops_when_shutdown_is_requested() -> Result<()> {
+        // Nothing listens on port 1, so each run fails fast and the daemon goes back to
+        // waiting for its next tick.
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:1", graph: "g" }
+            state: { backend: none }
+            mappings: []
+            "#,
+            None,
+        )?;
+        let (tx, rx) = watch::channel(false);
+
+        let stop = async {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            tx.send(true)
+        };
+        let (daemon, sent) = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            tokio::join!(
+                run_daemon(&cfg, &RunOptions::default(), 3600, None, rx),
+                stop
+            )
+        })
+        .await?;
+        assert_eq!(daemon?, 1);
+        sent?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn daemon_returns_after_max_runs() -> Result<()> {
+        // Both runs fail to connect, which still counts towards the limit.
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:1", graph: "g" }
+            state: { backend: none }
+            mappings: []
+            "#,
+            None,
+        )?;
+        let (_tx, rx) = watch::channel(false);
+
+        let runs = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            run_daemon(&cfg, &RunOptions::default(), 1, Some(2), rx),
+        )
+        .await??;
+        assert_eq!(runs, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn run_summary_totals_rows_and_collects_failures() -> Result<()> {
+        let mut summary = RunSummary::default();
+        summary.record(MappingSummary {
+            name: "customers".to_string(),
+            rows_fetched: 10,
+            rows_written: 8,
+            rows_deleted: 2,
+            error: None,
+        });
+        assert!(!summary.failed());
+
+        summary.record(MappingSummary {
+            name: "orders".to_string(),
+            rows_fetched: 5,
+            error: Some("missing key column ID".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            (
+                summary.rows_fetched,
+                summary.rows_written,
+                summary.rows_deleted
+            ),
+            (15, 8, 2)
+        );
+        assert_eq!(summary.mappings_failed, 1);
+
+        let json = serde_json::to_value(&summary)?;
+        assert_eq!(json["mappings"][0].get("error"), None);
+        assert_eq!(json["mappings"][1]["error"], "missing key column ID");
+
+        let err = summary.into_result().unwrap_err().to_string();
+        assert_eq!(err, "1 mapping(s) failed: 'orders': missing key column ID");
+        Ok(())
+    }
+
+    #[test]
+    fn min_rows_applies_to_full_loads_only() {
+        let mut common = CommonMappingFields {
+            name: "customers".to_string(),
+            mode: Mode::Full,
+            min_rows: Some(10),
+            ..Default::default()
+        };
+        assert!(check_min_rows(&common, 0, None).is_err());
+        assert!(check_min_rows(&common, 10, None).is_ok());
+
+        common.min_rows_action = GuardAction::Warn;
+        assert!(check_min_rows(&common, 0, None).is_ok());
+
+        common.mode = Mode::Incremental;
+        common.min_rows_action = GuardAction::Fail;
+        // First incremental run has no watermark and is effectively a full load.
+        assert!(check_min_rows(&common, 0, None).is_err());
+        assert!(check_min_rows(&common, 0, Some("2024-01-01T00:00:00+00:00")).is_ok());
+    }
+
+    #[test]
+    fn reconcile_is_refused_after_skipped_rows() {
+        let common = CommonMappingFields {
+            name: "products".to_string(),
+            ..Default::default()
+        };
+        let keys: HashSet<String> = ["1".to_string(), "2".to_string()].into();
+        assert!(reconcile_allowed(&common, &keys, 0));
+        assert!(!reconcile_allowed(&common, &keys, 1));
+    }
+
+    #[test]
+    fn reconcile_of_an_empty_fetch_needs_min_rows() {
+        let mut common = CommonMappingFields {
+            name: "products".to_string(),
+            ..Default::default()
+        };
+        let empty = HashSet::new();
+        assert!(!reconcile_allowed(&common, &empty, 0));
+
+        common.min_rows = Some(0);
+        assert!(reconcile_allowed(&common, &empty, 0));
+    }
+
+    #[test]
+    fn null_endpoint_edges_count_as_present_for_reconcile() -> Result<()> {
+        use serde_json::json;
+
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: g }
+            mappings:
+              - type: edge
+                name: reports_to
+                mode: full
+                source: { file: employees.json }
+                relationship: REPORTS_TO
+                from: { node_mapping: employees, match_on: [{ column: EMP_ID, property: id }] }
+                to:
+                  node_mapping: employees
+                  match_on: [{ column: MANAGER_ID, property: id }]
+                  skip_on_null: true
+                key: { column: REL_ID, property: rel_id }
+                reconcile_deletes: true
+                properties: {}
+            "#,
+            Some(ConfigFormat::Yaml),
+        )?;
+        let EntityMapping::Edge(edge_cfg) = &cfg.mappings[0] else {
+            panic!("expected an edge mapping");
+        };
+        let row = |value: JsonValue| LogicalRow {
+            values: value.as_object().cloned().unwrap(),
+        };
+        let rows = vec![
+            row(json!({ "REL_ID": "r1", "EMP_ID": 1, "MANAGER_ID": 2 })),
+            row(json!({ "REL_ID": "r2", "EMP_ID": 2, "MANAGER_ID": null })),
+        ];
+        let mapped = map_edges(&cfg, edge_cfg, &rows, 0, false)?;
+        assert_eq!((mapped.edges.len(), mapped.skipped.len()), (1, 0));
+
+        let mut keys = HashSet::new();
+        let unreadable = extend_edge_source_keys(
+            &mut keys,
+            edge_cfg,
+            &mapped.edges,
+            &mapped.null_endpoint_rows,
+        );
+        assert_eq!(unreadable, 0);
+        assert_eq!(keys, HashSet::from(["r1".to_string(), "r2".to_string()]));
+
+        // A dropped row without a key can't be accounted for, so reconcile is refused.
+        let keyless = vec![row(json!({ "EMP_ID": 3, "MANAGER_ID": null }))];
+        let unreadable = extend_edge_source_keys(&mut keys, edge_cfg, &[], &keyless);
+        assert_eq!(unreadable, 1);
+        assert!(!reconcile_allowed(&edge_cfg.common, &keys, unreadable));
+        Ok(())
+    }
+
+    #[test]
+    fn deleted_flag_comparison_is_exact_unless_relaxed() -> Result<()> {
+        use serde_json::json;
+
+        let delta = |flag: JsonValue, coerce: bool, ignore_case: bool| -> Result<DeltaSpec> {
+            Ok(serde_json::from_value(serde_json::json!({
+                "updated_at_column": "UPDATED_AT",
+                "deleted_flag_column": "DELETED",
+                "deleted_flag_value": flag,
+                "deleted_flag_coerce": coerce,
+                "deleted_flag_ignore_case": ignore_case
+            }))?)
+        };
+        let deleted = |delta: &DeltaSpec, values: &[JsonValue]| -> Vec<JsonValue> {
+            let rows = values
+                .iter()
+                .map(|v| LogicalRow {
+                    values: [("DELETED".to_string(), v.clone())].into_iter().collect(),
+                })
+                .collect();
+            let (_, deleted) = partition_by_deleted(rows, Some(delta));
+            deleted
+                .iter()
+                .map(|r| r.values["DELETED"].clone())
+                .collect()
+        };
+
+        // "1" vs 1 only matches with coercion, in either direction.
+        let values = [
+            json!("1"),
+            json!(1),
+            json!(1.0),
+            json!(" 1 "),
+            json!("0"),
+            json!(true),
+        ];
+        assert_eq!(
+            deleted(&delta(json!(1), false, false)?, &values),
+            [json!(1)]
+        );
+        assert_eq!(
+            deleted(&delta(json!(1), true, false)?, &values),
+            [json!("1"), json!(1), json!(1.0), json!(" 1 "), json!(true)]
+        );
+        assert_eq!(
+            deleted(&delta(json!("1"), true, false)?, &values),
+            [json!("1"), json!(1), json!(1.0)]
+        );
+        assert_eq!(
+            deleted(
+                &delta(json!(true), true, false)?,
+                &[json!("TRUE"), json!(1), json!(0)]
+            ),
+            [json!("TRUE"), json!(1)]
+        );
+
+        // "Y" vs "y" only matches with ignore_case; coercion doesn't change string comparison.
+        let values = [json!("Y"), json!("y"), json!("N"), json!("yes")];
+        assert_eq!(
+            deleted(&delta(json!("Y"), true, false)?, &values),
+            [json!("Y")]
+        );
+        assert_eq!(
+            deleted(&delta(json!("Y"), false, true)?, &values),
+            [json!("Y"), json!("y")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn initial_full_load_decides_where_a_new_mapping_starts() -> Result<()> {
+        let common = |initial_full_load: bool| -> Result<CommonMappingFields> {
+            Ok(CommonMappingFields {
+                name: "customers".to_string(),
+                mode: Mode::Incremental,
+                delta: Some(serde_json::from_value(serde_json::json!({
+                    "updated_at_column": "UPDATED_AT",
+                    "initial_full_load": initial_full_load
+                }))?),
+                ..Default::default()
+            })
+        };
+
+        // Nothing stored and initial_full_load: a full scan.
+        assert_eq!(starting_watermark(&common(true)?, None), None);
+
+        // Nothing stored and no initial full load: start from now.
+        let before = Utc::now();
+        let start = starting_watermark(&common(false)?, None).expect("starts at now");
+        let start = DateTime::parse_from_rfc3339(&start)?.with_timezone(&Utc);
+        assert!(start >= before && start <= Utc::now());
+
+        // A stored watermark always wins.
+        let stored = "2024-01-01T00:00:00+00:00".to_string();
+        assert_eq!(
+            starting_watermark(&common(false)?, Some(&stored)),
+            Some(stored)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn watermark_override_replaces_the_stored_watermark_in_the_query() -> Result<()> {
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: "g" }
+            mappings:
+              - type: node
+                name: orders
+                source: { table: ORDERS }
+                mode: incremental
+                delta: { updated_at_column: UPDATED_AT }
+                labels: [Order]
+                key: { column: ID, property: id }
+                properties: {}
+            "#,
+            Some(ConfigFormat::Yaml),
+        )?;
+        let stored = "2024-06-01T00:00:00+00:00".to_string();
+        let sql = |opts: &RunOptions| -> Result<String> {
+            let common = cfg.mappings[0].common();
+            let watermark = run_watermark(opts, common, Some(&stored));
+            build_sql(common, watermark.as_deref())
+        };
+
+        let mut opts = RunOptions::default();
+        assert_eq!(
+            sql(&opts)?,
+            "SELECT * FROM ORDERS WHERE UPDATED_AT > '2024-06-01T00:00:00+00:00'"
+        );
+
+        opts.override_watermarks(&cfg, &["orders=2024-01-15".to_string()])?;
+        assert_eq!(
+            sql(&opts)?,
+            "SELECT * FROM ORDERS WHERE UPDATED_AT > '2024-01-15T00:00:00+00:00'"
+        );
+        Ok(())
+    }
+
+    /// Customers, an incremental orders mapping and a knows edge between customers.
+    fn selection_config() -> Result<Config> {
+        Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: "g" }
+            mappings:
+              - type: node
+                name: customers
+                source: { file: "customers.json" }
+                labels: [Customer]
+                key: { column: ID, property: id }
+                properties: {}
+              - type: node
+                name: orders
+                source: { table: ORDERS }
+                mode: incremental
+                delta: { updated_at_column: UPDATED_AT }
+                labels: [Order]
+                key: { column: ID, property: id }
+                properties: {}
+              - type: edge
+                name: knows
+                source: { file: "knows.json" }
+                relationship: KNOWS
+                from: { node_mapping: customers, match_on: [{ column: A, property: id }] }
+                to: { node_mapping: customers, match_on: [{ column: B, property: id }] }
+                properties: {}
+            "#,
+            Some(ConfigFormat::Yaml),
+        )
+    }
+
+    #[test]
+    fn select_mappings_filters_by_name() -> Result<()> {
+        let cfg = selection_config()?;
+        let names = |only: &[&str], skip: &[&str]| -> Result<Vec<String>> {
+            let mut opts = RunOptions::default();
+            let owned = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+            opts.select_mappings(&cfg, &owned(only), &owned(skip))?;
+            Ok(opts
+                .execution_order(&cfg)?
+                .iter()
+                .map(|m| m.common().name.clone())
+                .collect())
+        };
+
+        assert_eq!(names(&["knows"], &[])?, ["knows"]);
+        assert_eq!(names(&[], &["knows"])?, ["customers", "orders"]);
+        assert_eq!(names(&["customers", "orders"], &["orders"])?, ["customers"]);
+        // The config is untouched, so skipped endpoint mappings can still be looked up.
+        assert!(cfg.node_mappings_by_name().contains_key("customers"));
+
+        let err = names(&["customer"], &["nope"]).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown mapping name(s): customer, nope");
+        Ok(())
+    }
+
+    #[test]
+    fn only_selected_mappings_with_a_ttl_are_reaped() -> Result<()> {
+        let cfg = Config::from_str(
+            r#"
+            falkordb: { endpoint: "falkor://127.0.0.1:6379", graph: "g" }
+            mappings:
+              - type: node
+                name: customers
+                source: { table: CUSTOMERS }
+                mode: incremental
+                delta: { updated_at_column: UPDATED_AT, soft_delete: { tombstone_ttl_secs: 60 } }
+                labels: [Customer]
+                key: { column: ID, property: id }
+                properties: {}
+              - type: node
+                name: orders
+                source: { table: ORDERS }
+                mode: incremental
+                delta: { updated_at_column: UPDATED_AT, soft_delete: { tombstone_ttl_secs: 60 } }
+                labels: [Order]
+                key: { column: ID, property: id }
+                properties: {}
+              - type: node
+                name: products
+                source: { table: PRODUCTS }
+                mode: incremental
+                delta: { updated_at_column: UPDATED_AT, soft_delete: {} }
+                labels: [Product]
+                key: { column: ID, property: id }
+                properties: {}
+            "#,
+            Some(ConfigFormat::Yaml),
+        )?;
+        let targets = |opts: &RunOptions| -> Vec<String> {
+            tombstone_reap_targets(&cfg, opts)
+                .iter()
+                .map(|m| m.common.name.clone())
+                .collect()
+        };
+
+        // Products keep their tombstones forever.
+        assert_eq!(targets(&RunOptions::default()), ["customers", "orders"]);
+
+        let mut opts = RunOptions::default();
+        opts.select_mappings(&cfg, &[], &["orders".to_string()])?;
+        assert_eq!(targets(&opts), ["customers"]);
+        Ok(())
+    }
+
+    #[test]
+    fn watermark_overrides_need_a_selected_delta_mapping_and_a_timestamp() -> Result<()> {
+        let cfg = selection_config()?;
+        let mut opts = RunOptions::default();
+        opts.override_watermarks(&cfg, &["orders=2024-01-15".to_string()])?;
+        assert_eq!(
+            opts.watermark_overrides.get("orders").map(String::as_str),
+            Some("2024-01-15T00:00:00+00:00")
+        );
+        opts.override_watermarks(&cfg, &["orders = 2024-01-15T08:30:00+02:00".to_string()])?;
+        assert_eq!(
+            opts.watermark_overrides.get("orders").map(String::as_str),
+            Some("2024-01-15T06:30:00+00:00")
+        );
+
+        let mut opts = RunOptions::default();
+        let err = opts
+            .override_watermarks(
+                &cfg,
+                &[
+                    "orders".to_string(),
+                    "nope=2024-01-01".to_string(),
+                    "customers=2024-01-01".to_string(),
+                    "orders=yesterday".to_string(),
+                ],
+            )
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "Invalid --from-watermark: 'orders' is not NAME=VALUE; unknown mapping 'nope'; \
+             mapping 'customers' has no delta section, so it has no watermark; \
+             watermark 'yesterday' for mapping 'orders' is not a timestamp"
+        );
+        assert!(opts.watermark_overrides.is_empty());
+
+        opts.select_mappings(&cfg, &["customers".to_string()], &[])?;
+        let err = opts
+            .override_watermarks(&cfg, &["orders=2024-01-15".to_string()])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid --from-watermark: mapping 'orders' is not selected"
+        );
+        Ok(())
+    }
+
+    /// Optional end-to-end test that loads a small JSON file into FalkorDB.
+    ///
+    /// Requires FALKORDB_ENDPOINT to be set. If it's missing, the test is skipped
+    /// by returning Ok(()) immediately.
+    #[tokio::test]
+    async fn end_to_end_file_load_into_falkordb() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+        let graph = std::env::var("FALKORDB_GRAPH")
+            .unwrap_or_else(|_| "snowflake_to_falkordb_load_test".to_string());
+
+        // Prepare a tiny in-memory config pointing at a temp JSON file.
+        let tmp_dir = std::env::temp_dir();
+        let input_path = tmp_dir.join("snowflake_to_falkordb_nodes.json");
+        std::fs::write(
+            &input_path,
+            r#"[
+                {"id": 1, "name": "Alice"},
+                {"id": 2, "name": "Bob"}
+            ]"#,
+        )?;
+
+        let source = SourceConfig {
+            file: Some(input_path.to_string_lossy().to_string()),
+            table: None,
+            stream: None,
+            select: None,
+            r#where: None,
+            s3: None,
+            stream_consume: false,
+            params: Default::default(),
+        };
+
+        let common = CommonMappingFields {
+            name: "test_nodes".to_string(),
+            source,
+            mode: Mode::Full,
+            delta: None,
+            ..Default::default()
+        };
 
         let key = NodeKeySpec {
             column: "id".to_string(),
             property: "id".to_string(),
+            ..Default::default()
         };
 
         let mut properties = HashMap::new();
@@ -473,14 +3617,19 @@ mod tests {
             "name".to_string(),
             PropertySpec {
                 column: "name".to_string(),
+                r#type: None,
+                required: true,
+                default: None,
+                as_list: false,
             },
         );
 
         let node_mapping = NodeMappingConfig {
             common,
             labels: vec!["TestNode".to_string()],
-            key,
+            key: key.into(),
             properties,
+            ..Default::default()
         };
 
         let cfg = Config {
@@ -489,6 +3638,7 @@ mod tests {
                 endpoint,
                 graph,
                 max_unwind_batch_size: Some(10),
+                ..Default::default()
             },
             state: Some(StateConfig {
                 backend: StateBackendKind::File,
@@ -498,11 +3648,683 @@ mod tests {
                         .to_string_lossy()
                         .to_string(),
                 ),
+                ..Default::default()
             }),
             mappings: vec![EntityMapping::Node(node_mapping)],
+            ..Default::default()
+        };
+
+        run_once(&cfg, &RunOptions::default())
+            .await?
+            .into_result()?;
+        Ok(())
+    }
+
+    /// Optional end-to-end test: several runs share one injected connection, as daemon ticks
+    /// do.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn runs_reuse_a_provided_connection() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_reuse.json");
+        std::fs::write(&input_path, r#"[{"id": 1}, {"id": 2}]"#)?;
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "{}", graph: snowflake_to_falkordb_reuse_test }}
+                state: {{ backend: none }}
+                mappings:
+                  - type: node
+                    name: items
+                    source: {{ file: "{}" }}
+                    labels: [ReuseProbe]
+                    key: {{ column: id, property: id }}
+                    properties: {{}}
+                "#,
+                endpoint,
+                input_path.display(),
+            ),
+            Some(ConfigFormat::Yaml),
+        )?;
+
+        let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+        for _ in 0..2 {
+            let summary = run_once_with_graph(&mut graph, &cfg, &RunOptions::default()).await?;
+            assert_eq!(summary.rows_written, 2);
+            summary.into_result()?;
+        }
+        assert_eq!(
+            query_count(&mut graph, "MATCH (n:ReuseProbe) RETURN count(n)").await?,
+            2
+        );
+        Ok(())
+    }
+
+    /// Optional end-to-end test: with `continue_on_error`, a mapping whose key column is
+    /// missing fails on its own, and the mapping after it still loads and saves its watermark.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn continue_on_error_runs_remaining_mappings() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+
+        let tmp_dir = std::env::temp_dir();
+        let input_path = tmp_dir.join("snowflake_to_falkordb_continue_on_error.json");
+        std::fs::write(
+            &input_path,
+            r#"[{"id": 1, "updated_at": "2024-05-01T00:00:00Z"}]"#,
+        )?;
+        let state_path = tmp_dir.join("snowflake_to_falkordb_continue_on_error_state.json");
+        let _ = std::fs::remove_file(&state_path);
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "{}", graph: snowflake_to_falkordb_continue_on_error_test }}
+                state: {{ backend: file, file_path: "{}" }}
+                continue_on_error: true
+                mappings:
+                  - type: node
+                    name: broken
+                    source: {{ file: "{}" }}
+                    labels: [Broken]
+                    key: {{ column: MISSING_ID, property: id }}
+                    properties: {{}}
+                  - type: node
+                    name: items
+                    source: {{ file: "{}" }}
+                    mode: incremental
+                    delta: {{ updated_at_column: updated_at }}
+                    labels: [Item]
+                    key: {{ column: id, property: id }}
+                    properties: {{}}
+                "#,
+                endpoint,
+                state_path.display(),
+                input_path.display(),
+                input_path.display(),
+            ),
+            Some(ConfigFormat::Yaml),
+        )?;
+
+        let summary = run_once(&cfg, &RunOptions::default()).await?;
+        assert_eq!(summary.mappings_failed, 1);
+        assert_eq!(summary.mappings.len(), 2);
+        assert!(summary.mappings[0].error.is_some());
+        assert_eq!(summary.mappings[1].error, None);
+        assert_eq!(summary.mappings[1].rows_written, 1);
+
+        let state = std::fs::read_to_string(&state_path)?;
+        assert!(state.contains("items"));
+        assert!(!state.contains("broken"));
+        assert!(summary.into_result().is_err());
+        Ok(())
+    }
+
+    /// Optional end-to-end test: a batch FalkorDB rejects (a null key can't be MERGEd) fails
+    /// the mapping, and the stored watermark stays where it was.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn failed_write_leaves_watermark_unchanged() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+
+        let tmp_dir = std::env::temp_dir();
+        let input_path = tmp_dir.join("snowflake_to_falkordb_failed_write.json");
+        std::fs::write(
+            &input_path,
+            r#"[{"id": null, "updated_at": "2024-05-01T00:00:00Z"}]"#,
+        )?;
+        let state_path = tmp_dir.join("snowflake_to_falkordb_failed_write_state.json");
+        let previous = "2024-01-01T00:00:00+00:00";
+        std::fs::write(
+            &state_path,
+            serde_json::json!({ "mappings": { "items": previous } }).to_string(),
+        )?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb:
+                  endpoint: "{}"
+                  graph: snowflake_to_falkordb_failed_write_test
+                  max_retries: 0
+                state: {{ backend: file, file_path: "{}" }}
+                mappings:
+                  - type: node
+                    name: items
+                    source: {{ file: "{}" }}
+                    mode: incremental
+                    delta: {{ updated_at_column: updated_at }}
+                    labels: [Item]
+                    key: {{ column: id, property: id }}
+                    properties: {{}}
+                "#,
+                endpoint,
+                state_path.display(),
+                input_path.display(),
+            ),
+            Some(ConfigFormat::Yaml),
+        )?;
+
+        let summary = run_once(&cfg, &RunOptions::default()).await?;
+        assert_eq!(summary.mappings_failed, 1);
+
+        let state: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&state_path)?)?;
+        assert_eq!(state["mappings"]["items"], previous);
+        Ok(())
+    }
+
+    /// Optional end-to-end test for a combined mapping: one pass over a denormalized file
+    /// creates both endpoint nodes and the relationship.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn end_to_end_combined_mapping_load() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_purchases.json");
+        std::fs::write(
+            &input_path,
+            r#"[
+                {"customer_id": 1, "customer": "Alice", "sku": "A-1", "product": "Widget", "qty": 2},
+                {"customer_id": 1, "customer": "Alice", "sku": "B-2", "product": "Gadget", "qty": 1},
+                {"customer_id": 2, "customer": "Bob", "sku": "A-1", "product": "Widget", "qty": 5}
+            ]"#,
+        )?;
+
+        let mut cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "{endpoint}", graph: "snowflake_to_falkordb_combined_test" }}
+                mappings:
+                  - type: combined
+                    name: purchases
+                    source: {{ file: "{path}" }}
+                    relationship: PURCHASED
+                    from:
+                      labels: [CombinedCustomer]
+                      key: {{ column: customer_id, property: id }}
+                      properties: {{ name: {{ column: customer }} }}
+                    to:
+                      labels: [CombinedProduct]
+                      key: {{ column: sku, property: sku }}
+                      properties: {{ name: {{ column: product }} }}
+                    properties: {{ qty: {{ column: qty }} }}
+                "#,
+                endpoint = endpoint,
+                path = input_path.to_string_lossy(),
+            ),
+            None,
+        )?;
+        cfg.state = Some(StateConfig {
+            backend: StateBackendKind::None,
+            ..Default::default()
+        });
+
+        let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+        graph
+            .query("MATCH (n) WHERE n:CombinedCustomer OR n:CombinedProduct DETACH DELETE n")
+            .execute()
+            .await?;
+
+        run_once(&cfg, &RunOptions::default())
+            .await?
+            .into_result()?;
+
+        let customers =
+            query_count(&mut graph, "MATCH (c:CombinedCustomer) RETURN count(c)").await?;
+        let products = query_count(&mut graph, "MATCH (p:CombinedProduct) RETURN count(p)").await?;
+        let edges = query_count(
+            &mut graph,
+            "MATCH (:CombinedCustomer)-[r:PURCHASED]->(:CombinedProduct) RETURN count(r)",
+        )
+        .await?;
+        assert_eq!((customers, products, edges), (2, 2, 3));
+        Ok(())
+    }
+
+    /// Optional end-to-end test: a mapping whose source is an empty JSON array succeeds and
+    /// writes nothing.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn empty_source_completes_without_writes() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_empty_run.json");
+        std::fs::write(&input_path, "[]")?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "{}", graph: snowflake_to_falkordb_empty_test }}
+                state: {{ backend: none }}
+                mappings:
+                  - {{ type: node, name: nothing, source: {{ file: "{}" }}, labels: [EmptyItem], key: {{ column: id, property: id }}, properties: {{}} }}
+                "#,
+                endpoint,
+                input_path.display(),
+            ),
+            Some(ConfigFormat::Yaml),
+        )?;
+
+        let summary = run_once(&cfg, &RunOptions::default())
+            .await?
+            .into_result()?;
+        assert_eq!(
+            (
+                summary.rows_fetched,
+                summary.rows_written,
+                summary.rows_deleted
+            ),
+            (0, 0, 0)
+        );
+        Ok(())
+    }
+
+    /// Optional end-to-end test: `sync_mapping` on an empty source returns before sending any
+    /// query, so a graph that doesn't exist yet isn't created.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn empty_source_sync_sends_no_queries() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+        let graph_name = "snowflake_to_falkordb_empty_sync_test";
+
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_empty_sync.json");
+        std::fs::write(&input_path, "[]")?;
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "{}", graph: {} }}
+                state: {{ backend: none }}
+                mappings:
+                  - {{ type: node, name: nothing, source: {{ file: "{}" }}, labels: [EmptyItem], key: {{ column: id, property: id }}, properties: {{}} }}
+                "#,
+                endpoint,
+                graph_name,
+                input_path.display(),
+            ),
+            Some(ConfigFormat::Yaml),
+        )?;
+
+        let redis_url = endpoint.replacen("falkor", "redis", 1);
+        let mut redis = redis::Client::open(redis_url)?
+            .get_multiplexed_async_connection()
+            .await?;
+        redis::cmd("DEL")
+            .arg(graph_name)
+            .query_async::<()>(&mut redis)
+            .await?;
+
+        let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+        let opts = RunOptions::default();
+        let mut ctx = RunContext {
+            cfg: &cfg,
+            opts: &opts,
+            target: RunTarget::Graph(&mut graph),
+            watermarks: HashMap::new(),
+            state: StateConnections::default(),
+            shared: SharedFetches::default(),
+            node_by_name: cfg.node_mappings_by_name(),
+            write: WriteOptions::from_config(&cfg.falkordb),
+            write_pool: Vec::new(),
+        };
+        let mut summary = MappingSummary::default();
+        sync_mapping(&mut ctx, &cfg.mappings[0], &mut summary).await?;
+        assert_eq!((summary.rows_fetched, summary.rows_written), (0, 0));
+
+        let exists: bool = redis::cmd("EXISTS")
+            .arg(graph_name)
+            .query_async(&mut redis)
+            .await?;
+        assert!(!exists, "an empty source must not create the graph");
+        Ok(())
+    }
+
+    /// Optional end-to-end test for `unique_key`: the constraint is created through the driver,
+    /// and sending it again once it exists is not an error.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn unique_key_constraint_is_created_once() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "{}", graph: snowflake_to_falkordb_unique_key_test }}
+                mappings:
+                  - {{ type: node, name: items, source: {{}}, labels: [UniqueItem], key: {{ column: id, property: id }}, properties: {{}}, unique_key: true }}
+                "#,
+                endpoint,
+            ),
+            Some(ConfigFormat::Yaml),
+        )?;
+
+        let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+        graph
+            .query("MERGE (:UniqueItem { id: 1 })")
+            .execute()
+            .await?;
+        let count_constraints = "CALL db.constraints() YIELD label WITH label WHERE label = 'UniqueItem' RETURN count(label)";
+
+        forget_schema_commands(&cfg);
+        ensure_unique_constraints(&mut graph, &cfg).await?;
+        assert_eq!(query_count(&mut graph, count_constraints).await?, 1);
+
+        // The constraint exists now, so the driver call fails with "already exists".
+        forget_schema_commands(&cfg);
+        ensure_unique_constraints(&mut graph, &cfg).await?;
+        assert_eq!(query_count(&mut graph, count_constraints).await?, 1);
+        Ok(())
+    }
+
+    /// Optional end-to-end test for `reconcile_deletes`: a node that is in the graph but no
+    /// longer in the source is deleted, paging through the graph one node at a time.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn reconcile_deletes_removes_nodes_missing_from_source() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+
+        let input_path = std::env::temp_dir().join("snowflake_to_falkordb_reconcile.json");
+        std::fs::write(&input_path, r#"[{"id": 1}, {"id": 2}]"#)?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb:
+                  endpoint: "{}"
+                  graph: snowflake_to_falkordb_reconcile_test
+                  max_unwind_batch_size: 1
+                mappings:
+                  - type: node
+                    name: items
+                    source: {{ file: "{}" }}
+                    labels: [ReconcileItem]
+                    key: {{ column: id, property: id }}
+                    properties: {{}}
+                    reconcile_deletes: true
+                "#,
+                endpoint,
+                input_path.display(),
+            ),
+            Some(ConfigFormat::Yaml),
+        )?;
+
+        let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+        graph
+            .query("MATCH (n:ReconcileItem) DETACH DELETE n")
+            .execute()
+            .await?;
+        graph
+            .query("UNWIND [1, 2, 3] AS id CREATE (:ReconcileItem { id: id })")
+            .execute()
+            .await?;
+
+        let summary = run_once(&cfg, &RunOptions::default())
+            .await?
+            .into_result()?;
+        assert_eq!(summary.rows_deleted, 1);
+
+        let remaining = query_count(&mut graph, "MATCH (n:ReconcileItem) RETURN count(n)").await?;
+        let orphan = query_count(
+            &mut graph,
+            "MATCH (n:ReconcileItem { id: 3 }) RETURN count(n)",
+        )
+        .await?;
+        assert_eq!((remaining, orphan), (2, 0));
+        Ok(())
+    }
+
+    /// Optional end-to-end test for edge `reconcile_deletes`: a relationship whose edge key is
+    /// no longer in the source is deleted, the others are kept.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn reconcile_deletes_removes_edges_missing_from_source() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
+        };
+
+        let tmp_dir = std::env::temp_dir();
+        let nodes_path = tmp_dir.join("snowflake_to_falkordb_reconcile_edge_nodes.json");
+        std::fs::write(&nodes_path, r#"[{"id": 1}, {"id": 2}]"#)?;
+        let edges_path = tmp_dir.join("snowflake_to_falkordb_reconcile_edges.json");
+        std::fs::write(
+            &edges_path,
+            r#"[{"edge_id": "e1", "a": 1, "b": 2}, {"edge_id": "e2", "a": 2, "b": 1}]"#,
+        )?;
+
+        let cfg = Config::from_str(
+            &format!(
+                r#"
+                falkordb:
+                  endpoint: "{}"
+                  graph: snowflake_to_falkordb_reconcile_edge_test
+                  max_unwind_batch_size: 1
+                mappings:
+                  - type: node
+                    name: people
+                    source: {{ file: "{}" }}
+                    labels: [ReconcilePerson]
+                    key: {{ column: id, property: id }}
+                    properties: {{}}
+                  - type: edge
+                    name: knows
+                    source: {{ file: "{}" }}
+                    relationship: KNOWS
+                    from: {{ node_mapping: people, match_on: [{{ column: a, property: id }}] }}
+                    to: {{ node_mapping: people, match_on: [{{ column: b, property: id }}] }}
+                    key: {{ column: edge_id, property: edge_id }}
+                    properties: {{}}
+                    reconcile_deletes: true
+                "#,
+                endpoint,
+                nodes_path.display(),
+                edges_path.display(),
+            ),
+            Some(ConfigFormat::Yaml),
+        )?;
+
+        let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+        graph
+            .query("MATCH (n:ReconcilePerson) DETACH DELETE n")
+            .execute()
+            .await?;
+        graph
+            .query(
+                "CREATE (a:ReconcilePerson { id: 1 }), (b:ReconcilePerson { id: 2 }), \
+                 (a)-[:KNOWS { edge_id: 'e1' }]->(b), (a)-[:KNOWS { edge_id: 'e3' }]->(b)",
+            )
+            .execute()
+            .await?;
+
+        let summary = run_once(&cfg, &RunOptions::default())
+            .await?
+            .into_result()?;
+        assert_eq!(summary.rows_deleted, 1);
+
+        let remaining = query_count(
+            &mut graph,
+            "MATCH (:ReconcilePerson)-[r:KNOWS]->(:ReconcilePerson) RETURN count(r)",
+        )
+        .await?;
+        let removed = query_count(
+            &mut graph,
+            "MATCH ()-[r:KNOWS { edge_id: 'e3' }]->() RETURN count(r)",
+        )
+        .await?;
+        assert_eq!((remaining, removed), (2, 0));
+        Ok(())
+    }
+
+    fn orphan_purge_config(endpoint: &str) -> Result<Config> {
+        Config::from_str(
+            &format!(
+                r#"
+                falkordb: {{ endpoint: "{}", graph: snowflake_to_falkordb_orphan_purge_test }}
+                mappings:
+                  - type: node
+                    name: people
+                    source: {{ file: people.json }}
+                    labels: [OrphanPerson]
+                    key: {{ column: id, property: id }}
+                    properties: {{}}
+                  - type: edge
+                    name: knows
+                    source: {{ file: knows.json }}
+                    relationship: KNOWS
+                    from: {{ node_mapping: people, match_on: [{{ column: a, property: id }}] }}
+                    to: {{ node_mapping: people, match_on: [{{ column: b, property: id }}] }}
+                    properties: {{}}
+                "#,
+                endpoint
+            ),
+            Some(ConfigFormat::Yaml),
+        )
+    }
+
+    #[test]
+    fn edge_purge_only_deletes_endpoints_when_asked() -> Result<()> {
+        let cfg = orphan_purge_config("falkor://127.0.0.1:6379")?;
+        let EntityMapping::Edge(edge_cfg) = &cfg.mappings[1] else {
+            panic!("expected an edge mapping");
+        };
+        let people = vec!["OrphanPerson".to_string()];
+        assert_eq!(
+            edge_purge_cypher(edge_cfg, &people, &people, false),
+            "MATCH (src:OrphanPerson)-[r:KNOWS]->(tgt:OrphanPerson) DELETE r"
+        );
+        assert_eq!(
+            edge_purge_cypher(edge_cfg, &people, &people, true),
+            "MATCH (src:OrphanPerson)-[r:KNOWS]->(tgt:OrphanPerson) DELETE r \
+             WITH src, tgt UNWIND [src, tgt] AS n \
+             WITH DISTINCT n WHERE NOT (n)--() DETACH DELETE n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn combined_purge_follows_the_mapping_direction() -> Result<()> {
+        let purge = |direction: &str| -> Result<String> {
+            let cfg = Config::from_str(
+                &format!(
+                    r#"
+                    falkordb: {{ endpoint: "falkor://127.0.0.1:6379", graph: g }}
+                    mappings:
+                      - type: combined
+                        name: purchases
+                        source: {{ file: purchases.json }}
+                        relationship: PURCHASED
+                        direction: {}
+                        from: {{ labels: [Customer], key: {{ column: customer_id, property: id }}, properties: {{}} }}
+                        to: {{ labels: [Product], key: {{ column: sku, property: sku }}, properties: {{}} }}
+                        properties: {{}}
+                    "#,
+                    direction
+                ),
+                Some(ConfigFormat::Yaml),
+            )?;
+            let EntityMapping::Combined(combined_cfg) = &cfg.mappings[0] else {
+                panic!("expected a combined mapping");
+            };
+            Ok(combined_purge_cypher(combined_cfg))
+        };
+        assert_eq!(
+            purge("out")?,
+            "MATCH (src:Customer)-[r:PURCHASED]->(tgt:Product) DELETE r"
+        );
+        assert_eq!(
+            purge("in")?,
+            "MATCH (src:Customer)<-[r:PURCHASED]-(tgt:Product) DELETE r"
+        );
+        assert_eq!(
+            purge("both")?,
+            "MATCH (src:Customer)-[r:PURCHASED]-(tgt:Product) DELETE r"
+        );
+        Ok(())
+    }
+
+    /// Optional check that purging an edge mapping with orphan removal deletes only the
+    /// endpoints left without relationships.
+    ///
+    /// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+    #[tokio::test]
+    async fn edge_purge_removes_only_orphaned_endpoints() -> Result<()> {
+        let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return Ok(()),
         };
+        let cfg = orphan_purge_config(&endpoint)?;
+        let mut graph = connect_falkordb_async(&cfg.falkordb).await?;
+        graph
+            .query("MATCH (n:OrphanPerson) DETACH DELETE n")
+            .execute()
+            .await?;
+        // 1 and 2 only have KNOWS edges, 3 also follows 4, and 5 was never connected.
+        graph
+            .query(
+                "CREATE (a:OrphanPerson { id: 1 }), (b:OrphanPerson { id: 2 }), \
+                 (c:OrphanPerson { id: 3 }), (d:OrphanPerson { id: 4 }), (:OrphanPerson { id: 5 }), \
+                 (a)-[:KNOWS]->(b), (b)-[:KNOWS]->(c), (c)-[:FOLLOWS]->(d)",
+            )
+            .execute()
+            .await?;
+
+        purge_mapping(
+            &mut graph,
+            &cfg.mappings[1],
+            &cfg.node_mappings_by_name(),
+            true,
+            None,
+        )
+        .await?;
+
+        let remaining = query_count(&mut graph, "MATCH (n:OrphanPerson) RETURN count(n)").await?;
+        let kept = query_count(
+            &mut graph,
+            "MATCH (n:OrphanPerson) WHERE n.id IN [3, 4, 5] RETURN count(n)",
+        )
+        .await?;
+        let follows = query_count(&mut graph, "MATCH ()-[r:FOLLOWS]->() RETURN count(r)").await?;
+        graph
+            .query("MATCH (n:OrphanPerson) DETACH DELETE n")
+            .execute()
+            .await?;
 
-        run_once(&cfg, false, &[]).await?;
+        // 1 and 2 were orphaned; 3 keeps its FOLLOWS, and 5 was never a KNOWS endpoint.
+        assert_eq!((remaining, kept, follows), (3, 3, 1));
         Ok(())
     }
 }
@@ -0,0 +1,178 @@
+use anyhow::{anyhow, Context, Result};
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::Client;
+
+use crate::config::S3Source;
+use crate::source::{parse_rows_from_bytes, LogicalRow};
+
+/// The objects named by a `source.s3` spec, read one at a time in key order. Each object is
+/// downloaded in full and parsed like a local `source.file`, so memory holds one object's
+/// rows at a time rather than the whole prefix.
+pub struct S3Objects {
+    client: Client,
+    bucket: String,
+    keys: std::vec::IntoIter<String>,
+}
+
+impl S3Objects {
+    /// List the objects to read. A prefix that matches nothing is an error.
+    pub async fn open(spec: &S3Source) -> Result<Self> {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = &spec.region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        let client = Client::new(&loader.load().await);
+
+        let keys = match (&spec.key, &spec.prefix) {
+            (Some(key), None) => vec![key.clone()],
+            (None, Some(prefix)) => list_keys(&client, &spec.bucket, prefix).await?,
+            _ => {
+                return Err(anyhow!(
+                    "source.s3 for bucket '{}' needs exactly one of key or prefix",
+                    spec.bucket
+                ))
+            }
+        };
+        Ok(S3Objects {
+            client,
+            bucket: spec.bucket.clone(),
+            keys: keys.into_iter(),
+        })
+    }
+
+    /// Download and parse the next object, or `None` after the last one.
+    pub async fn next_rows(&mut self) -> Result<Option<Vec<LogicalRow>>> {
+        let Some(key) = self.keys.next() else {
+            return Ok(None);
+        };
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .send()
+            .await
+            .map_err(|e| s3_error(e, &self.bucket, &key))?;
+        let bytes = object
+            .body
+            .collect()
+            .await
+            .with_context(|| format!("Failed to download s3://{}/{}", self.bucket, key))?
+            .into_bytes();
+        let name = format!("s3://{}/{}", self.bucket, key);
+        let rows = parse_rows_from_bytes(&name, bytes.to_vec())?;
+        tracing::info!(object = %name, rows = rows.len(), "Read S3 object");
+        Ok(Some(rows))
+    }
+}
+
+/// Keys under `prefix`, sorted, skipping zero-byte "folder" markers.
+async fn list_keys(client: &Client, bucket: &str, prefix: &str) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut pages = client
+        .list_objects_v2()
+        .bucket(bucket)
+        .prefix(prefix)
+        .into_paginator()
+        .send();
+    while let Some(page) = pages.next().await {
+        let page = page.map_err(|e| s3_error(e, bucket, prefix))?;
+        keys.extend(
+            page.contents()
+                .iter()
+                .filter_map(|object| object.key())
+                .filter(|key| !key.ends_with('/'))
+                .map(str::to_string),
+        );
+    }
+
+    if keys.is_empty() {
+        return Err(anyhow!(
+            "source.s3 prefix 's3://{}/{}' matched no objects",
+            bucket,
+            prefix
+        ));
+    }
+    keys.sort();
+    Ok(keys)
+}
+
+fn s3_error<E, R>(err: SdkError<E, R>, bucket: &str, target: &str) -> anyhow::Error
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+    R: std::fmt::Debug,
+{
+    s3_error_message(
+        err.code(),
+        bucket,
+        target,
+        &DisplayErrorContext(&err).to_string(),
+    )
+}
+
+/// Turn the S3 error codes users are most likely to hit into actionable messages.
+fn s3_error_message(code: Option<&str>, bucket: &str, target: &str, detail: &str) -> anyhow::Error {
+    match code {
+        Some("NoSuchBucket") => anyhow!("S3 bucket '{}' does not exist", bucket),
+        Some("NoSuchKey") => anyhow!("S3 object s3://{}/{} does not exist", bucket, target),
+        Some("AccessDenied") => anyhow!(
+            "Access denied to s3://{}/{}; check the AWS credentials and the bucket policy",
+            bucket,
+            target
+        ),
+        _ => anyhow!(
+            "S3 request for s3://{}/{} failed: {}",
+            bucket,
+            target,
+            detail
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_s3_errors_get_clear_messages() {
+        let msg = |code| s3_error_message(code, "exports", "daily/", "detail").to_string();
+        assert_eq!(
+            msg(Some("NoSuchBucket")),
+            "S3 bucket 'exports' does not exist"
+        );
+        assert!(msg(Some("AccessDenied")).starts_with("Access denied to s3://exports/daily/"));
+        assert_eq!(
+            msg(Some("SlowDown")),
+            "S3 request for s3://exports/daily/ failed: detail"
+        );
+    }
+
+    /// Optional S3 round trip.
+    ///
+    /// Uses environment variables:
+    /// - S3_TEST_BUCKET: a bucket the AWS credentials can read
+    /// - S3_TEST_PREFIX: a prefix holding at least one JSON or Parquet export
+    /// - S3_TEST_REGION (optional)
+    ///
+    /// If S3_TEST_BUCKET or S3_TEST_PREFIX is not set, the test is a no-op.
+    #[tokio::test]
+    async fn s3_prefix_smoke_test() -> Result<()> {
+        let (Ok(bucket), Ok(prefix)) = (
+            std::env::var("S3_TEST_BUCKET"),
+            std::env::var("S3_TEST_PREFIX"),
+        ) else {
+            return Ok(());
+        };
+        let spec = S3Source {
+            bucket,
+            key: None,
+            prefix: Some(prefix),
+            region: std::env::var("S3_TEST_REGION").ok(),
+        };
+        let mut objects = S3Objects::open(&spec).await?;
+        let rows = objects.next_rows().await?.unwrap_or_default();
+        assert!(!rows.is_empty());
+        Ok(())
+    }
+}
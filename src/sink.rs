@@ -2,12 +2,14 @@ use anyhow::Result;
 use falkordb::{FalkorClientBuilder, FalkorConnectionInfo, SyncGraph};
 use serde_json::{Map as JsonMap, Value as JsonValue};
 
-use crate::config::{FalkorConfig, NodeMappingConfig};
-use crate::cypher::json_value_to_cypher_literal;
+use crate::config::{FalkorConfig, NodeKey, NodeMappingConfig};
+use crate::cypher::rows_param;
+use crate::sink_async::endpoint_with_db_index;
 
 /// Establish a blocking FalkorDB client and select the configured graph.
 pub fn connect_falkordb_sync(cfg: &FalkorConfig) -> Result<SyncGraph> {
-    let conn_info: FalkorConnectionInfo = cfg.endpoint.as_str().try_into()?;
+    let endpoint = endpoint_with_db_index(&cfg.endpoint, cfg.db_index)?;
+    let conn_info: FalkorConnectionInfo = endpoint.as_str().try_into()?;
 
     let client = FalkorClientBuilder::new()
         .with_connection_info(conn_info)
@@ -21,6 +23,63 @@ pub fn connect_falkordb_sync(cfg: &FalkorConfig) -> Result<SyncGraph> {
 pub struct MappedNode {
     pub key: JsonValue,
     pub props: JsonMap<String, JsonValue>,
+    /// Label resolved from `labels_from_column`, added to the mapping's fixed labels.
+    pub label: Option<String>,
+}
+
+/// Label clause nodes are merged and matched on: the mapping's fixed labels. A dynamic label
+/// is left out so a row whose label changed still finds its node.
+pub fn node_label_clause(mapping: &NodeMappingConfig) -> String {
+    mapping.labels.join(":")
+}
+
+/// Clauses appended to a node MERGE's `SET` that give `n` its dynamic label and remove the
+/// mapping's other dynamic labels, e.g. `, n:Customer REMOVE n:Partner:Supplier`. Empty when
+/// the mapping has no `labels_from_column`.
+pub fn dynamic_label_update(mapping: &NodeMappingConfig, label: Option<&str>) -> String {
+    let Some(label) = label else {
+        return String::new();
+    };
+    let others: Vec<&str> = mapping
+        .label_groups()
+        .into_iter()
+        .flatten()
+        .filter(|l| *l != label)
+        .collect();
+    if others.is_empty() {
+        format!(", n:{}", label)
+    } else {
+        format!(", n:{} REMOVE n:{}", label, others.join(":"))
+    }
+}
+
+/// Property map matching a node by its key in `row.key`, e.g. `{ id: row.key }` or, for a
+/// composite key, `{ region: row.key.region, id: row.key.id }`.
+pub fn node_key_predicate(mapping: &NodeMappingConfig) -> String {
+    match &mapping.key {
+        NodeKey::Single(spec) => format!("{{ {}: row.key }}", spec.property),
+        NodeKey::Composite(specs) => {
+            let parts: Vec<String> = specs
+                .iter()
+                .map(|s| format!("{p}: row.key.{p}", p = s.property))
+                .collect();
+            format!("{{ {} }}", parts.join(", "))
+        }
+    }
+}
+
+/// Split a batch into groups sharing the same dynamic label, in first-seen order. Each group
+/// needs its own query because labels can't be parameterised.
+pub fn group_by_label(batch: &[MappedNode]) -> Vec<(Option<&str>, Vec<&MappedNode>)> {
+    let mut groups: Vec<(Option<&str>, Vec<&MappedNode>)> = Vec::new();
+    for node in batch {
+        let label = node.label.as_deref();
+        match groups.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, nodes)) => nodes.push(node),
+            None => groups.push((label, vec![node])),
+        }
+    }
+    groups
 }
 
 /// Build and execute a parameterised UNWIND+MERGE statement for a batch of nodes.
@@ -29,6 +88,9 @@ pub struct MappedNode {
 ///   UNWIND $rows AS row
 ///   MERGE (n:Customer { keyProp: row.key })
 ///   SET n += row.props
+///
+/// Composite keys match on every key property (`{ k1: row.key.k1, k2: row.key.k2 }`). A
+/// dynamic label is set after the MERGE, see [`dynamic_label_update`].
 pub fn write_nodes_batch_sync(
     graph: &mut SyncGraph,
     mapping: &NodeMappingConfig,
@@ -38,31 +100,31 @@ pub fn write_nodes_batch_sync(
         return Ok(());
     }
 
-    let label_clause = mapping.labels.join(":");
-
-    let rows_value = JsonValue::Array(
-        batch
-            .iter()
-            .map(|n| {
-                let mut obj = JsonMap::new();
-                obj.insert("key".to_string(), n.key.clone());
-                obj.insert("props".to_string(), JsonValue::Object(n.props.clone()));
-                JsonValue::Object(obj)
-            })
-            .collect(),
-    );
-
-    let rows_literal = json_value_to_cypher_literal(&rows_value);
-    let cypher = format!(
-        "UNWIND {rows} AS row \
-         MERGE (n:{labels} {{ {key_prop}: row.key }}) \
-         SET n += row.props",
-        rows = rows_literal,
-        labels = label_clause,
-        key_prop = mapping.key.property,
-    );
-
-    let _res = graph.query(&cypher).execute()?;
+    for (label, nodes) in group_by_label(batch) {
+        let rows_value = JsonValue::Array(
+            nodes
+                .iter()
+                .map(|n| {
+                    let mut obj = JsonMap::new();
+                    obj.insert("key".to_string(), n.key.clone());
+                    obj.insert("props".to_string(), JsonValue::Object(n.props.clone()));
+                    JsonValue::Object(obj)
+                })
+                .collect(),
+        );
+
+        let params = rows_param(&rows_value);
+        let cypher = format!(
+            "UNWIND $rows AS row \
+             MERGE (n:{labels} {key}) \
+             SET n += row.props{dynamic}",
+            labels = node_label_clause(mapping),
+            key = node_key_predicate(mapping),
+            dynamic = dynamic_label_update(mapping, label),
+        );
+
+        let _res = graph.query(&cypher).with_params(&params).execute()?;
+    }
 
     Ok(())
 }
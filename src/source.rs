@@ -1,12 +1,26 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::future::Future;
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use parquet::file::reader::{ChunkReader, FileReader, SerializedFileReader};
+use parquet::record::Field;
 use serde_json::{Map as JsonMap, Value as JsonValue};
 use snowflake_connector_rs::{
-    SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig, SnowflakeRow,
+    SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig, SnowflakeRow, SnowflakeSession,
 };
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::{JoinHandle, JoinSet};
 
-use crate::config::{CommonMappingFields, Config, SnowflakeConfig};
+use crate::config::{
+    is_glob_pattern, CommonMappingFields, Config, MaxRowsAction, Pagination, SnowflakeConfig,
+    PARAMS_UNSUPPORTED,
+};
+use crate::s3::S3Objects;
 
 /// Logical row abstraction used by the mapping layer.
 #[derive(Debug, Clone)]
@@ -26,42 +40,314 @@ pub async fn fetch_rows_for_mapping(
     common: &CommonMappingFields,
     watermark: Option<&str>,
 ) -> Result<Vec<LogicalRow>> {
+    let mut stream = open_row_stream(cfg, common, watermark, cfg.batch_size(common)).await?;
+    let mut rows = Vec::new();
+    while let Some(chunk) = stream.next_chunk().await? {
+        rows.extend(chunk);
+    }
+    Ok(rows)
+}
+
+/// Chunks handed from a source reader task to the [`RowStream`] consuming them.
+type ChunkSender = mpsc::Sender<Result<Vec<LogicalRow>>>;
+
+/// A mapping's rows, read a chunk at a time: one Snowflake page, one JSONL run of
+/// `chunk_rows` lines, or `chunk_rows` rows of a source that has to be read whole (JSON
+/// arrays, Parquet, each S3 object, single queries). The reader runs as its own task and waits
+/// while the previous chunk is still being mapped and written, so a run holds at most one
+/// chunk plus the one being read.
+pub struct RowStream {
+    chunks: mpsc::Receiver<Result<Vec<LogicalRow>>>,
+    reader: JoinHandle<()>,
+    limit: Option<RowLimit>,
+    fetched: usize,
+    finished: bool,
+}
+
+impl RowStream {
+    /// Stream rows that are already in memory, `chunk_rows` at a time.
+    pub fn from_rows(rows: Vec<LogicalRow>, chunk_rows: usize) -> Self {
+        RowStream::spawn(None, move |tx| async move {
+            send_in_chunks(&tx, rows, chunk_rows).await;
+            Ok(())
+        })
+    }
+
+    /// Start `read` as the reader task. An error it returns is handed to the consumer as the
+    /// stream's last item.
+    fn spawn<F, Fut>(limit: Option<RowLimit>, read: F) -> Self
+    where
+        F: FnOnce(ChunkSender) -> Fut,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let (tx, chunks) = mpsc::channel(1);
+        let read = read(tx.clone());
+        let reader = tokio::spawn(async move {
+            if let Err(e) = read.await {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+        RowStream {
+            chunks,
+            reader,
+            limit,
+            fetched: 0,
+            finished: false,
+        }
+    }
+
+    /// Like [`RowStream::spawn`], for readers that block on file I/O or decoding: `read` runs
+    /// on tokio's blocking pool, so it doesn't hold up the runtime's worker threads.
+    fn spawn_blocking<F>(limit: Option<RowLimit>, read: F) -> Self
+    where
+        F: FnOnce(&ChunkSender) -> Result<()> + Send + 'static,
+    {
+        let (tx, chunks) = mpsc::channel(1);
+        let reader = tokio::task::spawn_blocking(move || {
+            if let Err(e) = read(&tx) {
+                let _ = tx.blocking_send(Err(e));
+            }
+        });
+        RowStream {
+            chunks,
+            reader,
+            limit,
+            fetched: 0,
+            finished: false,
+        }
+    }
+
+    /// The next chunk, or `None` once the source is exhausted. Chunks are never empty.
+    pub async fn next_chunk(&mut self) -> Result<Option<Vec<LogicalRow>>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let Some(chunk) = self.chunks.recv().await else {
+            self.finished = true;
+            return Ok(None);
+        };
+        let mut chunk = chunk?;
+        self.fetched += chunk.len();
+
+        if let Some(limit) = &self.limit {
+            if self.fetched > limit.max {
+                // Stop the reader at its next send; rows it already read are dropped.
+                self.finished = true;
+                self.chunks.close();
+                limit.exceeded()?;
+                chunk.truncate(chunk.len() - (self.fetched - limit.max));
+                self.fetched = limit.max;
+                if chunk.is_empty() {
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(chunk))
+    }
+
+    /// Rows handed out so far.
+    pub fn fetched(&self) -> usize {
+        self.fetched
+    }
+}
+
+impl Drop for RowStream {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// Open a stream over a mapping's rows, from either a file, S3 or Snowflake. Errors opening
+/// the Snowflake session are returned here; errors while reading come from
+/// [`RowStream::next_chunk`].
+pub async fn open_row_stream(
+    cfg: &Config,
+    common: &CommonMappingFields,
+    watermark: Option<&str>,
+    chunk_rows: usize,
+) -> Result<RowStream> {
+    let limit = RowLimit::for_mapping(cfg, common);
+
     if let Some(file) = &common.source.file {
-        return load_rows_from_file(file);
+        let file = file.clone();
+        return Ok(RowStream::spawn_blocking(limit, move |tx| {
+            send_file_rows(&file, chunk_rows, tx)
+        }));
+    }
+
+    if let Some(s3) = &common.source.s3 {
+        let s3 = s3.clone();
+        return Ok(RowStream::spawn(limit, move |tx| async move {
+            let mut objects = S3Objects::open(&s3).await?;
+            while let Some(rows) = objects.next_rows().await? {
+                if !send_in_chunks(&tx, rows, chunk_rows).await {
+                    break;
+                }
+            }
+            Ok(())
+        }));
     }
 
     if let Some(sf_cfg) = &cfg.snowflake {
-        return fetch_rows_from_snowflake(sf_cfg, common, watermark).await;
+        let row_cap = limit.as_ref().map(RowLimit::row_cap);
+        let read = snowflake_read(sf_cfg, common);
+        let mut sql = build_sql(common, watermark)?;
+        if let (SnowflakeRead::Single, Some(cap)) = (&read, row_cap) {
+            let order_column = common.delta.as_ref().map(|d| d.updated_at_column.as_str());
+            sql = capped_sql(&sql, order_column, cap);
+        }
+        let conn = SnowflakeConnection::open(sf_cfg, common).await?;
+        return Ok(RowStream::spawn(limit, move |tx| async move {
+            send_snowflake_rows(conn, sql, read, row_cap, chunk_rows, &tx).await
+        }));
     }
 
     Err(anyhow!(
-        "No supported source configured for mapping {} (need `file` or Snowflake)",
+        "No supported source configured for mapping {} (need `file`, `s3` or Snowflake)",
         common.name
     ))
 }
 
-async fn fetch_rows_from_snowflake(
-    sf_cfg: &SnowflakeConfig,
-    common: &CommonMappingFields,
-    watermark: Option<&str>,
-) -> Result<Vec<LogicalRow>> {
-    let base_sql = build_sql(common, watermark)?;
+/// Hand `rows` to the stream `chunk_rows` at a time. Returns false once the stream has stopped
+/// reading.
+async fn send_in_chunks(tx: &ChunkSender, rows: Vec<LogicalRow>, chunk_rows: usize) -> bool {
+    let mut rows = rows.into_iter().peekable();
+    while rows.peek().is_some() {
+        let chunk: Vec<LogicalRow> = rows.by_ref().take(chunk_rows.max(1)).collect();
+        if tx.send(Ok(chunk)).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// `send_in_chunks` for readers on the blocking pool.
+fn send_in_chunks_blocking(tx: &ChunkSender, rows: Vec<LogicalRow>, chunk_rows: usize) -> bool {
+    let mut rows = rows.into_iter().peekable();
+    while rows.peek().is_some() {
+        let chunk: Vec<LogicalRow> = rows.by_ref().take(chunk_rows.max(1)).collect();
+        if tx.blocking_send(Ok(chunk)).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// `max_rows_per_mapping` as applied to one mapping's fetch. The stream stops reading once
+/// it has seen more than `max` rows, and Snowflake reads that can't stop early ask for at
+/// most [`row_cap`](Self::row_cap) rows, one more than the limit, so an oversized result is
+/// detected without materializing all of it.
+#[derive(Debug, Clone)]
+struct RowLimit {
+    mapping: String,
+    max: usize,
+    action: MaxRowsAction,
+}
+
+impl RowLimit {
+    fn for_mapping(cfg: &Config, common: &CommonMappingFields) -> Option<Self> {
+        cfg.max_rows_per_mapping.map(|max| RowLimit {
+            mapping: common.name.clone(),
+            max,
+            action: cfg.max_rows_action,
+        })
+    }
+
+    /// Rows a reader needs to tell that the limit was exceeded.
+    fn row_cap(&self) -> usize {
+        self.max.saturating_add(1)
+    }
 
-    let auth = if let Some(key_path) = &sf_cfg.private_key_path {
-        // Key-pair auth: use private_key_path as encrypted PEM and password as key passphrase.
-        let pem = std::fs::read_to_string(key_path)
-            .with_context(|| format!("Failed to read Snowflake private key from {}", key_path))?;
-        let pass_bytes = sf_cfg.password.as_deref().unwrap_or("").as_bytes().to_vec();
-        SnowflakeAuthMethod::KeyPair {
-            encrypted_pem: pem,
-            password: pass_bytes,
+    /// React to the fetch going over the limit: an error with `fail`, a warning with
+    /// `truncate`, after which the caller drops the rows past `max`.
+    fn exceeded(&self) -> Result<()> {
+        match self.action {
+            MaxRowsAction::Fail => Err(anyhow!(
+                "Mapping '{}' fetched more than max_rows_per_mapping ({}) rows; stopped reading",
+                self.mapping,
+                self.max
+            )),
+            MaxRowsAction::Truncate => {
+                tracing::warn!(
+                    mapping = %self.mapping,
+                    max_rows = self.max,
+                    "Fetch exceeded max_rows_per_mapping; TRUNCATING to the first {} rows, the rest are not loaded",
+                    self.max
+                );
+                Ok(())
+            }
         }
+    }
+}
+
+/// Which Snowflake authentication a config selects, with the setting it uses.
+#[derive(Debug, PartialEq, Eq)]
+enum AuthChoice<'a> {
+    KeyPair(&'a str),
+    Password(&'a str),
+    OAuth(&'a str),
+}
+
+/// Pick the authentication method: key pair when `private_key_path` is set, then password,
+/// then `oauth_token`.
+fn choose_auth(sf_cfg: &SnowflakeConfig) -> Result<AuthChoice<'_>> {
+    if let Some(key_path) = &sf_cfg.private_key_path {
+        Ok(AuthChoice::KeyPair(key_path))
     } else if let Some(pw) = &sf_cfg.password {
-        SnowflakeAuthMethod::Password(pw.clone())
+        Ok(AuthChoice::Password(pw))
+    } else if let Some(token) = &sf_cfg.oauth_token {
+        Ok(AuthChoice::OAuth(token))
     } else {
-        return Err(anyhow!(
-            "SnowflakeConfig.password or private_key_path must be set for authentication"
-        ));
+        Err(anyhow!(
+            "SnowflakeConfig.password, private_key_path or oauth_token must be set for authentication"
+        ))
+    }
+}
+
+/// Passphrase for the encrypted private key: `private_key_passphrase`, or `password` for
+/// configs written before the dedicated field existed. Empty when neither is set.
+fn key_passphrase(sf_cfg: &SnowflakeConfig) -> &str {
+    sf_cfg
+        .private_key_passphrase
+        .as_deref()
+        .or(sf_cfg.password.as_deref())
+        .unwrap_or("")
+}
+
+/// Query timeout for a mapping's fetch: the mapping's `query_timeout_ms` if set, otherwise
+/// the global `snowflake.query_timeout_ms`. Each fetch opens its own session, so the value
+/// only affects this mapping.
+fn effective_query_timeout(
+    sf_cfg: &SnowflakeConfig,
+    common: &CommonMappingFields,
+) -> Option<std::time::Duration> {
+    common
+        .query_timeout_ms
+        .or(sf_cfg.query_timeout_ms)
+        .map(std::time::Duration::from_millis)
+}
+
+/// Open a Snowflake session for one mapping's fetch.
+async fn open_snowflake_session(
+    sf_cfg: &SnowflakeConfig,
+    common: &CommonMappingFields,
+) -> Result<(SnowflakeClient, SnowflakeSession)> {
+    let auth = match choose_auth(sf_cfg)? {
+        AuthChoice::KeyPair(key_path) => {
+            // Key-pair auth: use private_key_path as encrypted PEM with its passphrase.
+            let pem = std::fs::read_to_string(key_path).with_context(|| {
+                format!("Failed to read Snowflake private key from {}", key_path)
+            })?;
+            let pass_bytes = key_passphrase(sf_cfg).as_bytes().to_vec();
+            SnowflakeAuthMethod::KeyPair {
+                encrypted_pem: pem,
+                password: pass_bytes,
+            }
+        }
+        AuthChoice::Password(pw) => SnowflakeAuthMethod::Password(pw.to_string()),
+        AuthChoice::OAuth(token) => SnowflakeAuthMethod::Oauth {
+            token: token.to_string(),
+        },
     };
 
     let config = SnowflakeClientConfig {
@@ -70,93 +356,640 @@ async fn fetch_rows_from_snowflake(
         database: Some(sf_cfg.database.clone()),
         schema: Some(sf_cfg.schema.clone()),
         role: sf_cfg.role.clone(),
-        timeout: sf_cfg
-            .query_timeout_ms
-            .map(|ms| std::time::Duration::from_millis(ms)),
+        timeout: effective_query_timeout(sf_cfg, common),
     };
 
     // Create client and session
     let client = SnowflakeClient::new(&sf_cfg.user, auth, config)?;
     let session = client.create_session().await?;
+    Ok((client, session))
+}
+
+/// How many times one query may log in again after the session expired before giving up.
+const SNOWFLAKE_REAUTH_ATTEMPTS: u32 = 2;
+
+/// Whether a connector error means the session or its login token expired, so a new session
+/// would succeed where this one failed: Snowflake error codes 390111 (session gone), 390112
+/// (session expired) and 390114 (token expired), or their messages.
+fn is_session_expired(err: &anyhow::Error) -> bool {
+    let message = format!("{:#}", err).to_ascii_lowercase();
+    ["390111", "390112", "390114"]
+        .iter()
+        .any(|code| message.contains(code))
+        || [
+            "session has expired",
+            "session no longer exists",
+            "token has expired",
+            "authentication token expired",
+        ]
+        .iter()
+        .any(|text| message.contains(text))
+}
+
+/// Whether to log in again and retry a query that failed with `err`, after `reauths` earlier
+/// re-authentications for the same query.
+fn should_reauthenticate(err: &anyhow::Error, reauths: u32) -> bool {
+    reauths < SNOWFLAKE_REAUTH_ATTEMPTS && is_session_expired(err)
+}
+
+/// A Snowflake session that logs in again when a query fails because the session expired,
+/// so long paged fetches and daemon runs can outlive the session token.
+struct SnowflakeConnection {
+    client: Arc<SnowflakeClient>,
+    session: tokio::sync::Mutex<SnowflakeSession>,
+    mapping: String,
+}
 
-    // If fetch_batch_size is set and we have a delta spec (incremental), use
-    // simple LIMIT/OFFSET paging ordered by the updated_at column. This keeps
-    // individual result sets bounded while preserving the same semantics as a
-    // single large query.
-    if let (Some(batch_size), Some(delta)) = (sf_cfg.fetch_batch_size, &common.delta) {
-        if batch_size > 0 && common.source.select.is_none() {
-            return fetch_rows_from_snowflake_paged(
-                &session,
+impl SnowflakeConnection {
+    async fn open(sf_cfg: &SnowflakeConfig, common: &CommonMappingFields) -> Result<Self> {
+        let (client, session) = open_snowflake_session(sf_cfg, common).await?;
+        Ok(Self {
+            client: Arc::new(client),
+            session: tokio::sync::Mutex::new(session),
+            mapping: common.name.clone(),
+        })
+    }
+
+    /// Another session of the same client, e.g. for a concurrent page worker.
+    async fn another_session(&self) -> Result<Self> {
+        Ok(Self {
+            client: self.client.clone(),
+            session: tokio::sync::Mutex::new(self.client.create_session().await?),
+            mapping: self.mapping.clone(),
+        })
+    }
+
+    /// Run `sql`, replacing the session and retrying (at most [`SNOWFLAKE_REAUTH_ATTEMPTS`]
+    /// times) when it fails because the session expired.
+    async fn query(&self, sql: &str) -> Result<Vec<SnowflakeRow>> {
+        let mut session = self.session.lock().await;
+        let mut reauths = 0;
+        loop {
+            let err = match session.query(sql).await {
+                Ok(rows) => return Ok(rows),
+                Err(e) => anyhow::Error::from(e),
+            };
+            if !should_reauthenticate(&err, reauths) {
+                return Err(err);
+            }
+            reauths += 1;
+            tracing::warn!(
+                mapping = %self.mapping,
+                attempt = reauths,
+                error = %format!("{:#}", err),
+                "Snowflake session expired; logging in again and retrying the query",
+            );
+            *session = self
+                .client
+                .create_session()
+                .await
+                .context("Failed to re-authenticate expired Snowflake session")?;
+        }
+    }
+}
+
+/// How a Snowflake fetch reads its rows, decided before the reader task starts.
+#[derive(Debug, PartialEq, Eq)]
+enum SnowflakeRead {
+    /// LIMIT/OFFSET pages ordered by the updated_at column (and the tiebreaker, if there is
+    /// one), one query per page.
+    Offset { order_by: String, batch_size: usize },
+    /// Keyset pages after the previous page's last `(order_column, tiebreaker)` pair.
+    Keyset {
+        order_column: String,
+        tiebreaker: String,
+        batch_size: usize,
+    },
+    /// LIMIT/OFFSET pages fetched over several sessions at once, ordered by the updated_at
+    /// and tiebreaker columns so every row has a fixed page.
+    Concurrent {
+        order_by: String,
+        batch_size: usize,
+        concurrency: usize,
+    },
+    /// A single query returning all rows.
+    Single,
+}
+
+/// Pick the read for a mapping. Paging applies when `fetch_batch_size` is set and the mapping
+/// has a delta spec (incremental) without a custom `select`: pages are ordered by the
+/// updated_at column, which keeps individual result sets bounded while preserving the same
+/// semantics as a single large query.
+fn snowflake_read(sf_cfg: &SnowflakeConfig, common: &CommonMappingFields) -> SnowflakeRead {
+    let (Some(batch_size), Some(delta)) = (sf_cfg.fetch_batch_size, &common.delta) else {
+        return SnowflakeRead::Single;
+    };
+    if batch_size == 0 || common.source.select.is_some() {
+        return SnowflakeRead::Single;
+    }
+    let order_column = delta.updated_at_column.clone();
+
+    if sf_cfg.pagination == Pagination::Keyset {
+        match &delta.tiebreaker_column {
+            Some(tiebreaker) => {
+                return SnowflakeRead::Keyset {
+                    order_column,
+                    tiebreaker: tiebreaker.clone(),
+                    batch_size,
+                };
+            }
+            None => tracing::warn!(
+                mapping = %common.name,
+                "Keyset pagination needs delta.tiebreaker_column; falling back to LIMIT/OFFSET paging"
+            ),
+        }
+    }
+    let order_by = match &delta.tiebreaker_column {
+        Some(tiebreaker) => format!("{}, {}", order_column, tiebreaker),
+        None => order_column,
+    };
+    let concurrency = sf_cfg.fetch_concurrency.unwrap_or(1);
+    if concurrency > 1 {
+        if delta.tiebreaker_column.is_some() {
+            return SnowflakeRead::Concurrent {
+                order_by,
+                batch_size,
+                concurrency,
+            };
+        }
+        tracing::warn!(
+            mapping = %common.name,
+            "fetch_concurrency needs delta.tiebreaker_column for a deterministic page order; fetching pages one at a time"
+        );
+    }
+    SnowflakeRead::Offset {
+        order_by,
+        batch_size,
+    }
+}
+
+/// Reader task of a Snowflake fetch: send `base_sql`'s rows to the stream as `read` says.
+/// Paged reads send each page as it arrives, in page order; a single-query read gets its whole
+/// result (at most `row_cap` rows) from the connector at once and sends it `chunk_rows` at a
+/// time.
+async fn send_snowflake_rows(
+    conn: SnowflakeConnection,
+    base_sql: String,
+    read: SnowflakeRead,
+    row_cap: Option<usize>,
+    chunk_rows: usize,
+    tx: &ChunkSender,
+) -> Result<()> {
+    let rows = match read {
+        SnowflakeRead::Offset {
+            order_by,
+            batch_size,
+        } => {
+            return send_snowflake_pages(&conn, &base_sql, &order_by, batch_size, tx).await;
+        }
+        SnowflakeRead::Keyset {
+            order_column,
+            tiebreaker,
+            batch_size,
+        } => {
+            return send_snowflake_keyset_pages(
+                &conn,
+                &base_sql,
+                &order_column,
+                &tiebreaker,
+                batch_size,
+                tx,
+            )
+            .await;
+        }
+        SnowflakeRead::Concurrent {
+            order_by,
+            batch_size,
+            concurrency,
+        } => {
+            return send_snowflake_pages_concurrent(
+                conn,
                 &base_sql,
-                &delta.updated_at_column,
+                &order_by,
                 batch_size,
+                concurrency,
+                row_cap,
+                tx,
             )
             .await;
         }
+        SnowflakeRead::Single => conn
+            .query(&base_sql)
+            .await?
+            .into_iter()
+            .map(snowflake_row_to_logical_row)
+            .collect::<Result<Vec<_>>>()?,
+    };
+
+    send_in_chunks(tx, rows, chunk_rows).await;
+    Ok(())
+}
+
+/// Temporary table that consuming DML writes to. It never receives rows; it only exists so
+/// an `INSERT ... SELECT FROM <stream>` can advance the stream offset.
+const STREAM_BOOKMARK_TABLE: &str = "SNOWFLAKE_TO_FALKORDB_STREAM_BOOKMARK";
+
+/// Session-scoped bookmark table, created before the transaction opens because DDL would
+/// commit it.
+fn stream_bookmark_ddl() -> String {
+    format!(
+        "CREATE TEMPORARY TABLE IF NOT EXISTS {} (CONSUMED_AT TIMESTAMP_LTZ)",
+        STREAM_BOOKMARK_TABLE
+    )
+}
+
+/// DML that reads `stream` without inserting anything, which advances the stream offset when
+/// its transaction commits.
+fn stream_consume_sql(stream: &str) -> String {
+    format!(
+        "INSERT INTO {} SELECT CURRENT_TIMESTAMP() FROM {} WHERE 1 = 0",
+        STREAM_BOOKMARK_TABLE, stream
+    )
+}
+
+/// An open Snowflake transaction that has read a stream (`source.stream_consume`). Inside
+/// the transaction the stream keeps returning the change set that was loaded, so committing
+/// after the graph write advances the offset past exactly those rows. Dropping it without
+/// [`commit`](Self::commit) ends the session and Snowflake rolls the transaction back,
+/// leaving the offset where it was.
+pub struct StreamConsumer {
+    _client: SnowflakeClient,
+    session: SnowflakeSession,
+    stream: String,
+}
+
+impl StreamConsumer {
+    /// Consume the stream and commit, advancing its offset.
+    pub async fn commit(self) -> Result<()> {
+        self.session
+            .query(stream_consume_sql(&self.stream).as_str())
+            .await
+            .with_context(|| format!("Failed to consume Snowflake stream {}", self.stream))?;
+        self.session
+            .query("COMMIT")
+            .await
+            .with_context(|| format!("Failed to commit consumption of stream {}", self.stream))?;
+        Ok(())
     }
+}
 
-    // Fallback: single query returning all rows.
-    let rows = session.query(base_sql.as_str()).await?;
+/// Read a `stream_consume` mapping's stream inside an explicit transaction and return its
+/// rows with the still-open transaction. Rows are read with a single query; paging does not
+/// apply.
+pub async fn fetch_stream_rows_for_consume(
+    cfg: &Config,
+    common: &CommonMappingFields,
+) -> Result<(Vec<LogicalRow>, StreamConsumer)> {
+    let sf_cfg = cfg.snowflake.as_ref().ok_or_else(|| {
+        anyhow!(
+            "Mapping '{}' sets source.stream_consume but no snowflake section is configured",
+            common.name
+        )
+    })?;
+    let stream = common.source.stream.clone().ok_or_else(|| {
+        anyhow!(
+            "Mapping '{}' sets source.stream_consume without source.stream",
+            common.name
+        )
+    })?;
 
-    let logical_rows = rows
+    let limit = RowLimit::for_mapping(cfg, common);
+    let mut sql = build_sql(common, None)?;
+    if let Some(limit) = &limit {
+        sql = capped_sql(&sql, None, limit.row_cap());
+    }
+    let (client, session) = open_snowflake_session(sf_cfg, common).await?;
+    session.query(stream_bookmark_ddl().as_str()).await?;
+    session.query("BEGIN").await?;
+    let mut rows = session
+        .query(sql.as_str())
+        .await?
         .into_iter()
         .map(snowflake_row_to_logical_row)
         .collect::<Result<Vec<_>>>()?;
+    if let Some(limit) = limit {
+        if rows.len() > limit.max {
+            limit.exceeded()?;
+            rows.truncate(limit.max);
+        }
+    }
 
-    Ok(logical_rows)
+    Ok((
+        rows,
+        StreamConsumer {
+            _client: client,
+            session,
+            stream,
+        },
+    ))
 }
 
-/// Fetch rows using LIMIT/OFFSET paging.
+/// Send rows to the stream a LIMIT/OFFSET page at a time, stopping once the stream stops
+/// reading.
 ///
 /// This is only used when:
 /// - `SnowflakeConfig.fetch_batch_size` is set to a positive value, and
 /// - `CommonMappingFields.delta` is present (so we have an updated_at column), and
 /// - `source.select` is not used (we control the generated SQL).
-async fn fetch_rows_from_snowflake_paged(
-    session: &snowflake_connector_rs::SnowflakeSession,
+async fn send_snowflake_pages(
+    conn: &SnowflakeConnection,
     base_sql: &str,
-    order_column: &str,
+    order_by: &str,
     batch_size: usize,
-) -> Result<Vec<LogicalRow>> {
-    let mut out = Vec::new();
+    tx: &ChunkSender,
+) -> Result<()> {
     let mut offset: usize = 0;
 
     loop {
-        let paged_sql = format!(
-            "{base} ORDER BY {col} LIMIT {limit} OFFSET {offset}",
-            base = base_sql,
-            col = order_column,
-            limit = batch_size,
-            offset = offset,
-        );
+        let paged_sql = paged_sql(base_sql, order_by, batch_size, offset);
 
-        // SnowflakeSession::query accepts &str / String (Into<QueryRequest>), so
-        // pass a string slice here.
-        let rows: Vec<SnowflakeRow> = session.query(paged_sql.as_str()).await?;
+        let rows: Vec<SnowflakeRow> = conn.query(&paged_sql).await?;
         let chunk_len = rows.len();
         if chunk_len == 0 {
             break;
         }
 
-        for row in rows {
-            out.push(snowflake_row_to_logical_row(row)?);
+        let page = rows
+            .into_iter()
+            .map(snowflake_row_to_logical_row)
+            .collect::<Result<Vec<_>>>()?;
+        if tx.send(Ok(page)).await.is_err() || chunk_len < batch_size {
+            break;
         }
 
-        if chunk_len < batch_size {
+        offset += chunk_len;
+    }
+
+    Ok(())
+}
+
+/// `base_sql` limited to its first `limit` rows, ordered by `order_column` when there is one
+/// so an incremental run keeps the oldest changes and the next run resumes after them.
+fn capped_sql(base_sql: &str, order_column: Option<&str>, limit: usize) -> String {
+    match order_column {
+        Some(col) => format!(
+            "SELECT * FROM ({}) ORDER BY {} LIMIT {}",
+            base_sql, col, limit
+        ),
+        None => format!("SELECT * FROM ({}) LIMIT {}", base_sql, limit),
+    }
+}
+
+/// One LIMIT/OFFSET page of `base_sql`, ordered by `order_by`.
+fn paged_sql(base_sql: &str, order_by: &str, limit: usize, offset: usize) -> String {
+    format!(
+        "{base} ORDER BY {col} LIMIT {limit} OFFSET {offset}",
+        base = base_sql,
+        col = order_by,
+        limit = limit,
+        offset = offset,
+    )
+}
+
+/// The `(updated_at, tiebreaker)` values of the last row of a keyset page.
+type KeysetCursor = (JsonValue, JsonValue);
+
+/// Send rows to the stream using keyset paging: each page asks for the rows strictly after
+/// the previous page's last `(order_column, tiebreaker)` pair, so later pages cost the same as
+/// the first and rows sharing a timestamp are neither skipped nor repeated.
+async fn send_snowflake_keyset_pages(
+    conn: &SnowflakeConnection,
+    base_sql: &str,
+    order_column: &str,
+    tiebreaker: &str,
+    batch_size: usize,
+    tx: &ChunkSender,
+) -> Result<()> {
+    fetch_keyset_pages(order_column, tiebreaker, batch_size, tx, |after| {
+        let sql = keyset_sql(
+            base_sql,
+            order_column,
+            tiebreaker,
+            after.as_ref(),
+            batch_size,
+        );
+        async move {
+            let rows: Vec<SnowflakeRow> = conn.query(&sql).await?;
+            rows.into_iter().map(snowflake_row_to_logical_row).collect()
+        }
+    })
+    .await
+}
+
+/// Drive keyset paging: request pages from `fetch_page` and send them to the stream until one
+/// comes back short or the stream stops reading.
+async fn fetch_keyset_pages<F, Fut>(
+    order_column: &str,
+    tiebreaker: &str,
+    batch_size: usize,
+    tx: &ChunkSender,
+    mut fetch_page: F,
+) -> Result<()>
+where
+    F: FnMut(Option<KeysetCursor>) -> Fut,
+    Fut: Future<Output = Result<Vec<LogicalRow>>>,
+{
+    let mut after = None;
+
+    loop {
+        let page = fetch_page(after.take()).await?;
+        let page_len = page.len();
+        if let Some(last) = page.last() {
+            let value = |column: &str| {
+                last.get(column).cloned().ok_or_else(|| {
+                    anyhow!("Keyset paging: row is missing ordering column '{}'", column)
+                })
+            };
+            after = Some((value(order_column)?, value(tiebreaker)?));
+        }
+        if page_len == 0 || tx.send(Ok(page)).await.is_err() || page_len < batch_size {
             break;
         }
+    }
 
-        offset += chunk_len;
+    Ok(())
+}
+
+/// One keyset page of `base_sql`: the first `limit` rows after `after` in
+/// `(order_column, tiebreaker)` order.
+fn keyset_sql(
+    base_sql: &str,
+    order_column: &str,
+    tiebreaker: &str,
+    after: Option<&KeysetCursor>,
+    limit: usize,
+) -> String {
+    let seek = match after {
+        Some((ts, key)) => format!(
+            " WHERE {col} > {ts} OR ({col} = {ts} AND {tb} > {key})",
+            col = order_column,
+            tb = tiebreaker,
+            ts = sql_literal(ts),
+            key = sql_literal(key),
+        ),
+        None => String::new(),
+    };
+    format!(
+        "SELECT * FROM ({base}){seek} ORDER BY {col}, {tb} LIMIT {limit}",
+        base = base_sql,
+        seek = seek,
+        col = order_column,
+        tb = tiebreaker,
+        limit = limit,
+    )
+}
+
+/// Render a row value as a SQL literal for a keyset predicate. Snowflake treats a backslash
+/// in a string literal as an escape, so backslashes are doubled as well as quotes; otherwise
+/// a trailing `\` would escape the closing quote.
+fn sql_literal(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => format!("'{}'", s.replace('\\', "\\\\").replace('\'', "''")),
+        JsonValue::Null => "NULL".to_string(),
+        other => other.to_string(),
     }
+}
 
-    Ok(out)
+/// Send LIMIT/OFFSET pages to the stream, fetched over up to `concurrency` Snowflake sessions
+/// at once. See [`fetch_pages_concurrently`]; with a `row_cap`, only the pages covering the
+/// first `row_cap` rows are fetched.
+async fn send_snowflake_pages_concurrent(
+    conn: SnowflakeConnection,
+    base_sql: &str,
+    order_by: &str,
+    batch_size: usize,
+    concurrency: usize,
+    row_cap: Option<usize>,
+    tx: &ChunkSender,
+) -> Result<()> {
+    tracing::info!(
+        workers = concurrency,
+        "Fetching Snowflake pages concurrently"
+    );
+
+    let mut sessions = Vec::with_capacity(concurrency);
+    while sessions.len() + 1 < concurrency {
+        sessions.push(conn.another_session().await?);
+    }
+    sessions.push(conn);
+
+    let base_sql = Arc::new(base_sql.to_string());
+    let order_by = Arc::new(order_by.to_string());
+    let fetchers: Vec<_> = sessions
+        .into_iter()
+        .map(|session| {
+            let session = Arc::new(session);
+            let base_sql = base_sql.clone();
+            let order_by = order_by.clone();
+            move |page: usize| {
+                let session = session.clone();
+                let sql = paged_sql(&base_sql, &order_by, batch_size, page * batch_size);
+                async move {
+                    session
+                        .query(&sql)
+                        .await?
+                        .into_iter()
+                        .map(snowflake_row_to_logical_row)
+                        .collect::<Result<Vec<_>>>()
+                }
+            }
+        })
+        .collect();
+    let max_pages = row_cap.map_or(usize::MAX, |cap| cap.div_ceil(batch_size));
+    fetch_pages_concurrently(fetchers, batch_size, max_pages, tx).await
 }
 
-fn build_sql(common: &CommonMappingFields, watermark: Option<&str>) -> Result<String> {
-    // If the user provided a full SELECT, we respect it as-is. We don't attempt to inject
-    // incremental predicates automatically here.
+/// Drive concurrent paging: each fetcher is a worker that claims the next page number and
+/// fetches it, and pages are sent to the stream in page order as soon as the next one is in,
+/// so callers see the same order as a sequential fetch without waiting for the last page.
+/// Workers claim at most `2 * workers` pages past the last one sent, which bounds the pages
+/// held at once. The fetch ends at the first short page or after `max_pages`; there is no
+/// up-front row count, so rows added during the fetch are read rather than cut off.
+async fn fetch_pages_concurrently<F, Fut>(
+    fetchers: Vec<F>,
+    batch_size: usize,
+    max_pages: usize,
+    tx: &ChunkSender,
+) -> Result<()>
+where
+    F: FnMut(usize) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Vec<LogicalRow>>> + Send + 'static,
+{
+    let claims = Arc::new(Semaphore::new(fetchers.len().saturating_mul(2).max(1)));
+    let next_page = Arc::new(AtomicUsize::new(0));
+    // Lowered to one past the first short page, so workers stop claiming pages after it.
+    let end_page = Arc::new(AtomicUsize::new(max_pages));
+    let (page_tx, mut page_rx) = mpsc::channel(fetchers.len().max(1));
+    let mut tasks = JoinSet::new();
+
+    for mut fetch_page in fetchers {
+        let (claims, next_page, end_page) = (claims.clone(), next_page.clone(), end_page.clone());
+        let page_tx = page_tx.clone();
+        tasks.spawn(async move {
+            loop {
+                let Ok(permit) = claims.clone().acquire_owned().await else {
+                    break;
+                };
+                let page = next_page.fetch_add(1, Ordering::Relaxed);
+                if page >= end_page.load(Ordering::Relaxed) {
+                    break;
+                }
+                let rows = fetch_page(page).await;
+                if let Ok(rows) = &rows {
+                    if rows.len() < batch_size {
+                        end_page.fetch_min(page + 1, Ordering::Relaxed);
+                    }
+                }
+                // The permit comes back when the page is sent on to the stream.
+                permit.forget();
+                let failed = rows.is_err();
+                if page_tx.send((page, rows)).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+    }
+    drop(page_tx);
+
+    let mut waiting: BTreeMap<usize, Vec<LogicalRow>> = BTreeMap::new();
+    let mut next = 0;
+    while let Some((page, rows)) = page_rx.recv().await {
+        waiting.insert(page, rows?);
+        while let Some(rows) = waiting.remove(&next) {
+            next += 1;
+            claims.add_permits(1);
+            let last = rows.len() < batch_size || next >= max_pages;
+            if !rows.is_empty() && tx.send(Ok(rows)).await.is_err() {
+                return Ok(());
+            }
+            if last {
+                return Ok(());
+            }
+        }
+    }
+
+    // Every worker stopped before the last page came in, so one of them must have died.
+    while let Some(res) = tasks.join_next().await {
+        res.context("Snowflake page fetch task failed")?;
+    }
+    Err(anyhow!("Snowflake page fetch stopped before page {}", next))
+}
+
+/// Placeholder in `source.select` replaced with the current watermark as a quoted literal.
+const WATERMARK_PLACEHOLDER: &str = "{{watermark}}";
+
+/// Watermark substituted for the placeholder before a mapping has one.
+const INITIAL_WATERMARK: &str = "1970-01-01";
+
+pub(crate) fn build_sql(common: &CommonMappingFields, watermark: Option<&str>) -> Result<String> {
+    if !common.source.params.is_empty() {
+        return Err(anyhow!("mapping '{}': {}", common.name, PARAMS_UNSUPPORTED));
+    }
+
+    // If the user provided a full SELECT, we respect it as-is apart from the watermark
+    // placeholder. We don't attempt to inject incremental predicates automatically.
     if let Some(sel) = &common.source.select {
-        return Ok(sel.clone());
+        return Ok(substitute_watermark(sel, watermark));
     }
 
     // If a Snowflake stream is configured, generate a simple SELECT against the
@@ -200,36 +1033,271 @@ fn build_sql(common: &CommonMappingFields, watermark: Option<&str>) -> Result<St
     ))
 }
 
+/// Replace every `{{watermark}}` in a custom SELECT with the watermark as a single-quoted SQL
+/// string, or `'1970-01-01'` when there is none yet. A SELECT without the token is unchanged.
+fn substitute_watermark(select: &str, watermark: Option<&str>) -> String {
+    if !select.contains(WATERMARK_PLACEHOLDER) {
+        return select.to_string();
+    }
+    let literal = sql_literal(&JsonValue::String(
+        watermark.unwrap_or(INITIAL_WATERMARK).to_string(),
+    ));
+    select.replace(WATERMARK_PLACEHOLDER, &literal)
+}
+
 fn snowflake_row_to_logical_row(row: SnowflakeRow) -> Result<LogicalRow> {
     let mut values = JsonMap::new();
 
-    for name in row.column_names() {
-        let name = name.to_string();
-        // Try to decode as JSON; fall back to string.
-        let json_val: JsonValue = match row.get::<JsonValue>(&name) {
-            Ok(v) => v,
-            Err(_) => {
-                let s: String = row.get(&name)?;
-                JsonValue::String(s)
-            }
-        };
-        values.insert(name, json_val);
+    for column in row.column_types() {
+        let name = column.name().to_string();
+        let raw: Option<String> = row.get(&name)?;
+        let column_type = column.column_type();
+        let value = snowflake_cell_to_json(
+            column_type.snowflake_type(),
+            column_type.scale(),
+            raw.as_deref(),
+        );
+        values.insert(name, value);
     }
 
     Ok(LogicalRow { values })
 }
 
+/// Convert one Snowflake cell, as the connector hands it over (the raw result text), to JSON
+/// using the column's Snowflake type: integers, floats and booleans stay numbers and booleans,
+/// timestamps become RFC3339 strings (matching Parquet sources, so watermarks compare), dates
+/// `YYYY-MM-DD`, times `HH:MM:SS[.f]` and VARIANT/OBJECT/ARRAY parsed JSON. Anything that
+/// doesn't parse as its column type, or has a type not listed here, is kept as a string.
+fn snowflake_cell_to_json(
+    snowflake_type: &str,
+    scale: Option<i64>,
+    raw: Option<&str>,
+) -> JsonValue {
+    let Some(raw) = raw else {
+        return JsonValue::Null;
+    };
+
+    let typed = match snowflake_type.to_ascii_lowercase().as_str() {
+        // NUMBER(p, 0) beyond i64 stays a string rather than losing digits as a float.
+        "fixed" if scale.unwrap_or(0) == 0 => raw.parse::<i64>().ok().map(JsonValue::from),
+        "fixed" | "real" => raw
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(JsonValue::Number),
+        "boolean" => match raw.to_ascii_lowercase().as_str() {
+            "1" | "true" => Some(JsonValue::Bool(true)),
+            "0" | "false" => Some(JsonValue::Bool(false)),
+            _ => None,
+        },
+        "timestamp_ntz" | "timestamp_ltz" | "timestamp_tz" => snowflake_epoch_parts(raw)
+            .and_then(|(secs, nanos)| DateTime::from_timestamp(secs, nanos))
+            .map(|dt| JsonValue::String(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true))),
+        "date" => raw
+            .parse::<i64>()
+            .ok()
+            .and_then(|days| {
+                NaiveDate::from_ymd_opt(1970, 1, 1)?
+                    .checked_add_signed(chrono::Duration::days(days))
+            })
+            .map(|d| JsonValue::String(d.format("%Y-%m-%d").to_string())),
+        "time" => snowflake_epoch_parts(raw)
+            .and_then(|(secs, nanos)| {
+                NaiveTime::from_num_seconds_from_midnight_opt(u32::try_from(secs).ok()?, nanos)
+            })
+            .map(|t| JsonValue::String(t.to_string())),
+        "variant" | "object" | "array" => serde_json::from_str(raw).ok(),
+        _ => None,
+    };
+
+    typed.unwrap_or_else(|| JsonValue::String(raw.to_string()))
+}
+
+/// Split Snowflake's `seconds[.fraction]` wire form for timestamps and times into whole
+/// seconds and nanoseconds. TIMESTAMP_TZ appends the offset after a space; the seconds are
+/// already UTC, so it is ignored.
+fn snowflake_epoch_parts(raw: &str) -> Option<(i64, u32)> {
+    let value = raw.split_whitespace().next()?;
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let secs: i64 = whole.parse().ok()?;
+    let nanos: u32 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<9}", fraction).parse().ok()?
+    };
+    // "-1.25" is 1.25s before the epoch: whole second -2 plus 0.75s.
+    if whole.starts_with('-') && nanos > 0 {
+        Some((secs - 1, 1_000_000_000 - nanos))
+    } else {
+        Some((secs, nanos))
+    }
+}
+
+/// Clean up common export quirks before parsing: a leading UTF-8 BOM, surrounding
+/// whitespace/newlines, and a trailing comma after the last array element (`[{..},]`).
+fn sanitise_file_contents(contents: &str) -> String {
+    let trimmed = contents.trim_start_matches('\u{feff}').trim();
+
+    if let Some(body) = trimmed.strip_suffix(']') {
+        if let Some(without_comma) = body.trim_end().strip_suffix(',') {
+            return format!("{}]", without_comma);
+        }
+    }
+
+    trimmed.to_string()
+}
+
+/// Files named by `source.file`: a single path, or a glob pattern's matches in sorted order.
+/// A pattern that matches nothing is an error.
+fn source_file_paths(pattern: &str) -> Result<Vec<String>> {
+    if !is_glob_pattern(pattern) {
+        return Ok(vec![pattern.to_string()]);
+    }
+
+    let mut paths = glob::glob(pattern)
+        .with_context(|| format!("Invalid glob pattern '{}' in source.file", pattern))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to expand source.file pattern '{}'", pattern))?;
+    if paths.is_empty() {
+        return Err(anyhow!(
+            "source.file pattern '{}' matched no files",
+            pattern
+        ));
+    }
+    paths.sort();
+
+    Ok(paths
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Reader task of a file source: send the rows of every file `source.file` names, in order.
+/// JSONL files are read line by line, `chunk_rows` lines per chunk; other formats are parsed
+/// one whole file at a time and then sent in chunks.
+fn send_file_rows(pattern: &str, chunk_rows: usize, tx: &ChunkSender) -> Result<()> {
+    for path in source_file_paths(pattern)? {
+        let more = if is_jsonl(&path) {
+            send_jsonl_rows(&path, chunk_rows, tx)?
+        } else {
+            send_in_chunks_blocking(tx, load_rows_from_file(&path)?, chunk_rows)
+        };
+        if !more {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Send a JSONL file's rows without reading the whole file first. Returns false once the
+/// stream has stopped reading.
+fn send_jsonl_rows(path: &str, chunk_rows: usize, tx: &ChunkSender) -> Result<bool> {
+    let file =
+        fs::File::open(path).with_context(|| format!("Failed to open input file {}", path))?;
+    let chunk_rows = chunk_rows.max(1);
+    let mut chunk = Vec::with_capacity(chunk_rows);
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read input file {}", path))?;
+        if let Some(row) = parse_jsonl_line(&line, idx, path)? {
+            chunk.push(row);
+        }
+        if chunk.len() == chunk_rows && tx.blocking_send(Ok(std::mem::take(&mut chunk))).is_err() {
+            return Ok(false);
+        }
+    }
+    if chunk.is_empty() {
+        return Ok(true);
+    }
+    Ok(tx.blocking_send(Ok(chunk)).is_ok())
+}
+
+fn is_parquet(name: &str) -> bool {
+    name.to_lowercase().ends_with(".parquet")
+}
+
+/// Newline-delimited JSON: one row object per line.
+fn is_jsonl(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(".jsonl") || name.ends_with(".ndjson")
+}
+
 fn load_rows_from_file(path: &str) -> Result<Vec<LogicalRow>> {
-    let contents =
+    if is_parquet(path) {
+        return load_rows_from_parquet(path);
+    }
+
+    let raw =
         fs::read_to_string(path).with_context(|| format!("Failed to read input file {}", path))?;
+    if is_jsonl(path) {
+        return parse_jsonl_rows(&raw, path);
+    }
+    parse_json_rows(&raw, path)
+}
+
+/// Parse the contents of an object or file named `name` (used for the format and in error
+/// messages): Parquet when the name ends in `.parquet`, one object per line when it ends in
+/// `.jsonl` or `.ndjson`, otherwise a JSON array of rows.
+pub fn parse_rows_from_bytes(name: &str, bytes: Vec<u8>) -> Result<Vec<LogicalRow>> {
+    if is_parquet(name) {
+        return parse_parquet_rows(bytes::Bytes::from(bytes), name);
+    }
+    let raw = String::from_utf8(bytes).with_context(|| format!("{} is not valid UTF-8", name))?;
+    if is_jsonl(name) {
+        return parse_jsonl_rows(&raw, name);
+    }
+    parse_json_rows(&raw, name)
+}
+
+/// Parse newline-delimited row objects read from `origin`. Blank lines are skipped; errors
+/// name the 1-based line.
+fn parse_jsonl_rows(raw: &str, origin: &str) -> Result<Vec<LogicalRow>> {
+    let mut rows = Vec::new();
+    for (idx, line) in raw.lines().enumerate() {
+        rows.extend(parse_jsonl_line(line, idx, origin)?);
+    }
+    Ok(rows)
+}
+
+/// Parse the line at 0-based `idx` of a JSONL input: `None` for a blank line. A UTF-8 BOM
+/// at the start of the first line is ignored.
+fn parse_jsonl_line(line: &str, idx: usize, origin: &str) -> Result<Option<LogicalRow>> {
+    let line = if idx == 0 {
+        line.trim_start_matches('\u{feff}')
+    } else {
+        line
+    };
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let value: JsonValue = serde_json::from_str(line)
+        .with_context(|| format!("Failed to parse JSON at line {} of {}", idx + 1, origin))?;
+    match value {
+        JsonValue::Object(map) => Ok(Some(LogicalRow { values: map })),
+        _ => Err(anyhow!(
+            "Line {} of {} is not a JSON object",
+            idx + 1,
+            origin
+        )),
+    }
+}
+
+/// Parse a JSON array of row objects read from `origin`.
+fn parse_json_rows(raw: &str, origin: &str) -> Result<Vec<LogicalRow>> {
+    let contents = sanitise_file_contents(raw);
 
     let value: JsonValue = serde_json::from_str(&contents)
-        .with_context(|| format!("Failed to parse JSON input from {}", path))?;
+        .with_context(|| format!("Failed to parse JSON input from {}", origin))?;
 
-    let arr = value
-        .as_array()
-        .cloned()
-        .ok_or_else(|| anyhow!("Expected top-level JSON array in input file {}", path))?;
+    let JsonValue::Array(arr) = value else {
+        return Err(anyhow!(
+            "Expected top-level JSON array in input file {}",
+            origin
+        ));
+    };
 
     let mut rows = Vec::with_capacity(arr.len());
     for (idx, v) in arr.into_iter().enumerate() {
@@ -239,7 +1307,7 @@ fn load_rows_from_file(path: &str) -> Result<Vec<LogicalRow>> {
                 return Err(anyhow!(
                     "Row at index {} in {} is not a JSON object",
                     idx,
-                    path
+                    origin
                 ));
             }
         }
@@ -248,62 +1316,774 @@ fn load_rows_from_file(path: &str) -> Result<Vec<LogicalRow>> {
     Ok(rows)
 }
 
+/// Read a Parquet file into rows, one `LogicalRow` per record.
+fn load_rows_from_parquet(path: &str) -> Result<Vec<LogicalRow>> {
+    let file =
+        fs::File::open(path).with_context(|| format!("Failed to open input file {}", path))?;
+    parse_parquet_rows(file, path)
+}
+
+/// Decode Parquet data read from `path` into rows.
+fn parse_parquet_rows<R: ChunkReader + 'static>(data: R, path: &str) -> Result<Vec<LogicalRow>> {
+    let reader = SerializedFileReader::new(data)
+        .with_context(|| format!("Failed to read Parquet input from {}", path))?;
+
+    let mut rows = Vec::with_capacity(reader.metadata().file_metadata().num_rows().max(0) as usize);
+    for record in reader
+        .get_row_iter(None)
+        .with_context(|| format!("Failed to read Parquet records from {}", path))?
+    {
+        let record =
+            record.with_context(|| format!("Failed to decode Parquet record in {}", path))?;
+        let values = record
+            .get_column_iter()
+            .map(|(name, field)| (name.clone(), parquet_field_to_json(field)))
+            .collect();
+        rows.push(LogicalRow { values });
+    }
+
+    Ok(rows)
+}
+
+/// Convert a Parquet field to JSON. Timestamps become RFC3339 strings so watermark parsing
+/// works the same as for Snowflake and JSON sources; dates become `YYYY-MM-DD`.
+fn parquet_field_to_json(field: &Field) -> JsonValue {
+    let timestamp = |dt: Option<DateTime<Utc>>| {
+        dt.map(|dt| JsonValue::String(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)))
+            .unwrap_or(JsonValue::Null)
+    };
+
+    match field {
+        Field::Null => JsonValue::Null,
+        Field::Bool(b) => JsonValue::Bool(*b),
+        Field::Int(i) => JsonValue::from(*i),
+        Field::Long(l) => JsonValue::from(*l),
+        Field::Float(f) => JsonValue::from(*f as f64),
+        Field::Double(d) => JsonValue::from(*d),
+        Field::Str(s) => JsonValue::String(s.clone()),
+        Field::TimestampMillis(ms) => timestamp(DateTime::from_timestamp_millis(*ms)),
+        Field::TimestampMicros(us) => timestamp(DateTime::from_timestamp_micros(*us)),
+        Field::Date(days) => NaiveDate::from_ymd_opt(1970, 1, 1)
+            .and_then(|epoch| epoch.checked_add_signed(chrono::Duration::days(*days as i64)))
+            .map(|d| JsonValue::String(d.format("%Y-%m-%d").to_string()))
+            .unwrap_or(JsonValue::Null),
+        other => other.to_json_value(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::{CommonMappingFields, Mode, SnowflakeConfig, SourceConfig};
     use anyhow::Result;
+    use serde_json::json;
 
-    /// Optional Snowflake connectivity smoke test.
-    ///
-    /// This test will only actually hit Snowflake if the following env vars are set:
-    /// - SNOWFLAKE_ACCOUNT
-    /// - SNOWFLAKE_USER
-    /// - SNOWFLAKE_PASSWORD
-    /// - SNOWFLAKE_WAREHOUSE
-    /// - SNOWFLAKE_DATABASE
-    /// - SNOWFLAKE_SCHEMA
-    ///
-    /// Otherwise it returns Ok(()) immediately so it doesn't fail in environments
-    /// without Snowflake credentials configured.
-    #[tokio::test]
-    async fn snowflake_connectivity_smoke_test() -> Result<()> {
-        let account = match std::env::var("SNOWFLAKE_ACCOUNT") {
-            Ok(v) => v,
-            Err(_) => return Ok(()),
+    fn write_temp_input(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).expect("failed to write temp input file");
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn paged_sql_orders_and_offsets() {
+        assert_eq!(
+            paged_sql("SELECT * FROM T", "UPDATED_AT", 500, 1500),
+            "SELECT * FROM T ORDER BY UPDATED_AT LIMIT 500 OFFSET 1500"
+        );
+    }
+
+    #[test]
+    fn select_watermark_placeholder_is_substituted() {
+        let select = "SELECT * FROM T WHERE UPDATED_AT > {{watermark}}";
+        assert_eq!(
+            substitute_watermark(select, Some("2024-05-01T00:00:00Z")),
+            "SELECT * FROM T WHERE UPDATED_AT > '2024-05-01T00:00:00Z'"
+        );
+        assert_eq!(
+            substitute_watermark(select, None),
+            "SELECT * FROM T WHERE UPDATED_AT > '1970-01-01'"
+        );
+        assert_eq!(
+            substitute_watermark(select, Some("it's")),
+            "SELECT * FROM T WHERE UPDATED_AT > 'it''s'"
+        );
+
+        let plain = "SELECT * FROM T WHERE A = '{watermark}'";
+        assert_eq!(substitute_watermark(plain, Some("2024-05-01")), plain);
+    }
+
+    #[test]
+    fn select_params_are_rejected_rather_than_inlined() {
+        let mut common = CommonMappingFields {
+            name: "orders".to_string(),
+            ..Default::default()
         };
-        let user = match std::env::var("SNOWFLAKE_USER") {
-            Ok(v) => v,
-            Err(_) => return Ok(()),
+        common.source.select = Some("SELECT * FROM ORDERS WHERE D >= :start_date".to_string());
+        common
+            .source
+            .params
+            .insert("start_date".to_string(), json!("2024-01-01"));
+        let message = format!("{:#}", build_sql(&common, None).unwrap_err());
+        assert!(message.contains("mapping 'orders'"), "{}", message);
+        assert!(message.contains("would only be inlined"), "{}", message);
+    }
+
+    #[test]
+    fn stream_consume_statements_touch_only_the_bookmark_table() {
+        assert_eq!(
+            stream_bookmark_ddl(),
+            "CREATE TEMPORARY TABLE IF NOT EXISTS SNOWFLAKE_TO_FALKORDB_STREAM_BOOKMARK \
+             (CONSUMED_AT TIMESTAMP_LTZ)"
+        );
+        assert_eq!(
+            stream_consume_sql("DB.PUBLIC.ORDERS_STREAM"),
+            "INSERT INTO SNOWFLAKE_TO_FALKORDB_STREAM_BOOKMARK SELECT CURRENT_TIMESTAMP() \
+             FROM DB.PUBLIC.ORDERS_STREAM WHERE 1 = 0"
+        );
+    }
+
+    #[test]
+    fn snowflake_read_pages_only_incremental_table_sources() -> Result<()> {
+        let mut common = CommonMappingFields {
+            delta: Some(serde_json::from_value(
+                json!({ "updated_at_column": "UPDATED_AT" }),
+            )?),
+            ..Default::default()
         };
-        let password = match std::env::var("SNOWFLAKE_PASSWORD") {
-            Ok(v) => v,
-            Err(_) => return Ok(()),
+        let mut sf_cfg = SnowflakeConfig {
+            fetch_batch_size: Some(500),
+            pagination: Pagination::Keyset,
+            ..Default::default()
         };
-        let warehouse = match std::env::var("SNOWFLAKE_WAREHOUSE") {
-            Ok(v) => v,
-            Err(_) => return Ok(()),
+
+        // Keyset paging without a tiebreaker falls back to offsets.
+        let sequential = SnowflakeRead::Offset {
+            order_by: "UPDATED_AT".to_string(),
+            batch_size: 500,
+        };
+        assert_eq!(snowflake_read(&sf_cfg, &common), sequential);
+        // So do concurrent pages, which need a deterministic order.
+        sf_cfg.fetch_concurrency = Some(4);
+        sf_cfg.pagination = Pagination::Offset;
+        assert_eq!(snowflake_read(&sf_cfg, &common), sequential);
+
+        if let Some(delta) = &mut common.delta {
+            delta.tiebreaker_column = Some("ID".to_string());
+        }
+        assert_eq!(
+            snowflake_read(&sf_cfg, &common),
+            SnowflakeRead::Concurrent {
+                order_by: "UPDATED_AT, ID".to_string(),
+                batch_size: 500,
+                concurrency: 4,
+            }
+        );
+        common.source.select = Some("SELECT * FROM T".to_string());
+        assert_eq!(snowflake_read(&sf_cfg, &common), SnowflakeRead::Single);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_pages_are_streamed_in_order() -> Result<()> {
+        // 25 rows in pages of 10; later pages finish first.
+        let fetchers: Vec<_> = (0..3)
+            .map(|_| {
+                |page: usize| async move {
+                    let delay = 30u64.saturating_sub(page as u64 * 10);
+                    tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                    Ok::<_, anyhow::Error>(
+                        (page * 10 + 1..=(page * 10 + 10).min(25))
+                            .map(|id| LogicalRow {
+                                values: json!({ "ID": id }).as_object().cloned().unwrap(),
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                }
+            })
+            .collect();
+
+        let (tx, mut rx) = mpsc::channel(1);
+        let reader = tokio::spawn(async move {
+            let mut ids = Vec::new();
+            while let Some(page) = rx.recv().await {
+                let page: Vec<LogicalRow> = page?;
+                ids.extend(page.iter().filter_map(|r| r.get("ID")?.as_u64()));
+            }
+            Ok::<_, anyhow::Error>(ids)
+        });
+        fetch_pages_concurrently(fetchers, 10, usize::MAX, &tx).await?;
+        drop(tx);
+        assert_eq!(reader.await??, (1..=25).collect::<Vec<u64>>());
+
+        // A failing page fails the fetch.
+        let failing = vec![|page: usize| async move {
+            if page == 1 {
+                Err(anyhow!("page 1 failed"))
+            } else {
+                Ok(vec![
+                    LogicalRow {
+                        values: JsonMap::new(),
+                    };
+                    10
+                ])
+            }
+        }];
+        let (tx, _rx) = mpsc::channel(8);
+        let err = fetch_pages_concurrently(failing, 10, usize::MAX, &tx)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "page 1 failed");
+        Ok(())
+    }
+
+    #[test]
+    fn keyset_sql_seeks_past_cursor() {
+        assert_eq!(
+            keyset_sql("SELECT * FROM T", "UPDATED_AT", "ID", None, 500),
+            "SELECT * FROM (SELECT * FROM T) ORDER BY UPDATED_AT, ID LIMIT 500"
+        );
+        let cursor = (json!("2024-01-01T00:00:00Z"), json!("o'k"));
+        assert_eq!(
+            keyset_sql("SELECT * FROM T", "UPDATED_AT", "ID", Some(&cursor), 500),
+            "SELECT * FROM (SELECT * FROM T) WHERE UPDATED_AT > '2024-01-01T00:00:00Z' \
+             OR (UPDATED_AT = '2024-01-01T00:00:00Z' AND ID > 'o''k') \
+             ORDER BY UPDATED_AT, ID LIMIT 500"
+        );
+    }
+
+    #[tokio::test]
+    async fn keyset_paging_keeps_rows_with_identical_timestamps() -> Result<()> {
+        // 25 rows, all with the same timestamp, stored out of key order.
+        let table: Vec<LogicalRow> = (1..=25)
+            .rev()
+            .map(|id| LogicalRow {
+                values: json!({ "UPDATED_AT": "2024-01-01T00:00:00Z", "ID": id })
+                    .as_object()
+                    .cloned()
+                    .unwrap(),
+            })
+            .collect();
+        let sort_key = |row: &LogicalRow| {
+            (
+                row.get("UPDATED_AT")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                row.get("ID").and_then(|v| v.as_i64()),
+            )
         };
-        let database = match std::env::var("SNOWFLAKE_DATABASE") {
-            Ok(v) => v,
-            Err(_) => return Ok(()),
+
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut queries = 0;
+        fetch_keyset_pages("UPDATED_AT", "ID", 10, &tx, |after| {
+            queries += 1;
+            let mut page: Vec<LogicalRow> = table
+                .iter()
+                .filter(|row| match &after {
+                    Some((ts, id)) => {
+                        sort_key(row) > (ts.as_str().map(str::to_string), id.as_i64())
+                    }
+                    None => true,
+                })
+                .cloned()
+                .collect();
+            page.sort_by_key(sort_key);
+            page.truncate(10);
+            async move { Ok(page) }
+        })
+        .await?;
+        drop(tx);
+
+        let mut rows = Vec::new();
+        while let Some(page) = rx.recv().await {
+            rows.extend(page?);
+        }
+        let ids: Vec<i64> = rows.iter().filter_map(|r| r.get("ID")?.as_i64()).collect();
+        assert_eq!(ids, (1..=25).collect::<Vec<_>>());
+        assert_eq!(queries, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keyset_paging_stops_when_the_stream_stops_reading() -> Result<()> {
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        let mut queries = 0;
+        fetch_keyset_pages("UPDATED_AT", "ID", 10, &tx, |after| {
+            queries += 1;
+            let start = after.and_then(|(_, id)| id.as_i64()).unwrap_or(0);
+            let page: Vec<LogicalRow> = (start + 1..=start + 10)
+                .map(|id| LogicalRow {
+                    values: serde_json::json!({ "UPDATED_AT": "2024-01-01", "ID": id })
+                        .as_object()
+                        .cloned()
+                        .unwrap(),
+                })
+                .collect();
+            async move { Ok(page) }
+        })
+        .await?;
+
+        assert_eq!(queries, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn auth_method_follows_key_pair_password_oauth_order() -> Result<()> {
+        let mut sf_cfg = SnowflakeConfig {
+            private_key_path: Some("/keys/rsa_key.p8".to_string()),
+            password: Some("secret".to_string()),
+            oauth_token: Some("token".to_string()),
+            ..Default::default()
         };
-        let schema = match std::env::var("SNOWFLAKE_SCHEMA") {
-            Ok(v) => v,
-            Err(_) => return Ok(()),
+        assert_eq!(
+            choose_auth(&sf_cfg)?,
+            AuthChoice::KeyPair("/keys/rsa_key.p8")
+        );
+        sf_cfg.private_key_path = None;
+        assert_eq!(choose_auth(&sf_cfg)?, AuthChoice::Password("secret"));
+        sf_cfg.password = None;
+        assert_eq!(choose_auth(&sf_cfg)?, AuthChoice::OAuth("token"));
+        sf_cfg.oauth_token = None;
+        assert!(choose_auth(&sf_cfg).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn key_passphrase_prefers_dedicated_field() {
+        let mut sf_cfg = SnowflakeConfig {
+            password: Some("legacy".to_string()),
+            private_key_path: Some("/keys/rsa_key.p8".to_string()),
+            ..Default::default()
         };
+        assert_eq!(key_passphrase(&sf_cfg), "legacy");
+        sf_cfg.private_key_passphrase = Some("dedicated".to_string());
+        assert_eq!(key_passphrase(&sf_cfg), "dedicated");
+        sf_cfg.password = None;
+        sf_cfg.private_key_passphrase = None;
+        assert_eq!(key_passphrase(&sf_cfg), "");
+    }
 
+    #[test]
+    fn mapping_query_timeout_overrides_global() {
         let sf_cfg = SnowflakeConfig {
+            account: "acc".to_string(),
+            user: "user".to_string(),
+            password: None,
+            private_key_path: None,
+            private_key_passphrase: None,
+            oauth_token: None,
+            warehouse: "wh".to_string(),
+            database: "db".to_string(),
+            schema: "public".to_string(),
+            role: None,
+            fetch_batch_size: None,
+            fetch_concurrency: None,
+            query_timeout_ms: Some(30_000),
+            pagination: Pagination::Offset,
+        };
+        let mut common = CommonMappingFields::default();
+        assert_eq!(
+            effective_query_timeout(&sf_cfg, &common),
+            Some(std::time::Duration::from_millis(30_000))
+        );
+
+        common.query_timeout_ms = Some(3_600_000);
+        assert_eq!(
+            effective_query_timeout(&sf_cfg, &common),
+            Some(std::time::Duration::from_secs(3_600))
+        );
+    }
+
+    #[test]
+    fn expired_sessions_are_reauthenticated_a_bounded_number_of_times() {
+        let expired = anyhow!(
+            "Communication error: 390112 (08001): Your session has expired. Please login again."
+        )
+        .context("Failed to fetch page");
+        assert!(should_reauthenticate(&expired, 0));
+        assert!(should_reauthenticate(
+            &expired,
+            SNOWFLAKE_REAUTH_ATTEMPTS - 1
+        ));
+        assert!(!should_reauthenticate(&expired, SNOWFLAKE_REAUTH_ATTEMPTS));
+
+        let token_expired =
+            anyhow!("Authentication token has expired.  The user must authenticate again.");
+        assert!(should_reauthenticate(&token_expired, 0));
+
+        // Other failures are returned as they are.
+        let syntax = anyhow!("SQL compilation error: syntax error line 1 at position 7");
+        assert!(!should_reauthenticate(&syntax, 0));
+        let timeout = anyhow!("Timed out waiting for query results");
+        assert!(!should_reauthenticate(&timeout, 0));
+    }
+
+    #[test]
+    fn load_rows_from_file_strips_bom() -> Result<()> {
+        let path = write_temp_input(
+            "snowflake_to_falkordb_bom.json",
+            "\u{feff}[{\"id\": 1, \"name\": \"Alice\"}]",
+        );
+        let rows = load_rows_from_file(&path)?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get("name"), Some(&JsonValue::from("Alice")));
+        Ok(())
+    }
+
+    #[test]
+    fn load_rows_from_file_tolerates_whitespace_and_trailing_comma() -> Result<()> {
+        let path = write_temp_input(
+            "snowflake_to_falkordb_trailing.json",
+            "\n  [\n  {\"id\": 1},\n  {\"id\": 2},\n]\n\n  \t",
+        );
+        let rows = load_rows_from_file(&path)?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].get("id"), Some(&JsonValue::from(2)));
+        Ok(())
+    }
+
+    #[test]
+    fn load_rows_from_jsonl_file_reads_one_object_per_line() -> Result<()> {
+        let path = write_temp_input(
+            "snowflake_to_falkordb_rows.jsonl",
+            "{\"id\": 1, \"name\": \"Alice\"}\n\n  {\"id\": 2, \"name\": \"Bob\"}\r\n",
+        );
+        let rows = load_rows_from_file(&path)?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].get("name"), Some(&JsonValue::from("Bob")));
+
+        let ndjson = parse_rows_from_bytes("export.NDJSON", b"{\"id\": 3}\n".to_vec())?;
+        assert_eq!(ndjson[0].get("id"), Some(&JsonValue::from(3)));
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_jsonl_line_is_reported_by_number() {
+        let path = write_temp_input(
+            "snowflake_to_falkordb_malformed.jsonl",
+            "{\"id\": 1}\n\n{\"id\": 2,}\n{\"id\": 3}\n",
+        );
+        let err = format!("{:#}", load_rows_from_file(&path).unwrap_err());
+        assert!(err.contains("line 3 of"), "{}", err);
+        assert!(err.contains("malformed.jsonl"), "{}", err);
+    }
+
+    fn file_mapping(file: &str) -> CommonMappingFields {
+        CommonMappingFields {
+            name: "customers".to_string(),
+            source: SourceConfig {
+                file: Some(file.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn large_jsonl_file_is_streamed_in_bounded_chunks() -> Result<()> {
+        let lines: String = (1..=2500)
+            .map(|id| format!("{{\"id\": {}}}\n", id))
+            .collect();
+        let path = write_temp_input("snowflake_to_falkordb_large.jsonl", &lines);
+        let common = file_mapping(&path);
+
+        let mut cfg = Config::default();
+        let mut stream = open_row_stream(&cfg, &common, None, 1000).await?;
+        let mut chunk_sizes = Vec::new();
+        let mut last_id = 0;
+        while let Some(chunk) = stream.next_chunk().await? {
+            chunk_sizes.push(chunk.len());
+            last_id = chunk.last().and_then(|r| r.get("id")?.as_i64()).unwrap();
+        }
+        assert_eq!(chunk_sizes, vec![1000, 1000, 500]);
+        assert_eq!(last_id, 2500);
+        assert_eq!(stream.fetched(), 2500);
+
+        // The limit cuts the stream short mid-chunk.
+        cfg.max_rows_per_mapping = Some(1500);
+        cfg.max_rows_action = MaxRowsAction::Truncate;
+        let mut stream = open_row_stream(&cfg, &common, None, 1000).await?;
+        let mut chunk_sizes = Vec::new();
+        while let Some(chunk) = stream.next_chunk().await? {
+            chunk_sizes.push(chunk.len());
+        }
+        assert_eq!(chunk_sizes, vec![1000, 500]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn max_rows_per_mapping_truncates_or_fails_file_source() -> Result<()> {
+        let path = write_temp_input(
+            "snowflake_to_falkordb_max_rows.json",
+            r#"[{"id": 1}, {"id": 2}, {"id": 3}, {"id": 4}, {"id": 5}]"#,
+        );
+        let common = file_mapping(&path);
+        let mut cfg = Config {
+            max_rows_per_mapping: Some(3),
+            max_rows_action: MaxRowsAction::Truncate,
+            ..Default::default()
+        };
+
+        let rows = fetch_rows_for_mapping(&cfg, &common, None).await?;
+        let ids: Vec<i64> = rows.iter().filter_map(|r| r.get("id")?.as_i64()).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        cfg.max_rows_action = MaxRowsAction::Fail;
+        let err = fetch_rows_for_mapping(&cfg, &common, None)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("max_rows_per_mapping (3)"), "{}", err);
+
+        cfg.max_rows_per_mapping = Some(5);
+        assert_eq!(fetch_rows_for_mapping(&cfg, &common, None).await?.len(), 5);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn glob_source_reads_every_match_in_sorted_order() -> Result<()> {
+        let dir = std::env::temp_dir().join("snowflake_to_falkordb_glob");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("customers_2024-01-02.json"), r#"[{"id": 3}]"#)?;
+        std::fs::write(
+            dir.join("customers_2024-01-01.json"),
+            r#"[{"id": 1}, {"id": 2}]"#,
+        )?;
+        std::fs::write(dir.join("orders_2024-01-01.json"), r#"[{"id": 99}]"#)?;
+
+        let cfg = Config::default();
+        let pattern = dir.join("customers_*.json");
+        let glob_mapping = file_mapping(&pattern.to_string_lossy());
+        let rows = fetch_rows_for_mapping(&cfg, &glob_mapping, None).await?;
+        let ids: Vec<_> = rows.iter().filter_map(|r| r.get("id")).cloned().collect();
+        assert_eq!(ids, vec![json!(1), json!(2), json!(3)]);
+
+        // A plain path is still read as a single file.
+        let single_mapping = file_mapping(&dir.join("orders_2024-01-01.json").to_string_lossy());
+        let single = fetch_rows_for_mapping(&cfg, &single_mapping, None).await?;
+        assert_eq!(single.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn glob_source_without_matches_is_an_error() {
+        let pattern = std::env::temp_dir().join("snowflake_to_falkordb_no_such_*.json");
+        let err = source_file_paths(&pattern.to_string_lossy())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("matched no files"), "{}", err);
+    }
+
+    #[test]
+    fn load_rows_from_parquet_converts_types() -> Result<()> {
+        use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        let path = std::env::temp_dir().join("snowflake_to_falkordb_rows.parquet");
+        let schema = Arc::new(parse_message_type(
+            "message row {
+                REQUIRED INT64 id;
+                REQUIRED DOUBLE score;
+                REQUIRED BOOLEAN active;
+                OPTIONAL BYTE_ARRAY name (UTF8);
+                REQUIRED INT64 updated_at (TIMESTAMP(MILLIS,true));
+            }",
+        )?);
+
+        let mut writer =
+            SerializedFileWriter::new(fs::File::create(&path)?, schema, Default::default())?;
+        let mut row_group = writer.next_row_group()?;
+
+        let mut col = row_group.next_column()?.expect("id column");
+        col.typed::<Int64Type>().write_batch(&[1, 2], None, None)?;
+        col.close()?;
+        let mut col = row_group.next_column()?.expect("score column");
+        col.typed::<DoubleType>()
+            .write_batch(&[0.5, 2.25], None, None)?;
+        col.close()?;
+        let mut col = row_group.next_column()?.expect("active column");
+        col.typed::<BoolType>()
+            .write_batch(&[true, false], None, None)?;
+        col.close()?;
+        let mut col = row_group.next_column()?.expect("name column");
+        // Second row's name is null (definition level 0).
+        col.typed::<ByteArrayType>().write_batch(
+            &[ByteArray::from("Alice")],
+            Some(&[1, 0]),
+            None,
+        )?;
+        col.close()?;
+        let mut col = row_group.next_column()?.expect("updated_at column");
+        col.typed::<Int64Type>().write_batch(
+            &[1_704_067_200_000, 1_704_153_600_500],
+            None,
+            None,
+        )?;
+        col.close()?;
+
+        row_group.close()?;
+        writer.close()?;
+
+        let rows = load_rows_from_file(&path.to_string_lossy())?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get("id"), Some(&JsonValue::from(1)));
+        assert_eq!(rows[0].get("score"), Some(&JsonValue::from(0.5)));
+        assert_eq!(rows[0].get("active"), Some(&JsonValue::Bool(true)));
+        assert_eq!(rows[0].get("name"), Some(&JsonValue::from("Alice")));
+        assert_eq!(rows[1].get("name"), Some(&JsonValue::Null));
+        assert_eq!(
+            rows[0].get("updated_at"),
+            Some(&JsonValue::from("2024-01-01T00:00:00Z"))
+        );
+        assert_eq!(
+            rows[1].get("updated_at"),
+            Some(&JsonValue::from("2024-01-02T00:00:00.500Z"))
+        );
+        Ok(())
+    }
+
+    /// Snowflake settings from the `SNOWFLAKE_*` environment, or `None` to skip live tests.
+    fn snowflake_env_config() -> Option<SnowflakeConfig> {
+        let account = std::env::var("SNOWFLAKE_ACCOUNT").ok()?;
+        let user = std::env::var("SNOWFLAKE_USER").ok()?;
+        let password = std::env::var("SNOWFLAKE_PASSWORD").ok()?;
+        let warehouse = std::env::var("SNOWFLAKE_WAREHOUSE").ok()?;
+        let database = std::env::var("SNOWFLAKE_DATABASE").ok()?;
+        let schema = std::env::var("SNOWFLAKE_SCHEMA").ok()?;
+
+        Some(SnowflakeConfig {
             account,
             user,
             password: Some(password),
             private_key_path: None,
+            private_key_passphrase: None,
+            oauth_token: None,
             warehouse,
             database,
             schema,
             role: None,
             fetch_batch_size: None,
+            fetch_concurrency: None,
             query_timeout_ms: Some(10_000),
+            pagination: Pagination::Offset,
+        })
+    }
+
+    #[test]
+    fn snowflake_cells_keep_their_column_types() {
+        let cases = [
+            ("fixed", Some(0), Some("42"), json!(42)),
+            ("fixed", Some(0), Some("-7"), json!(-7)),
+            ("fixed", Some(2), Some("12.50"), json!(12.5)),
+            ("real", None, Some("1.5e3"), json!(1500.0)),
+            ("boolean", None, Some("1"), json!(true)),
+            ("boolean", None, Some("false"), json!(false)),
+            ("text", None, Some("123"), json!("123")),
+            (
+                "timestamp_ntz",
+                None,
+                Some("1704164645.500000000"),
+                json!("2024-01-02T03:04:05.500Z"),
+            ),
+            (
+                "timestamp_ltz",
+                None,
+                Some("1704164645"),
+                json!("2024-01-02T03:04:05Z"),
+            ),
+            (
+                "timestamp_tz",
+                None,
+                Some("1704164645.000000000 1500"),
+                json!("2024-01-02T03:04:05Z"),
+            ),
+            (
+                "timestamp_ntz",
+                None,
+                Some("-1.25"),
+                json!("1969-12-31T23:59:58.750Z"),
+            ),
+            ("date", None, Some("19724"), json!("2024-01-02")),
+            ("time", None, Some("3723.5"), json!("01:02:03.500")),
+            (
+                "variant",
+                None,
+                Some("{\"a\": [1, 2]}"),
+                json!({ "a": [1, 2] }),
+            ),
+            ("fixed", None, None, JsonValue::Null),
+            // Values that don't fit their type fall back to strings.
+            (
+                "fixed",
+                Some(0),
+                Some("99999999999999999999999"),
+                json!("99999999999999999999999"),
+            ),
+            ("boolean", None, Some("maybe"), json!("maybe")),
+            ("geography", None, Some("POINT(1 2)"), json!("POINT(1 2)")),
+        ];
+        for (snowflake_type, scale, raw, expected) in cases {
+            assert_eq!(
+                snowflake_cell_to_json(snowflake_type, scale, raw),
+                expected,
+                "{} {:?}",
+                snowflake_type,
+                raw
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn snowflake_rows_keep_numeric_boolean_and_timestamp_types() -> Result<()> {
+        let Some(sf_cfg) = snowflake_env_config() else {
+            return Ok(());
+        };
+        let common = CommonMappingFields {
+            name: "snowflake_typed_columns".to_string(),
+            source: SourceConfig {
+                select: Some(
+                    "SELECT 42 AS N, 12.5::NUMBER(10, 2) AS D, 1.5::FLOAT AS F, TRUE AS B, \
+                     '123' AS S, '2024-01-02 03:04:05'::TIMESTAMP_NTZ AS TS, NULL AS Z"
+                        .to_string(),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let cfg = Config {
+            snowflake: Some(sf_cfg),
+            ..Default::default()
+        };
+
+        let rows = fetch_rows_for_mapping(&cfg, &common, None).await?;
+        let row = &rows[0];
+        assert_eq!(row.get("N"), Some(&json!(42)));
+        assert_eq!(row.get("D"), Some(&json!(12.5)));
+        assert_eq!(row.get("F"), Some(&json!(1.5)));
+        assert_eq!(row.get("B"), Some(&json!(true)));
+        assert_eq!(row.get("S"), Some(&json!("123")));
+        assert_eq!(row.get("TS"), Some(&json!("2024-01-02T03:04:05Z")));
+        assert_eq!(row.get("Z"), Some(&JsonValue::Null));
+        Ok(())
+    }
+
+    /// Optional Snowflake connectivity smoke test.
+    ///
+    /// This test will only actually hit Snowflake if the following env vars are set:
+    /// - SNOWFLAKE_ACCOUNT
+    /// - SNOWFLAKE_USER
+    /// - SNOWFLAKE_PASSWORD
+    /// - SNOWFLAKE_WAREHOUSE
+    /// - SNOWFLAKE_DATABASE
+    /// - SNOWFLAKE_SCHEMA
+    ///
+    /// Otherwise it returns Ok(()) immediately so it doesn't fail in environments
+    /// without Snowflake credentials configured.
+    #[tokio::test]
+    async fn snowflake_connectivity_smoke_test() -> Result<()> {
+        let Some(sf_cfg) = snowflake_env_config() else {
+            return Ok(());
         };
 
         let common = CommonMappingFields {
@@ -314,12 +2094,20 @@ mod tests {
                 stream: None,
                 select: Some("SELECT 1 AS ONE".to_string()),
                 r#where: None,
+                s3: None,
+                stream_consume: false,
+                params: Default::default(),
             },
             mode: Mode::Full,
             delta: None,
+            ..Default::default()
         };
 
-        let rows = fetch_rows_from_snowflake(&sf_cfg, &common, None).await?;
+        let cfg = Config {
+            snowflake: Some(sf_cfg),
+            ..Default::default()
+        };
+        let rows = fetch_rows_for_mapping(&cfg, &common, None).await?;
         assert!(!rows.is_empty());
         Ok(())
     }
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+use crate::config::TransformConfig;
+use crate::source::LogicalRow;
+
+/// A rewrite of fetched rows that the declarative mapping can't express, applied to each row
+/// after fetching and before mapping.
+pub trait RowTransform: Send + Sync {
+    fn apply(&self, row: &mut LogicalRow) -> Result<()>;
+}
+
+/// Builds a transform from its `transforms` entry, failing if the entry is unusable (e.g. a
+/// missing `columns` list). Called once per entry during config validation and once per fetch.
+pub type TransformFactory =
+    Arc<dyn Fn(&TransformConfig) -> Result<Box<dyn RowTransform>> + Send + Sync>;
+
+/// Names accepted in a mapping's `transforms` list: the built-ins, then anything added with
+/// [`register_transform`].
+static TRANSFORMS: Lazy<RwLock<BTreeMap<String, TransformFactory>>> =
+    Lazy::new(|| RwLock::new(builtin_transforms()));
+
+fn builtin<F>(f: F) -> TransformFactory
+where
+    F: Fn(&TransformConfig) -> Result<Box<dyn RowTransform>> + Send + Sync + 'static,
+{
+    Arc::new(f)
+}
+
+fn builtin_transforms() -> BTreeMap<String, TransformFactory> {
+    let mut builtins: BTreeMap<String, TransformFactory> = BTreeMap::new();
+    builtins.insert(
+        "lowercase".to_string(),
+        builtin(|cfg| {
+            Ok(Box::new(StringTransform {
+                columns: cfg.columns.clone(),
+                f: str::to_lowercase,
+            }))
+        }),
+    );
+    builtins.insert(
+        "trim".to_string(),
+        builtin(|cfg| {
+            Ok(Box::new(StringTransform {
+                columns: cfg.columns.clone(),
+                f: |s| s.trim().to_string(),
+            }))
+        }),
+    );
+    builtins.insert(
+        "sha256".to_string(),
+        builtin(|cfg| {
+            if cfg.columns.is_empty() {
+                return Err(anyhow!("transform 'sha256' needs a columns list"));
+            }
+            Ok(Box::new(Sha256Transform {
+                columns: cfg.columns.clone(),
+            }))
+        }),
+    );
+    builtins
+}
+
+/// Make a custom transform available to `transforms` entries under `name`. Register before
+/// validating the config that uses it. A name can only be registered once, and the built-ins
+/// can't be replaced.
+pub fn register_transform<F>(name: &str, factory: F) -> Result<()>
+where
+    F: Fn(&TransformConfig) -> Result<Box<dyn RowTransform>> + Send + Sync + 'static,
+{
+    let mut transforms = TRANSFORMS
+        .write()
+        .map_err(|_| anyhow!("transform registry is poisoned"))?;
+    if transforms.contains_key(name) {
+        return Err(anyhow!("transform '{}' is already registered", name));
+    }
+    transforms.insert(name.to_string(), Arc::new(factory));
+    Ok(())
+}
+
+/// Problem with a transform entry, if any, for config validation: an unknown name, or an
+/// entry its transform can't be built from.
+pub fn transform_problem(cfg: &TransformConfig) -> Option<String> {
+    build_transform(cfg).err().map(|e| e.to_string())
+}
+
+/// Build the transform a `transforms` entry names.
+pub fn build_transform(cfg: &TransformConfig) -> Result<Box<dyn RowTransform>> {
+    let factory = {
+        let transforms = TRANSFORMS
+            .read()
+            .map_err(|_| anyhow!("transform registry is poisoned"))?;
+        match transforms.get(&cfg.name) {
+            Some(factory) => Arc::clone(factory),
+            None => {
+                return Err(anyhow!(
+                    "unknown transform '{}' (expected one of: {})",
+                    cfg.name,
+                    transforms.keys().cloned().collect::<Vec<_>>().join(", ")
+                ))
+            }
+        }
+    };
+    factory(cfg)
+}
+
+/// Run a mapping's transforms, in order, over every row.
+pub fn apply_transforms(configs: &[TransformConfig], rows: &mut [LogicalRow]) -> Result<()> {
+    if configs.is_empty() {
+        return Ok(());
+    }
+    let transforms = configs
+        .iter()
+        .map(build_transform)
+        .collect::<Result<Vec<_>>>()?;
+    for row in rows.iter_mut() {
+        for transform in &transforms {
+            transform.apply(row)?;
+        }
+    }
+    Ok(())
+}
+
+/// Values of `columns` in `row`, or every value when `columns` is empty. Missing columns are
+/// skipped.
+fn selected_values<'a>(
+    row: &'a mut LogicalRow,
+    columns: &'a [String],
+) -> Box<dyn Iterator<Item = &'a mut JsonValue> + 'a> {
+    if columns.is_empty() {
+        Box::new(row.values.values_mut())
+    } else {
+        Box::new(
+            row.values
+                .iter_mut()
+                .filter(|(name, _)| columns.contains(*name))
+                .map(|(_, value)| value),
+        )
+    }
+}
+
+/// Rewrites string values; other values are left alone.
+struct StringTransform {
+    columns: Vec<String>,
+    f: fn(&str) -> String,
+}
+
+impl RowTransform for StringTransform {
+    fn apply(&self, row: &mut LogicalRow) -> Result<()> {
+        for value in selected_values(row, &self.columns) {
+            if let JsonValue::String(s) = value {
+                *s = (self.f)(s);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replaces values with the lowercase hex SHA-256 of their text (numbers and booleans as
+/// written in JSON). Nulls stay null so optional columns remain optional.
+struct Sha256Transform {
+    columns: Vec<String>,
+}
+
+impl RowTransform for Sha256Transform {
+    fn apply(&self, row: &mut LogicalRow) -> Result<()> {
+        for value in selected_values(row, &self.columns) {
+            let text = match value {
+                JsonValue::Null => continue,
+                JsonValue::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            *value = JsonValue::String(format!("{:x}", Sha256::digest(text.as_bytes())));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(value: JsonValue) -> LogicalRow {
+        LogicalRow {
+            values: value.as_object().cloned().unwrap(),
+        }
+    }
+
+    fn transform(name: &str, columns: &[&str]) -> TransformConfig {
+        TransformConfig {
+            name: name.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn sha256_hashes_the_column_value() -> Result<()> {
+        let mut rows = vec![row(
+            json!({ "EMAIL": "alice@example.com", "ID": 7, "PHONE": null, "NAME": "Alice" }),
+        )];
+        apply_transforms(&[transform("sha256", &["EMAIL", "ID", "PHONE"])], &mut rows)?;
+
+        assert_eq!(
+            rows[0].get("EMAIL"),
+            Some(&json!(
+                "ff8d9819fc0e12bf0d24892e45987e249a28dce836a85cad60e28eaaa8c6d976"
+            ))
+        );
+        assert_eq!(
+            rows[0].get("ID"),
+            Some(&json!(
+                "7902699be42c8a8e46fbbb4501726517e86b22c56a189f7625a6da49081b2451"
+            ))
+        );
+        assert_eq!(rows[0].get("PHONE"), Some(&JsonValue::Null));
+        assert_eq!(rows[0].get("NAME"), Some(&json!("Alice")));
+        Ok(())
+    }
+
+    #[test]
+    fn transforms_run_in_order_over_selected_columns() -> Result<()> {
+        let mut rows = vec![row(
+            json!({ "EMAIL": "  Bob@X.com ", "CODE": " AB ", "N": 1 }),
+        )];
+        apply_transforms(
+            &[transform("trim", &[]), transform("lowercase", &["EMAIL"])],
+            &mut rows,
+        )?;
+        assert_eq!(rows[0].get("EMAIL"), Some(&json!("bob@x.com")));
+        assert_eq!(rows[0].get("CODE"), Some(&json!("AB")));
+        assert_eq!(rows[0].get("N"), Some(&json!(1)));
+        Ok(())
+    }
+
+    /// Prefixes string values with a fixed tag taken from the transform's first column.
+    struct TagTransform {
+        tag: String,
+    }
+
+    impl RowTransform for TagTransform {
+        fn apply(&self, row: &mut LogicalRow) -> Result<()> {
+            for value in row.values.values_mut() {
+                if let JsonValue::String(s) = value {
+                    *s = format!("{}:{}", self.tag, s);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn registered_transforms_are_built_by_name() -> Result<()> {
+        register_transform("test_tag", |cfg| {
+            let tag = cfg
+                .columns
+                .first()
+                .ok_or_else(|| anyhow!("transform 'test_tag' needs a tag"))?;
+            Ok(Box::new(TagTransform { tag: tag.clone() }))
+        })?;
+
+        let mut rows = vec![row(json!({ "NAME": "Alice", "N": 1 }))];
+        apply_transforms(&[transform("test_tag", &["vip"])], &mut rows)?;
+        assert_eq!(rows[0].get("NAME"), Some(&json!("vip:Alice")));
+        assert_eq!(rows[0].get("N"), Some(&json!(1)));
+
+        assert_eq!(
+            transform_problem(&transform("test_tag", &[])).as_deref(),
+            Some("transform 'test_tag' needs a tag")
+        );
+        assert!(register_transform("sha256", |_| Err(anyhow!("no")))
+            .unwrap_err()
+            .to_string()
+            .contains("already registered"));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_or_incomplete_transforms_are_rejected() {
+        assert!(transform_problem(&transform("rot13", &[]))
+            .unwrap()
+            .contains("unknown transform 'rot13'"));
+        assert_eq!(
+            transform_problem(&transform("sha256", &[])).as_deref(),
+            Some("transform 'sha256' needs a columns list")
+        );
+        assert!(transform_problem(&transform("trim", &[])).is_none());
+    }
+}
@@ -0,0 +1,79 @@
+//! The loader used as a library, with its config built in code rather than parsed.
+
+use anyhow::Result;
+use snowflake_to_falkordb::{
+    dry_run, run_once, validate_config, CommonMappingFields, Config, EntityMapping, FalkorConfig,
+    NodeKeySpec, NodeMappingConfig, RunOptions, SourceConfig, StateBackendKind, StateConfig,
+};
+
+fn file_config(endpoint: &str, input: &str) -> Config {
+    Config {
+        falkordb: FalkorConfig {
+            endpoint: endpoint.to_string(),
+            graph: "snowflake_to_falkordb_embed_test".to_string(),
+            ..Default::default()
+        },
+        state: Some(StateConfig {
+            backend: StateBackendKind::None,
+            ..Default::default()
+        }),
+        mappings: vec![EntityMapping::Node(NodeMappingConfig {
+            common: CommonMappingFields {
+                name: "embedded_people".to_string(),
+                source: SourceConfig {
+                    file: Some(input.to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            labels: vec!["EmbeddedPerson".to_string()],
+            key: NodeKeySpec {
+                column: "ID".to_string(),
+                property: "id".to_string(),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })],
+        ..Default::default()
+    }
+}
+
+fn write_input(name: &str) -> Result<String> {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, r#"[{"ID": 1}, {"ID": 2}, {"ID": 3}]"#)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[tokio::test]
+async fn config_built_in_code_runs_through_the_pipeline() -> Result<()> {
+    let input = write_input("snowflake_to_falkordb_embed_dry_run.json")?;
+    // Nothing listens on port 1; the dry run never connects.
+    let cfg = file_config("falkor://127.0.0.1:1", &input);
+    validate_config(&cfg)?;
+
+    let log = dry_run(&cfg, &RunOptions::default()).await?;
+    assert_eq!((log.statements, log.rows), (2, 3));
+    Ok(())
+}
+
+/// Optional end-to-end load through the library API.
+///
+/// Requires FALKORDB_ENDPOINT; otherwise it is a no-op.
+#[tokio::test]
+async fn run_once_loads_a_file_source() -> Result<()> {
+    let endpoint = match std::env::var("FALKORDB_ENDPOINT") {
+        Ok(v) => v,
+        Err(_) => return Ok(()),
+    };
+    let input = write_input("snowflake_to_falkordb_embed_run.json")?;
+    let cfg = file_config(&endpoint, &input);
+
+    let opts = RunOptions {
+        purge_graph: true,
+        ..Default::default()
+    };
+    let summary = run_once(&cfg, &opts).await?.into_result()?;
+    assert_eq!((summary.rows_fetched, summary.rows_written), (3, 3));
+    Ok(())
+}